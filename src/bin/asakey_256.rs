@@ -1,16 +1,15 @@
 #![warn(missing_docs)]
 #![allow(non_snake_case)]
-#![feature(rustdoc_missing_doc_code_examples)]
 
 //! Asakey demo with 256-bit state.
 //!
 //! Demonstration for using the Asakey construction of [DMP2022](https://doi.org/10.1145/3548606.3560635),
 //! with an inner state of 64x4 bits.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 use rand::{Rng, thread_rng};
-use CryptoTools::stream::asakey::Asakey;
+use CryptoTools::stream::asakey::{Asakey, AsakeyParams};
 use CryptoTools::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
 
 /// DSS demonstration with 256-bit state.
@@ -29,8 +28,7 @@ fn main() -> Result<(), Error>{
     } // Example using the SipHash permutation
 
     // Setup
-    let (b, r, k): (usize, usize, usize) = (256, 16, 32);
-    let mut asakey = Asakey::<Ux4::<u64>>::new(vec![b, r, k], perm)?;
+    let mut asakey = Asakey::<Ux4::<u64>>::new(AsakeyParams::asakey_256(), perm)?;
     println!("Asakey parameters: b = {}, r = {}, k = {}", asakey.b(), asakey.r(), asakey.k());
 
     // Generate key
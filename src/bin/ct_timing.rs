@@ -0,0 +1,123 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Constant-time timing test demo.
+//!
+//! Statistical timing test in the style of [dudect](https://eprint.iacr.org/2016/1123):
+//! the running times of a function are measured on two classes of inputs
+//! (fixed input equal to the secret, and random inputs), and compared with Welch's t-test.
+//! A t-statistic above 4.5 in absolute value indicates a timing leak.
+//! The early-exit comparison of slices is included as a reference that should leak.
+//! Best run in release mode: `cargo run --release --bin ct_timing`.
+
+use CryptoTools::error::Error;
+use std::hint::black_box;
+use std::time::Instant;
+use rand::{Rng, thread_rng};
+
+use CryptoTools::utilities::{ustates::Ux4, ct::{ct_eq, ConstantTime}};
+
+/// Number of measurements per test.
+const NB_MEASUREMENTS: usize = 200_000;
+
+/// Number of calls per measurement, to get above the timer resolution.
+const NB_CALLS: usize = 16;
+
+/// Threshold on |t| above which a leak is reported.
+const THRESHOLD: f64 = 4.5;
+
+/// Size of the compared slices in bytes.
+const SIZE: usize = 512;
+
+/// Welford accumulator for the mean and variance of one class.
+#[derive(Default)]
+struct Class {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Class {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.n - 1.0)
+    }
+}
+
+/// Welch's t-statistic between two classes.
+fn welch_t(a: &Class, b: &Class) -> f64 {
+    (a.mean - b.mean) / (a.variance() / a.n + b.variance() / b.n).sqrt()
+}
+
+/// Measure `f` on fixed and random inputs, and return the t-statistic.
+/// Measurements above the 90th percentile are cropped, as in dudect.
+fn measure<T>(fixed: &T, random: impl Fn() -> T, f: impl Fn(&T) -> bool) -> f64
+where
+    T: Clone
+{
+    let mut rng = thread_rng();
+
+    // Inputs are prepared beforehand, so that only `f` runs between the timer calls
+    let inputs: Vec<(bool, T)> = (0..NB_MEASUREMENTS)
+        .map(|_| {
+            let class = rng.gen::<bool>();
+            (class, if class {random()} else {fixed.clone()})
+        })
+        .collect();
+
+    let samples: Vec<(bool, f64)> = inputs.iter()
+        .map(|(class, input)| {
+            let start = Instant::now();
+            for _ in 0..NB_CALLS {
+                black_box(f(black_box(input)));
+            }
+            (*class, start.elapsed().as_nanos() as f64)
+        })
+        .collect();
+
+    let mut times: Vec<f64> = samples.iter().map(|&(_, t)| t).collect();
+    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let cutoff = times[times.len() * 9 / 10];
+
+    let (mut fixed_class, mut random_class) = (Class::default(), Class::default());
+    for (class, time) in samples.into_iter().filter(|&(_, t)| t <= cutoff) {
+        if class {random_class.push(time)} else {fixed_class.push(time)}
+    }
+    welch_t(&fixed_class, &random_class)
+}
+
+/// Print the result of one test.
+fn report(name: &str, t: f64) {
+    let verdict = if t.abs() > THRESHOLD {"LEAK"} else {"no leak detected"};
+    println!("{:<32}\tt = {:>8.2}\t{}", name, t, verdict);
+}
+
+/// Constant-time timing test demonstration.
+fn main() -> Result<(), Error>{
+    println!("\n################\n# Crypto Tools #\n################\n");
+    let execution_start = Instant::now();
+    println!("# Timing Test, {} measurements of {} calls, threshold |t| > {}\n", NB_MEASUREMENTS, NB_CALLS, THRESHOLD);
+
+    let mut rng = thread_rng();
+
+    // Byte slices
+    let secret: Vec<u8> = (0..SIZE).map(|_| rng.gen()).collect();
+    let random_bytes = || -> Vec<u8> {(0..SIZE).map(|_| thread_rng().gen()).collect()};
+    report("slice == (early exit)", measure(&secret, random_bytes, |x| x.as_slice() == secret.as_slice()));
+    report("ct_eq on slices", measure(&secret, random_bytes, |x| ct_eq(x, &secret)));
+
+    // States
+    let key = rng.gen::<Ux4<u64>>();
+    let random_state = || thread_rng().gen::<Ux4<u64>>();
+    report("Ux4::ct_eq", measure(&key, random_state, |x| x.ct_eq(&key)));
+    report("Ux4::ct_is_zero", measure(&Ux4::zero(), random_state, |x| x.ct_is_zero()));
+
+    println!("\n-> Total execution time: {:.2?}", execution_start.elapsed());
+    Ok(())
+}
@@ -1,16 +1,15 @@
 #![warn(missing_docs)]
 #![allow(non_snake_case)]
-#![feature(rustdoc_missing_doc_code_examples)]
 
 //! DSS demo with 256-bit state.
 //!
 //! Demonstration for using the DSS construction of [TODO],
 //! with an inner state of 64x4 bits.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 use rand::{Rng, thread_rng};
-use CryptoTools::stream::dss::DSS;
+use CryptoTools::stream::dss::{DSS, DSSParams};
 use CryptoTools::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
 
 /// DSS demonstration with 256-bit state.
@@ -29,8 +28,7 @@ fn main() -> Result<(), Error>{
     } // Example using the SipHash permutation
 
     // Setup
-    let (b, r, k): (usize, usize, usize) = (256, 32, 32);
-    let mut double_sponge = DSS::<Ux4::<u64>>::new(vec![b, r, k], perm)?;
+    let mut double_sponge = DSS::<Ux4::<u64>>::new(DSSParams::dss_256(), perm)?;
     println!("DSS parameters: b = {}, r = {}, k = {}",
         double_sponge.b(), double_sponge.r(), double_sponge.k());
 
@@ -1,16 +1,15 @@
 #![warn(missing_docs)]
 #![allow(non_snake_case)]
-#![feature(rustdoc_missing_doc_code_examples)]
 
 //! Duplex demo with 256-bit state.
 //!
 //! Demonstration for using the Duplex construction of [DM2019](https://doi.org/10.1007/978-3-030-34618-8_8),
 //! with an inner state of 64x4 bits.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 use rand::{Rng, thread_rng};
-use CryptoTools::other::duplex::Duplex;
+use CryptoTools::other::duplex::{Duplex, DuplexParams};
 use CryptoTools::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
 
 /// Duplex demonstration with 256-bit state.
@@ -27,18 +26,16 @@ fn main() -> Result<(), Error>{
     } // Example using the SipHash permutation
 
     // Define parameters
-    let (b, r, k, u, alpha) = (256, 32, 32, 3, 17);
     let nb_rounds: usize = 3;
     let nb_calls: usize = 128;
     let flag = true;
 
     // Setup
-    let mut duplex = Duplex::new(vec!(b, r, k, u, alpha), perm)?;
+    let mut duplex = Duplex::new(DuplexParams::duplex_256(), perm)?;
 
-    let mut delta = 0;
-    for i in 0..nb_rounds {
+    for (delta, i) in (0..nb_rounds).enumerate() {
         // Reset
-        duplex.reset(delta);
+        duplex.reset(delta)?;
 
         // Next
         let mut input: Ux4::<u64>;
@@ -47,12 +44,10 @@ fn main() -> Result<(), Error>{
         print!("Round {}: ", i);
         for _ in 0..nb_calls {
             input = rng.gen::<Ux4::<u64>>();
-            output = duplex.duplex(flag, input);
+            output = duplex.duplex(flag, input)?;
             print!("{:X}", output.0[0]);
         }
         println!("\n");
-
-        delta += 1;
     }
 
     println!("-> Total execution time: {:.2?}", execution_start.elapsed());
@@ -0,0 +1,80 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Statistical test battery demo.
+//!
+//! Runs the NIST SP 800-22 tests on the outputs of the SPRNG of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4)
+//! and on the keystreams of Asakey and DSS, showing that the rotation-only permutation
+//! of the 64-bit SPRNG demo is detected as non-random.
+
+use CryptoTools::error::Error;
+use std::time::Instant;
+use rand::{Rng, thread_rng};
+
+use CryptoTools::prng::{PRNG, sprng::{SPRNG, SPRNGParams}};
+use CryptoTools::stream::{asakey::{Asakey, AsakeyParams}, dss::{DSS, DSSParams}};
+use CryptoTools::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
+use CryptoTools::utilities::{bitops::urot, bitbuf::{BitBuf, BitOrder}, sts::{run_all, prng_bits, TestResult}};
+
+/// Significance level.
+const ALPHA: f64 = 0.01;
+
+/// Number of bits per sequence.
+const NB_BITS: usize = 1 << 17;
+
+/// Print the results of the battery for one generator.
+fn report(name: &str, results: &[TestResult]) {
+    println!("## {}", name);
+    for result in results {
+        let p_values: Vec<String> = result.p_values.iter().map(|p| format!("{:.6}", p)).collect();
+        let status = if result.passed(ALPHA) {"PASS"} else {"FAIL"};
+        println!("{:<20}\t{}\t{}", result.name, status, p_values.join(" "));
+    }
+    println!();
+}
+
+/// Statistical test battery demonstration.
+fn main() -> Result<(), Error>{
+    println!("\n################\n# Crypto Tools #\n################\n");
+    let execution_start = Instant::now();
+    println!("# Statistical Test Battery, {} bits per sequence, alpha = {}\n", NB_BITS, ALPHA);
+
+    let mut rng = thread_rng();
+
+    // Define permutations
+    let rot_17: fn(u64) -> u64 = move |value| urot::<u64>(value, 17);   // Rotation only, as in the sprng_64 demo
+    fn perm(mut state: Ux4<u64>) -> Ux4<u64> {
+        const N_ROUNDS: usize = 4;
+        for _ in 0..N_ROUNDS {state = SipHash_perm(&state);}
+        state
+    } // Example using the SipHash permutation
+
+    // SPRNG with rotation-only permutation
+    let params = SPRNGParams::sprng_64();
+    let mut sprng = SPRNG::new(params, rot_17)?;
+    let inputs: Vec<u64> = (0..8).map(|_| rng.gen::<u64>()).collect();
+    sprng.refresh(inputs)?;
+    report("SPRNG, 64-bit state, rotation permutation", &run_all(&prng_bits(&mut sprng, *params.r(), NB_BITS)?)?);
+
+    // SPRNG with SipHash permutation
+    let params = SPRNGParams::sprng_256();
+    let mut sprng = SPRNG::new(params, perm)?;
+    let inputs: Vec<Ux4<u64>> = (0..8).map(|_| Ux4::<u64>::rand()).collect();
+    sprng.refresh(inputs)?;
+    report("SPRNG, 256-bit state, SipHash permutation", &run_all(&prng_bits(&mut sprng, *params.r(), NB_BITS)?)?);
+
+    // Keystreams, obtained by encrypting zeros
+    let zeros = vec![0_u8; NB_BITS / 8];
+    let (key, nonce) = (rng.gen::<Ux4<u64>>(), rng.gen::<Ux4<u64>>());
+
+    let mut asakey = Asakey::<Ux4<u64>>::new(AsakeyParams::asakey_256(), perm)?;
+    let keystream = asakey.encrypt(key, nonce, &zeros)?;
+    report("Asakey, 256-bit state, SipHash permutation", &run_all(&BitBuf::from_bytes(&keystream, BitOrder::LsbFirst).to_bits())?);
+
+    let mut double_sponge = DSS::<Ux4<u64>>::new(DSSParams::dss_256(), perm)?;
+    let keystream = double_sponge.encrypt(key, nonce, &zeros)?;
+    report("DSS, 256-bit state, SipHash permutation", &run_all(&BitBuf::from_bytes(&keystream, BitOrder::LsbFirst).to_bits())?);
+
+    println!("-> Total execution time: {:.2?}", execution_start.elapsed());
+    Ok(())
+}
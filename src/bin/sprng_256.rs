@@ -6,10 +6,10 @@
 //! Demonstration for using the sponge-based PRNG of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4),
 //! with an inner state of 64x4 bits.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 
-use CryptoTools::prng::{PRNG, sprng::SPRNG};
+use CryptoTools::prng::{PRNG, sprng::{SPRNG, SPRNGParams}};
 use CryptoTools::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
 
 /// SPRNG demonstration with 256-bit state.
@@ -26,12 +26,11 @@ fn main() -> Result<(), Error>{
     } // Example using the SipHash permutation
 
     // Define parameters
-    let (n, r, t, s) = (256, 32, 1, 3);     // input size, rate, number of truncations, size of seed
     let nb_inputs: usize = 8;               // number of inputs for refresh
     let nb_next: usize = 128;               // number of calls to next per refresh
 
     // Setup
-    let mut sprng = SPRNG::new(vec!(n, r, t, s), perm)?;
+    let mut sprng = SPRNG::new(SPRNGParams::sprng_256(), perm)?;
 
     for i in 0..8 {
         // Generate refresh inputs
@@ -6,11 +6,11 @@
 //! Demonstration for using the sponge-based PRNG of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4),
 //! with an inner state of 64 bits.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 use rand::{Rng, thread_rng};
 
-use CryptoTools::prng::{PRNG, sprng::SPRNG};
+use CryptoTools::prng::{PRNG, sprng::{SPRNG, SPRNGParams}};
 use CryptoTools::utilities::bitops::urot;
 
 /// SPRNG demonstration with 64-bit state.
@@ -23,12 +23,11 @@ fn main() -> Result<(), Error>{
     let rot_17: fn(u64) -> u64 = move |value| urot::<u64>(value, 17);   // Example using a rotation as permutation
 
     // Define parameters
-    let (n, r, t, s) = (64, 4, 1, 3);   // input size, rate, number of truncations, size of seed
     let nb_inputs: usize = 8;           // number of inputs for refresh
     let nb_next: usize = 24;            // number of calls to next per refresh
 
     // Setup
-    let mut sprng = SPRNG::new(vec!(n, r, t, s), rot_17)?;
+    let mut sprng = SPRNG::new(SPRNGParams::sprng_64(), rot_17)?;
 
     for i in 0..8 {
         // Generate refresh inputs
@@ -0,0 +1,117 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the error type shared by all the tools of the crate.
+//!
+//! Setup functions return `Error::InvalidParameter` instead of panicking on bad parameters,
+//! so that a misconfiguration can be handled by the caller.
+
+use std::fmt::{self, Display, Formatter};
+
+/// Errors returned by the tools of the crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// A parameter is out of its valid range, `field` is its name.
+    InvalidParameter {
+        /// Name of the parameter.
+        field: &'static str,
+        /// Description of the constraint that is not satisfied.
+        reason: String,
+    },
+
+    /// The key must be set before this operation.
+    KeyNotSet,
+
+    /// The state must be initialized before this operation.
+    StateNotInitialized,
+
+    /// An input does not have the expected length.
+    BadLength {
+        /// Name of the input.
+        field: &'static str,
+        /// Expected length.
+        expected: usize,
+        /// Actual length.
+        got: usize,
+    },
+
+    /// A tag or a signature does not match.
+    AuthenticationFailure,
+
+    /// The generator must be reseeded before producing more outputs.
+    ReseedRequired,
+
+    /// Not enough entropy was estimated in the inputs.
+    InsufficientEntropy {
+        /// Estimated min-entropy in bits.
+        estimated: f64,
+        /// Required min-entropy in bits.
+        required: f64,
+    },
+
+    /// Malformed textual input, such as a test vector file or an hexadecimal string.
+    Parse(String),
+
+    /// Error from an I/O operation, such as reading a file or polling an entropy source.
+    Io(std::io::Error),
+}
+
+impl Error {
+    /// Shorthand for `Error::InvalidParameter`.
+    pub fn invalid(field: &'static str, reason: impl Into<String>) -> Self {
+        Error::InvalidParameter { field, reason: reason.into() }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidParameter { field, reason } => write!(f, "invalid parameter {}: {}", field, reason),
+            Error::KeyNotSet => write!(f, "key is not set"),
+            Error::StateNotInitialized => write!(f, "state is not initialized"),
+            Error::BadLength { field, expected, got } =>
+                write!(f, "wrong length for {}: expected {}, got {}", field, expected, got),
+            Error::AuthenticationFailure => write!(f, "authentication failure"),
+            Error::ReseedRequired => write!(f, "reseed required"),
+            Error::InsufficientEntropy { estimated, required } =>
+                write!(f, "estimated {:.2} bits of min-entropy, {} required", estimated, required),
+            Error::Parse(message) => write!(f, "parse error: {}", message),
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display_and_source() {
+        let error = Error::invalid("r", "rate r must be greater than 0");
+        assert!(error.to_string() == "invalid parameter r: rate r must be greater than 0");
+        assert!(matches!(error, Error::InvalidParameter { field: "r", .. }));
+
+        let error = Error::BadLength { field: "seed", expected: 4, got: 3 };
+        assert!(error.to_string() == "wrong length for seed: expected 4, got 3");
+
+        let error: Error = std::io::Error::other("boom").into();
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(std::error::Error::source(&Error::KeyNotSet).is_none());
+    }
+}
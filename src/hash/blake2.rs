@@ -0,0 +1,521 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the BLAKE2 family of Hash functions.
+//!
+//! Implements BLAKE2s and BLAKE2b from [RFC 7693](https://www.rfc-editor.org/rfc/rfc7693),
+//! with the key, salt and personalization parameters of [BLAKE2](https://www.blake2.net/blake2.pdf).
+//! The compression function is built on the ARX mixing function G, as in ChaCha,
+//! over 32-bit words for BLAKE2s and 64-bit words for BLAKE2b.
+
+use crate::error::Error;
+use getset::Getters;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::BitXor;
+
+use crate::hash::{Digest, sha2::{IV256, IV512}};
+use crate::utilities::zeroize::Zeroize;
+
+/// Message word permutations, one per round (modulo 10).
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Trait for the words of the BLAKE2 functions, implemented for `u32` (BLAKE2s) and `u64` (BLAKE2b).
+pub trait BLAKE2Word: Copy + Zeroize + BitXor<Output = Self>
+{
+    /// Size of the input blocks in bytes.
+    const BLOCK_SIZE: usize;
+
+    /// Maximum size of the output and of the key in bytes.
+    const MAX_OUTPUT: usize;
+
+    /// Initial value of the state, shared with SHA-256 or SHA-512.
+    const IV: [Self; 8];
+
+    /// Process one input block, `counter` being the number of input bytes so far
+    /// and `last` marking the final block.
+    fn compress(state: &mut [Self; 8], block: &[u8], counter: u128, last: bool);
+
+    /// Deserialize a word from little-endian bytes, padded with zeros if too short.
+    fn from_le_slice(bytes: &[u8]) -> Self;
+
+    /// Serialize the state in little-endian order.
+    fn to_le_vec(state: &[Self; 8]) -> Vec<u8>;
+}
+
+/// Implements `BLAKE2Word` with the given number of rounds and rotation amounts of G.
+macro_rules! impl_blake2_word {
+    ($t:ty, $IV:ident, $rounds:expr, $R:expr) => {
+        impl BLAKE2Word for $t {
+            const BLOCK_SIZE: usize = 16*size_of::<$t>();
+            const MAX_OUTPUT: usize = 8*size_of::<$t>();
+            const IV: [$t; 8] = $IV;
+
+            fn compress(state: &mut [$t; 8], block: &[u8], counter: u128, last: bool) {
+                let mut m = [0 as $t; 16];
+                for (word, chunk) in m.iter_mut().zip(block.chunks_exact(size_of::<$t>())) {
+                    *word = <$t>::from_le_bytes(chunk.try_into().unwrap());
+                }
+
+                let mut v = [0 as $t; 16];
+                v[..8].copy_from_slice(state);
+                v[8..].copy_from_slice(&$IV);
+                v[12] ^= counter as $t;
+                v[13] ^= (counter >> <$t>::BITS) as $t;
+                if last {
+                    v[14] = !v[14];
+                }
+
+                // Mixing function G on the words a, b, c, d with the message words x, y
+                let G = |v: &mut [$t; 16], a: usize, b: usize, c: usize, d: usize, x: $t, y: $t| {
+                    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+                    v[d] = (v[d] ^ v[a]).rotate_right($R[0]);
+                    v[c] = v[c].wrapping_add(v[d]);
+                    v[b] = (v[b] ^ v[c]).rotate_right($R[1]);
+                    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+                    v[d] = (v[d] ^ v[a]).rotate_right($R[2]);
+                    v[c] = v[c].wrapping_add(v[d]);
+                    v[b] = (v[b] ^ v[c]).rotate_right($R[3]);
+                };
+
+                for round in 0..$rounds {
+                    let s = &SIGMA[round % 10];
+                    // Columns, then diagonals
+                    G(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+                    G(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+                    G(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+                    G(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+                    G(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+                    G(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+                    G(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+                    G(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+                }
+
+                for (i, word) in state.iter_mut().enumerate() {
+                    *word ^= v[i] ^ v[i + 8];
+                }
+                m.zeroize();
+                v.zeroize();
+            }
+
+            fn from_le_slice(bytes: &[u8]) -> $t {
+                let mut word = [0_u8; size_of::<$t>()];
+                word[..bytes.len()].copy_from_slice(bytes);
+                <$t>::from_le_bytes(word)
+            }
+
+            fn to_le_vec(state: &[$t; 8]) -> Vec<u8> {
+                state.iter().flat_map(|word| word.to_le_bytes()).collect()
+            }
+        }
+    };
+}
+
+impl_blake2_word!(u32, IV256, 10, [16, 12, 8, 7]);
+impl_blake2_word!(u64, IV512, 12, [32, 24, 16, 63]);
+
+// BLAKE2 parameters.
+
+#[derive(Getters, Clone, Debug, PartialEq, Eq)]
+/// Parameters of BLAKE2, built with [`BLAKE2Params::builder`] or taken from a preset.
+///
+/// The key is not a parameter, it is given to [`BLAKE2::new`].
+pub struct BLAKE2Params
+{
+    /// Size of the output in bytes.
+    #[getset(get = "pub")]
+    output_size: usize,
+
+    /// Salt, zero-padded to two words.
+    #[getset(get = "pub")]
+    salt: Vec<u8>,
+
+    /// Personalization string, zero-padded to two words.
+    #[getset(get = "pub")]
+    personal: Vec<u8>,
+}
+
+impl BLAKE2Params
+{
+    /// Start building parameters. The salt and the personalization string default to empty,
+    /// the output size must be set.
+    pub fn builder() -> BLAKE2ParamsBuilder {
+        BLAKE2ParamsBuilder::default()
+    }
+
+    /// BLAKE2s-256: 32-byte output, no salt and no personalization.
+    pub fn blake2s_256() -> Self {
+        Self { output_size: 32, salt: Vec::new(), personal: Vec::new() }
+    }
+
+    /// BLAKE2b-512: 64-byte output, no salt and no personalization.
+    pub fn blake2b_512() -> Self {
+        Self { output_size: 64, salt: Vec::new(), personal: Vec::new() }
+    }
+
+    /// Check the parameters against the limits of BLAKE2b.
+    /// The tighter limits of BLAKE2s are checked by [`BLAKE2::new`].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.output_size == 0 || self.output_size > 64 {
+            return Err(Error::invalid("output_size", "output size must be between 1 and 64 bytes"));
+        }
+        if self.salt.len() > 16 {
+            return Err(Error::invalid("salt", "salt must be at most 16 bytes"));
+        }
+        if self.personal.len() > 16 {
+            return Err(Error::invalid("personal", "personalization string must be at most 16 bytes"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Builder for [`BLAKE2Params`].
+pub struct BLAKE2ParamsBuilder
+{
+    output_size: Option<usize>,
+    salt: Vec<u8>,
+    personal: Vec<u8>,
+}
+
+impl BLAKE2ParamsBuilder
+{
+    /// Set the size of the output in bytes.
+    pub fn output_size(mut self, output_size: usize) -> Self {
+        self.output_size = Some(output_size);
+        self
+    }
+
+    /// Set the salt.
+    pub fn salt(mut self, salt: impl AsRef<[u8]>) -> Self {
+        self.salt = salt.as_ref().to_vec();
+        self
+    }
+
+    /// Set the personalization string.
+    pub fn personal(mut self, personal: impl AsRef<[u8]>) -> Self {
+        self.personal = personal.as_ref().to_vec();
+        self
+    }
+
+    /// Check the parameters and build them.
+    pub fn build(self) -> Result<BLAKE2Params, Error> {
+        let params = BLAKE2Params {
+            output_size: self.output_size.ok_or_else(|| Error::invalid("output_size", "output size must be set"))?,
+            salt: self.salt,
+            personal: self.personal,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+// BLAKE2 structure.
+
+#[derive(Clone)]
+/// Structure implementing BLAKE2s (32-bit words) and BLAKE2b (64-bit words)
+/// from [RFC 7693](https://www.rfc-editor.org/rfc/rfc7693).
+///
+/// With a key, the key is padded to a full block and processed before the message,
+/// which makes BLAKE2 a MAC (or a PRF) without the nested calls of HMAC.
+pub struct BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    /// Parameters of the Hash function.
+    params: BLAKE2Params,
+
+    /// State after the parameter block, before the key.
+    init: [W; 8],
+
+    /// Key padded to a full block, empty without a key.
+    key_block: Vec<u8>,
+
+    /// Inner state.
+    state: [W; 8],
+
+    /// Input bytes not processed yet, at most one block.
+    /// A full block is only processed once more inputs arrive, the last one being processed by `finalize`.
+    buffer: Vec<u8>,
+
+    /// Number of input bytes processed so far, key block included.
+    counter: u128,
+}
+
+impl<W> Zeroize for BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    /// Wipes the key, the inner state and the buffered inputs.
+    fn zeroize(&mut self) {
+        self.key_block.zeroize();
+        self.state.zeroize();
+        self.buffer.zeroize();
+        self.counter.zeroize();
+    }
+}
+
+impl<W> Drop for BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<W> Debug for BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    /// Prints the parameters, with the key, the inner state and the buffered inputs redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BLAKE2")
+            .field("block_size", &W::BLOCK_SIZE)
+            .field("params", &self.params)
+            .field("key", &format_args!("<redacted>"))
+            .field("state", &format_args!("<redacted>"))
+            .field("buffer", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<W> BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    /// Setup function, with an optional `key` (empty for unkeyed hashing).
+    pub fn new(params: BLAKE2Params, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let key = key.as_ref();
+        params.validate()?;
+        let half = W::BLOCK_SIZE/8;
+        if params.output_size > W::MAX_OUTPUT {
+            return Err(Error::invalid("output_size", format!("output size must be at most {} bytes", W::MAX_OUTPUT)));
+        }
+        if params.salt.len() > half {
+            return Err(Error::invalid("salt", format!("salt must be at most {} bytes", half)));
+        }
+        if params.personal.len() > half {
+            return Err(Error::invalid("personal", format!("personalization string must be at most {} bytes", half)));
+        }
+        if key.len() > W::MAX_OUTPUT {
+            return Err(Error::invalid("key", format!("key must be at most {} bytes", W::MAX_OUTPUT)));
+        }
+
+        Ok(Self::setup(params, key))
+    }
+
+    /// Build the state from checked parameters.
+    /// The parameter block holds the output size, the key size, a fanout and depth of 1
+    /// (sequential mode), the salt and the personalization string.
+    fn setup(params: BLAKE2Params, key: &[u8]) -> Self {
+        let half = W::BLOCK_SIZE/8;
+        let word = half/2;
+        let mut block = vec![0_u8; W::BLOCK_SIZE/2];
+        block[..4].copy_from_slice(&[params.output_size as u8, key.len() as u8, 1, 1]);
+        block[2*half..2*half + params.salt.len()].copy_from_slice(&params.salt);
+        block[3*half..3*half + params.personal.len()].copy_from_slice(&params.personal);
+
+        let mut init = W::IV;
+        for (i, chunk) in block.chunks(word).enumerate() {
+            init[i] = init[i] ^ W::from_le_slice(chunk);
+        }
+
+        let mut key_block = key.to_vec();
+        if !key_block.is_empty() {
+            key_block.resize(W::BLOCK_SIZE, 0x00);
+        }
+
+        let mut blake2 = Self {
+            params,
+            init,
+            key_block,
+            state: init,
+            buffer: Vec::with_capacity(W::BLOCK_SIZE),
+            counter: 0,
+        };
+        blake2.reset();
+        blake2
+    }
+}
+
+impl BLAKE2<u32>
+{
+    /// Unkeyed BLAKE2s-256.
+    pub fn blake2s_256() -> Self {
+        Self::setup(BLAKE2Params::blake2s_256(), &[])
+    }
+}
+
+impl BLAKE2<u64>
+{
+    /// Unkeyed BLAKE2b-512.
+    pub fn blake2b_512() -> Self {
+        Self::setup(BLAKE2Params::blake2b_512(), &[])
+    }
+}
+
+impl<W> Digest for BLAKE2<W>
+where
+    W: BLAKE2Word
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        W::BLOCK_SIZE
+    }
+
+    fn output_size(&self) -> usize {
+        self.params.output_size
+    }
+
+    /// Reset to the state after the parameter block, with the key block buffered.
+    fn reset(&mut self) {
+        self.state = self.init;
+        self.buffer.zeroize();
+        self.buffer.extend(&self.key_block);
+        self.counter = 0;
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let mut data = data.as_ref();
+        while !data.is_empty() {
+            if self.buffer.len() == W::BLOCK_SIZE {
+                self.counter += W::BLOCK_SIZE as u128;
+                W::compress(&mut self.state, &self.buffer, self.counter, false);
+                self.buffer.zeroize();
+            }
+            let take = data.len().min(W::BLOCK_SIZE - self.buffer.len());
+            self.buffer.extend(&data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    /// Process the last block, zero-padded, and return the little-endian output. The state is reset afterwards.
+    fn finalize(&mut self) -> Result<Vec<u8>, Error> {
+        self.counter += self.buffer.len() as u128;
+        self.buffer.resize(W::BLOCK_SIZE, 0x00);
+        W::compress(&mut self.state, &self.buffer, self.counter, true);
+
+        let mut output = W::to_le_vec(&self.state);
+        output.truncate(self.params.output_size);
+        self.reset();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::hex::from_hex;
+
+    /// Deterministic input sequence of the RFC 7693 self-test (Appendix E).
+    fn selftest_seq(length: usize, seed: u32) -> Vec<u8> {
+        let (mut a, mut b) = (0xdead4bad_u32.wrapping_mul(seed), 1_u32);
+        (0..length).map(|_| {
+            let t = a.wrapping_add(b);
+            (a, b) = (b, t);
+            (t >> 24) as u8
+        }).collect()
+    }
+
+    /// RFC 7693 self-test: all the outputs, unkeyed and keyed, are hashed together.
+    fn selftest<W: BLAKE2Word>(mut outer: BLAKE2<W>, output_sizes: [usize; 4], input_sizes: [usize; 6]) -> Vec<u8> {
+        for output_size in output_sizes {
+            for input_size in input_sizes {
+                let input = selftest_seq(input_size, input_size as u32);
+                let params = BLAKE2Params::builder().output_size(output_size).build().unwrap();
+                let key = selftest_seq(output_size, output_size as u32);
+                for key in [&[][..], &key[..]] {
+                    let mut inner = BLAKE2::<W>::new(params.clone(), key).unwrap();
+                    inner.update(&input);
+                    outer.update(inner.finalize().unwrap());
+                }
+            }
+        }
+        outer.finalize().unwrap()
+    }
+
+    #[test]
+    fn rfc7693_vectors() {
+        // Appendix A and B
+        let mut blake2b = BLAKE2::blake2b_512();
+        blake2b.update(b"abc");
+        assert!(blake2b.finalize().unwrap() == from_hex("ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+            7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923").unwrap());
+        let mut blake2s = BLAKE2::blake2s_256();
+        blake2s.update(b"abc");
+        assert!(blake2s.finalize().unwrap()
+            == from_hex("508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982").unwrap());
+
+        // Appendix E
+        let params = BLAKE2Params::builder().output_size(32).build().unwrap();
+        let result = selftest(BLAKE2::<u64>::new(params.clone(), []).unwrap(), [20, 32, 48, 64], [0, 3, 128, 129, 255, 1024]);
+        assert!(result == from_hex("c23a7800d98123bd10f506c61e29da5603d763b8bbad2e737f5e765a7bccd475").unwrap());
+        let result = selftest(BLAKE2::<u32>::new(params, []).unwrap(), [16, 20, 28, 32], [0, 3, 64, 65, 255, 1024]);
+        assert!(result == from_hex("6a411f08ce25adcdfb02aba641451cec53c598b24f4fc787fbdc88797f4c1dfe").unwrap());
+    }
+
+    #[test]
+    fn salt_and_personalization() {
+        let params = BLAKE2Params::builder().output_size(20).salt(b"saltsalt").personal(b"personal").build().unwrap();
+        let mut blake2s = BLAKE2::<u32>::new(params, b"key").unwrap();
+        blake2s.update(b"abc");
+        assert!(blake2s.finalize().unwrap() == from_hex("b22efd5adb18e1ac152338bcea7a2e232a2f9c1c").unwrap());
+
+        // Short salts and personalization strings are zero-padded
+        let params = BLAKE2Params::builder().output_size(64).salt(b"salt").personal(b"me").build().unwrap();
+        let mut blake2b = BLAKE2::<u64>::new(params, [0_u8; 64]).unwrap();
+        blake2b.update([b'x'; 300]);
+        assert!(blake2b.finalize().unwrap() == from_hex("0933780fcc5e88e490514003f3587a677feef5f65dab3963d123c23fca0a7b20\
+            b10364c3a30d94a46747f1a360c369f5e0fab72a6f52316042473946e0edab90").unwrap());
+    }
+
+    #[test]
+    fn streaming() {
+        let data: Vec<u8> = (0..300).map(|i| (i*7) as u8).collect();
+        let params = BLAKE2Params::builder().output_size(32).build().unwrap();
+        let mut blake2 = BLAKE2::<u32>::new(params, b"secret key").unwrap();
+        blake2.update(&data);
+        let expected = blake2.finalize().unwrap();
+
+        // Splits around the block boundaries, where the last block must not be processed early
+        for split in [0, 1, 63, 64, 65, 128, 256, 300] {
+            blake2.update(&data[..split]);
+            blake2.update(&data[split..]);
+            assert!(blake2.finalize().unwrap() == expected);
+        }
+        blake2.update(b"discarded");
+        blake2.reset();
+        blake2.update(&data);
+        assert!(blake2.finalize().unwrap() == expected);
+        assert!(format!("{:?}", blake2).contains("<redacted>"));
+    }
+
+    #[test]
+    fn setup_errors() {
+        let result = BLAKE2Params::builder().output_size(65).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "output_size", .. })));
+        let result = BLAKE2Params::builder().output_size(32).salt([0; 17]).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "salt", .. })));
+
+        // Limits of BLAKE2s
+        let params = BLAKE2Params::builder().output_size(33).build().unwrap();
+        assert!(matches!(BLAKE2::<u32>::new(params, []), Err(Error::InvalidParameter { field: "output_size", .. })));
+        let params = BLAKE2Params::builder().output_size(32).personal([0; 9]).build().unwrap();
+        assert!(matches!(BLAKE2::<u32>::new(params, []), Err(Error::InvalidParameter { field: "personal", .. })));
+        assert!(matches!(BLAKE2::<u32>::new(BLAKE2Params::blake2s_256(), [0; 33]),
+            Err(Error::InvalidParameter { field: "key", .. })));
+        assert!(BLAKE2::<u64>::new(BLAKE2Params::blake2b_512(), [0; 64]).is_ok());
+    }
+}
@@ -0,0 +1,263 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing HMAC.
+//!
+//! Based on [RFC 2104](https://www.rfc-editor.org/rfc/rfc2104), generic over the `Digest` trait.
+//! The block size and the output size of the underlying Hash function are taken from its `Digest` metadata.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::mac::Mac;
+use crate::utilities::zeroize::Zeroize;
+
+// HMAC structure.
+
+#[derive(Clone)]
+/// Structure implementing HMAC from [RFC 2104](https://www.rfc-editor.org/rfc/rfc2104).
+///
+/// The inner and outer Hash functions are keyed once at setup,
+/// so that `reset` and `finalize` do not need the key anymore.
+pub struct HMAC<D>
+{
+    /// Inner Hash function after absorbing the key XORed with ipad.
+    inner_init: D,
+
+    /// Outer Hash function after absorbing the key XORed with opad.
+    outer_init: D,
+
+    /// Inner Hash function absorbing the message.
+    inner: D,
+}
+
+impl<D> Debug for HMAC<D>
+where
+    D: Digest
+{
+    /// Prints the sizes, the keyed states are redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HMAC")
+            .field("block_size", &self.inner.block_size())
+            .field("output_size", &self.inner.output_size())
+            .field("key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<D> HMAC<D>
+where
+    D: Digest + Clone
+{
+    /// Setup function, keying HMAC over the Hash function `digest` with `key`.
+    /// Keys longer than the block size are hashed first, then zero-padded to the block size.
+    /// A hashed key that is still longer than the block size, as with small-rate sponges, is used whole.
+    pub fn new(digest: D, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let block_size = digest.block_size();
+        if block_size == 0 {
+            return Err(Error::invalid("digest", "block size of the Hash function must be greater than 0"));
+        }
+
+        let mut block = key.as_ref().to_vec();
+        if block.len() > block_size {
+            let mut hash = digest.clone();
+            hash.reset();
+            hash.update(&block);
+            block.zeroize();
+            block = hash.finalize_bytes()?;
+        }
+        if block.len() < block_size {
+            block.resize(block_size, 0x00);
+        }
+
+        let mut inner_init = digest.clone();
+        inner_init.reset();
+        let mut pad: Vec<u8> = block.iter().map(|k| k ^ 0x36).collect();
+        inner_init.update(&pad);
+
+        let mut outer_init = digest;
+        outer_init.reset();
+        pad = block.iter().map(|k| k ^ 0x5c).collect();
+        outer_init.update(&pad);
+
+        pad.zeroize();
+        block.zeroize();
+        Ok(Self { inner: inner_init.clone(), inner_init, outer_init })
+    }
+
+    /// One-shot HMAC of `data` under `key`.
+    pub fn mac(digest: D, key: impl AsRef<[u8]>, data: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let mut hmac = Self::new(digest, key)?;
+        hmac.update(data);
+        hmac.finalize()
+    }
+}
+
+impl<D> Digest for HMAC<D>
+where
+    D: Digest + Clone
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        self.inner.block_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.inner.output_size()
+    }
+
+    /// Reset to the keyed initial state.
+    fn reset(&mut self) {
+        self.inner = self.inner_init.clone();
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.inner.update(data);
+    }
+
+    /// Return the tag and reset to the keyed initial state.
+    fn finalize(&mut self) -> Result<Self::Output, Error> {
+        let inner = self.inner.finalize_bytes()?;
+        let mut outer = self.outer_init.clone();
+        outer.update(inner);
+        self.reset();
+        outer.finalize_bytes()
+    }
+}
+
+impl<D> Mac for HMAC<D>
+where
+    D: Digest + Clone
+{
+    /// Underlying Hash function.
+    type Params = D;
+
+    fn new_with_key(digest: D, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        Self::new(digest, key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::{sponge::Sponge, sha2::SHA2};
+    use crate::utilities::hex::from_hex;
+
+    fn hash(data: &[&[u8]]) -> Vec<u8> {
+        let mut sponge = Sponge::siphash().unwrap();
+        for part in data {
+            sponge.update(part);
+        }
+        sponge.finalize().unwrap()
+    }
+
+    // H((K ^ opad) || H((K ^ ipad) || m)) with the key K already padded
+    fn reference(block: &[u8], message: &[u8]) -> Vec<u8> {
+        let ipad: Vec<u8> = block.iter().map(|k| k ^ 0x36).collect();
+        let opad: Vec<u8> = block.iter().map(|k| k ^ 0x5c).collect();
+        hash(&[&opad, &hash(&[&ipad, message])])
+    }
+
+    #[test]
+    fn definition() {
+        // Short keys are zero-padded to the 8-byte block
+        let tag = HMAC::mac(Sponge::siphash().unwrap(), b"key", b"message").unwrap();
+        assert!(tag == reference(b"key\0\0\0\0\0", b"message") && tag.len() == 32);
+
+        // Longer keys are hashed, the 32-byte hash being used whole
+        let long = [0x0b_u8; 20];
+        let tag = HMAC::mac(Sponge::siphash().unwrap(), long, b"m").unwrap();
+        assert!(tag == reference(&hash(&[&long]), b"m"));
+        assert!(HMAC::mac(Sponge::siphash().unwrap(), [1_u8; 8], b"m").unwrap() == reference(&[1_u8; 8], b"m"));
+    }
+
+    #[test]
+    fn streaming_and_reset() {
+        let mut hmac = HMAC::new(Sponge::half_siphash().unwrap(), b"secret").unwrap();
+        assert!(hmac.block_size() == 4 && hmac.output_size() == 16);
+
+        hmac.update(b"hello ");
+        hmac.update(b"world");
+        let tag = hmac.finalize().unwrap();
+        hmac.update(b"hello world");
+        assert!(hmac.finalize().unwrap() == tag);
+
+        hmac.update(b"discarded");
+        hmac.reset();
+        hmac.update(b"hello world");
+        assert!(hmac.finalize().unwrap() == tag);
+        assert!(format!("{:?}", hmac).contains("<redacted>"));
+
+        let mut hmac = HMAC::new_with_key(Sponge::half_siphash().unwrap(), b"secret").unwrap();
+        hmac.update(b"hello world");
+        hmac.verify(&tag).unwrap();
+        hmac.update(b"hello world");
+        assert!(matches!(hmac.verify(&tag[..15]), Err(Error::AuthenticationFailure)));
+    }
+
+    #[test]
+    fn rfc4231_vectors() {
+        // Test cases 1 to 7 of RFC 4231, with the tags of HMAC-SHA-224, 256, 384 and 512
+        let large = b"This is a test using a larger than block-size key and a larger than block-size data. \
+            The key needs to be hashed before being used by the HMAC algorithm.";
+        let cases: [(Vec<u8>, Vec<u8>, [&str; 4]); 7] = [
+            (vec![0x0b; 20], b"Hi There".to_vec(), [
+                "896fb1128abbdf196832107cd49df33f47b4b1169912ba4f53684b22",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+                "afd03944d84895626b0825f4ab46907f15f9dadbe4101ec682aa034c7cebc59cfaea9ea9076ede7f4af152e8b2fa9cb6",
+                "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde\
+                daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"]),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec(), [
+                "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+                "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+                "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"]),
+            (vec![0xaa; 20], vec![0xdd; 50], [
+                "7fb3cb3588c6c1f6ffa9694d7d6ad2649365b0c1f65d69d1ec8333ea",
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+                "88062608d3e6ad8a0aa2ace014c8a86f0aa635d947ac9febe83ef4e55966144b2a5ab39dc13814b94e3ab6e101a34f27",
+                "fa73b0089d56a284efb0f0756c890be9b1b5dbdd8ee81a3655f83e33b2279d39\
+                bf3e848279a722c806b485a47e67c807b946a337bee8942674278859e13292fb"]),
+            ((1..=25).collect(), vec![0xcd; 50], [
+                "6c11506874013cac6a2abc1bb382627cec6a90d86efc012de7afec5a",
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+                "3e8a69b7783c25851933ab6290af6ca77a9981480850009cc5577c6e1f573b4e6801dd23c4a7d679ccf8a386c674cffb",
+                "b0ba465637458c6990e5a8c5f61d4af7e576d97ff94b872de76f8050361ee3db\
+                a91ca5c11aa25eb4d679275cc5788063a5f19741120c4f2de2adebeb10a298dd"]),
+            // Truncated to 128 bits
+            (vec![0x0c; 20], b"Test With Truncation".to_vec(), [
+                "0e2aea68a90c8d37c988bcdb9fca6fa8",
+                "a3b6167473100ee06e0c796c2955552b",
+                "3abf34c3503b2a23a46efc619baef897",
+                "415fad6271580a531d4179bc891d87a6"]),
+            (vec![0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(), [
+                "95e9a0db962095adaebe9b2d6f0dbce2d499f112f2d2b7273fa6870e",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+                "4ece084485813e9088d2c63a041bc5b44f9ef1012a2b588f3cd11f05033ac4c60c2ef6ab4030fe8296248df163f44952",
+                "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+                6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"]),
+            (vec![0xaa; 131], large.to_vec(), [
+                "3a854166ac5d9f023f54d517d0b39dbd946770db9c2b95c9f6f565d1",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+                "6617178e941f020d351e2f254e8fd32c602420feb0b8fb9adccebb82461e99c5a678cc31e799176d3860e6110c46523e",
+                "e37b6a775dc87dbaa4dfa9f96e5e3ffddebd71f8867289865df5a32d20cdc944\
+                b6022cac3c4982b10d5eeb55c3e4de15134676fb6de0446065c97440fa8c6a58"]),
+        ];
+
+        for (key, data, tags) in cases {
+            let computed = [
+                HMAC::mac(SHA2::sha224(), &key, &data).unwrap(),
+                HMAC::mac(SHA2::sha256(), &key, &data).unwrap(),
+                HMAC::mac(SHA2::sha384(), &key, &data).unwrap(),
+                HMAC::mac(SHA2::sha512(), &key, &data).unwrap(),
+            ];
+            for (tag, expected) in computed.iter().zip(tags) {
+                let expected = from_hex(expected).unwrap();
+                assert!(tag[..expected.len()] == expected);
+            }
+        }
+    }
+}
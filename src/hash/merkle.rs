@@ -0,0 +1,409 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing Merkle trees.
+//!
+//! Based on [RFC 6962](https://www.rfc-editor.org/rfc/rfc6962) (Certificate Transparency), generic over the `Digest` trait.
+//! Leaves and nodes are hashed with the prefixes 0x00 and 0x01, so that a node can never be passed off as a leaf.
+//! Proofs are verified with the algorithms of [RFC 9162](https://www.rfc-editor.org/rfc/rfc9162), Section 2.1.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::utilities::ct::ct_eq;
+
+/// Largest power of 2 smaller than `n`, for `n` > 1.
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+// MerkleTree structure.
+
+#[derive(Clone)]
+/// Structure implementing an append-only Merkle tree from [RFC 6962](https://www.rfc-editor.org/rfc/rfc6962).
+///
+/// Only the leaf hashes are stored, so that roots and proofs can be computed for any earlier tree size.
+/// The Hash function is used as a template: each hash is computed on a reset copy.
+pub struct MerkleTree<D>
+{
+    /// Underlying Hash function.
+    digest: D,
+
+    /// Hashes of the leaves, in insertion order.
+    leaves: Vec<Vec<u8>>,
+}
+
+impl<D> Debug for MerkleTree<D>
+where
+    D: Digest
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MerkleTree")
+            .field("output_size", &self.digest.output_size())
+            .field("size", &self.leaves.len())
+            .finish()
+    }
+}
+
+impl<D> MerkleTree<D>
+where
+    D: Digest + Clone
+{
+    /// Setup function for an empty tree, over the Hash function `digest`.
+    pub fn new(digest: D) -> Result<Self, Error> {
+        if digest.output_size() == 0 {
+            return Err(Error::invalid("digest", "output size of the Hash function must be greater than 0"));
+        }
+
+        Ok(Self { digest, leaves: Vec::new() })
+    }
+
+    /// Number of leaves.
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Whether the tree has no leaves.
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    /// Append the leaf `data` and return its index.
+    pub fn push(&mut self, data: impl AsRef<[u8]>) -> Result<usize, Error> {
+        let leaf = self.leaf_hash(data)?;
+        self.leaves.push(leaf);
+        Ok(self.leaves.len() - 1)
+    }
+
+    /// Hash of the leaf `data`, H(0x00 || data).
+    pub fn leaf_hash(&self, data: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        self.hash(&[&[0x00], data.as_ref()])
+    }
+
+    /// Hash of the node with children `left` and `right`, H(0x01 || left || right).
+    pub fn node_hash(&self, left: impl AsRef<[u8]>, right: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        self.hash(&[&[0x01], left.as_ref(), right.as_ref()])
+    }
+
+    /// Root of the current tree. The root of the empty tree is the hash of the empty string.
+    pub fn root(&self) -> Result<Vec<u8>, Error> {
+        self.root_at(self.leaves.len())
+    }
+
+    /// Root of the tree made of the first `size` leaves.
+    pub fn root_at(&self, size: usize) -> Result<Vec<u8>, Error> {
+        self.check_size(size)?;
+        self.mth(&self.leaves[..size])
+    }
+
+    /// Inclusion proof of the leaf `index` in the tree made of the first `size` leaves,
+    /// as the audit path PATH(index, D\[size\]) from the leaf up to the root.
+    pub fn inclusion_proof(&self, index: usize, size: usize) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_size(size)?;
+        if index >= size {
+            return Err(Error::invalid("index", format!("Merkle tree: leaf index must be lower than the tree size {}", size)));
+        }
+
+        self.path(index, &self.leaves[..size])
+    }
+
+    /// Consistency proof PROOF(old_size, D\[new_size\]) between the trees made of
+    /// the first `old_size` and `new_size` leaves, with 0 < `old_size` <= `new_size`.
+    pub fn consistency_proof(&self, old_size: usize, new_size: usize) -> Result<Vec<Vec<u8>>, Error> {
+        self.check_size(new_size)?;
+        if old_size == 0 || old_size > new_size {
+            return Err(Error::invalid("old_size", "Merkle tree: old size must be in 1..=new_size"));
+        }
+
+        self.subproof(old_size, &self.leaves[..new_size], true)
+    }
+
+    /// Verify that `leaf_hash` is the leaf `index` of the tree of size `size` with root `root`.
+    /// The leaves stored in this tree are not used, only the Hash function.
+    pub fn verify_inclusion(&self, leaf_hash: impl AsRef<[u8]>, index: usize, size: usize, proof: &[Vec<u8>],
+        root: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        if index >= size {
+            return Err(Error::AuthenticationFailure);
+        }
+
+        let (mut fn_, mut sn) = (index, size - 1);
+        let mut r = leaf_hash.as_ref().to_vec();
+        for p in proof {
+            if sn == 0 {
+                return Err(Error::AuthenticationFailure);
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                r = self.node_hash(p, &r)?;
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                r = self.node_hash(&r, p)?;
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+
+        if sn == 0 && ct_eq(&r, root.as_ref()) {
+            Ok(())
+        } else {
+            Err(Error::AuthenticationFailure)
+        }
+    }
+
+    /// Verify that the tree of size `old_size` with root `old_root` is a prefix of
+    /// the tree of size `new_size` with root `new_root`.
+    /// The leaves stored in this tree are not used, only the Hash function.
+    pub fn verify_consistency(&self, old_size: usize, new_size: usize, proof: &[Vec<u8>],
+        old_root: impl AsRef<[u8]>, new_root: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        let (old_root, new_root) = (old_root.as_ref(), new_root.as_ref());
+        if old_size == 0 || old_size > new_size {
+            return Err(Error::AuthenticationFailure);
+        }
+        if old_size == new_size {
+            return if proof.is_empty() && ct_eq(old_root, new_root) {
+                Ok(())
+            } else {
+                Err(Error::AuthenticationFailure)
+            };
+        }
+        if proof.is_empty() {
+            return Err(Error::AuthenticationFailure);
+        }
+
+        // The old root starts the path when the old tree is a complete subtree
+        let mut path: Vec<&[u8]> = proof.iter().map(|p| p.as_slice()).collect();
+        if old_size.is_power_of_two() {
+            path.insert(0, old_root);
+        }
+
+        let (mut fn_, mut sn) = (old_size - 1, new_size - 1);
+        while fn_ & 1 == 1 {
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+
+        let (mut fr, mut sr) = (path[0].to_vec(), path[0].to_vec());
+        for c in &path[1..] {
+            if sn == 0 {
+                return Err(Error::AuthenticationFailure);
+            }
+            if fn_ & 1 == 1 || fn_ == sn {
+                fr = self.node_hash(c, &fr)?;
+                sr = self.node_hash(c, &sr)?;
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            } else {
+                sr = self.node_hash(&sr, c)?;
+            }
+            fn_ >>= 1;
+            sn >>= 1;
+        }
+
+        if sn == 0 && ct_eq(&fr, old_root) && ct_eq(&sr, new_root) {
+            Ok(())
+        } else {
+            Err(Error::AuthenticationFailure)
+        }
+    }
+
+    /// Hash of the concatenation of `parts`, on a reset copy of the Hash function.
+    fn hash(&self, parts: &[&[u8]]) -> Result<Vec<u8>, Error> {
+        let mut digest = self.digest.clone();
+        digest.reset();
+        for part in parts {
+            digest.update(part);
+        }
+        digest.finalize_bytes()
+    }
+
+    fn check_size(&self, size: usize) -> Result<(), Error> {
+        if size > self.leaves.len() {
+            return Err(Error::invalid("size", format!("Merkle tree: size must be at most {}", self.leaves.len())));
+        }
+        Ok(())
+    }
+
+    /// Merkle Tree Hash MTH(D\[n\]) over the leaf hashes `leaves`.
+    fn mth(&self, leaves: &[Vec<u8>]) -> Result<Vec<u8>, Error> {
+        match leaves.len() {
+            0 => self.hash(&[]),
+            1 => Ok(leaves[0].clone()),
+            n => {
+                let k = split(n);
+                self.node_hash(self.mth(&leaves[..k])?, self.mth(&leaves[k..])?)
+            }
+        }
+    }
+
+    /// Audit path PATH(m, D\[n\]) over the leaf hashes `leaves`.
+    fn path(&self, m: usize, leaves: &[Vec<u8>]) -> Result<Vec<Vec<u8>>, Error> {
+        let n = leaves.len();
+        if n <= 1 {
+            return Ok(Vec::new());
+        }
+
+        let k = split(n);
+        let (mut path, sibling) = if m < k {
+            (self.path(m, &leaves[..k])?, self.mth(&leaves[k..])?)
+        } else {
+            (self.path(m - k, &leaves[k..])?, self.mth(&leaves[..k])?)
+        };
+        path.push(sibling);
+        Ok(path)
+    }
+
+    /// Consistency subproof SUBPROOF(m, D\[n\], b) over the leaf hashes `leaves`.
+    fn subproof(&self, m: usize, leaves: &[Vec<u8>], b: bool) -> Result<Vec<Vec<u8>>, Error> {
+        let n = leaves.len();
+        if m == n {
+            return if b { Ok(Vec::new()) } else { Ok(vec![self.mth(leaves)?]) };
+        }
+
+        let k = split(n);
+        let (mut proof, sibling) = if m <= k {
+            (self.subproof(m, &leaves[..k], b)?, self.mth(&leaves[k..])?)
+        } else {
+            (self.subproof(m - k, &leaves[k..], false)?, self.mth(&leaves[..k])?)
+        };
+        proof.push(sibling);
+        Ok(proof)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::{sponge::Sponge, sha2::SHA2};
+    use crate::utilities::hex::from_hex;
+
+    // Leaves of the certificate-transparency reference test data
+    fn tree() -> MerkleTree<SHA2<u32>> {
+        let mut tree = MerkleTree::new(SHA2::sha256()).unwrap();
+        for leaf in ["", "00", "10", "2021", "3031", "40414243", "5051525354555657", "606162636465666768696a6b6c6d6e6f"] {
+            tree.push(from_hex(leaf).unwrap()).unwrap();
+        }
+        tree
+    }
+
+    fn hex(values: &[&str]) -> Vec<Vec<u8>> {
+        values.iter().map(|v| from_hex(v).unwrap()).collect()
+    }
+
+    #[test]
+    fn roots() {
+        let roots = [
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+
+        let tree = tree();
+        for (size, root) in roots.iter().enumerate() {
+            assert!(tree.root_at(size).unwrap() == from_hex(root).unwrap());
+        }
+        assert!(tree.root().unwrap() == from_hex(roots[8]).unwrap() && tree.len() == 8);
+        assert!(matches!(tree.root_at(9), Err(Error::InvalidParameter { field: "size", .. })));
+    }
+
+    #[test]
+    fn inclusion_proofs() {
+        let tree = tree();
+        type Case = (usize, usize, [&'static str; 3]);
+        let cases: [Case; 2] = [
+            (0, 8, ["96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4"]),
+            (5, 8, ["bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7"]),
+        ];
+        for (index, size, path) in cases {
+            assert!(tree.inclusion_proof(index, size).unwrap() == hex(&path));
+        }
+        assert!(tree.inclusion_proof(2, 3).unwrap() == hex(&["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"]));
+
+        // Every leaf of every tree size, and rejection of tampered proofs
+        for size in 1..=8 {
+            let root = tree.root_at(size).unwrap();
+            for index in 0..size {
+                let mut proof = tree.inclusion_proof(index, size).unwrap();
+                let leaf = &tree.leaves[index];
+                tree.verify_inclusion(leaf, index, size, &proof, &root).unwrap();
+                assert!(tree.verify_inclusion(leaf, index ^ 1, size, &proof, &root).is_err());
+                if let Some(first) = proof.first_mut() {
+                    first[0] ^= 1;
+                    assert!(matches!(tree.verify_inclusion(leaf, index, size, &proof, &root), Err(Error::AuthenticationFailure)));
+                }
+            }
+        }
+
+        assert!(matches!(tree.inclusion_proof(3, 3), Err(Error::InvalidParameter { field: "index", .. })));
+        assert!(tree.verify_inclusion(&tree.leaves[0], 1, 1, &[], tree.root_at(1).unwrap()).is_err());
+    }
+
+    #[test]
+    fn consistency_proofs() {
+        let tree = tree();
+        type Case = (usize, usize, Vec<&'static str>);
+        let cases: [Case; 3] = [
+            (1, 8, vec!["96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4"]),
+            (6, 8, vec!["0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7"]),
+            (2, 5, vec!["5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b"]),
+        ];
+        for (old_size, new_size, proof) in cases {
+            assert!(tree.consistency_proof(old_size, new_size).unwrap() == hex(&proof));
+        }
+
+        // Every pair of tree sizes, and rejection of tampered proofs and mismatched roots
+        for new_size in 1..=8 {
+            let new_root = tree.root_at(new_size).unwrap();
+            for old_size in 1..=new_size {
+                let old_root = tree.root_at(old_size).unwrap();
+                let mut proof = tree.consistency_proof(old_size, new_size).unwrap();
+                tree.verify_consistency(old_size, new_size, &proof, &old_root, &new_root).unwrap();
+                assert!(tree.verify_consistency(old_size, new_size, &proof, &new_root, &old_root).is_err()
+                    || old_size == new_size);
+                if let Some(last) = proof.last_mut() {
+                    last[0] ^= 1;
+                    assert!(matches!(tree.verify_consistency(old_size, new_size, &proof, &old_root, &new_root),
+                        Err(Error::AuthenticationFailure)));
+                }
+            }
+        }
+
+        assert!(tree.verify_consistency(2, 4, &[], tree.root_at(2).unwrap(), tree.root_at(4).unwrap()).is_err());
+        assert!(matches!(tree.consistency_proof(0, 4), Err(Error::InvalidParameter { field: "old_size", .. })));
+        assert!(matches!(tree.consistency_proof(5, 4), Err(Error::InvalidParameter { field: "old_size", .. })));
+    }
+
+    #[test]
+    fn domain_separation() {
+        // A node cannot be presented as a leaf, and any Digest works
+        let mut tree = MerkleTree::new(Sponge::siphash().unwrap()).unwrap();
+        assert!(tree.is_empty());
+        assert!(tree.push(b"a").unwrap() == 0 && tree.push(b"b").unwrap() == 1);
+        let root = tree.root().unwrap();
+        let node = [tree.leaves[0].clone(), tree.leaves[1].clone()].concat();
+        assert!(tree.leaf_hash(&node).unwrap() != root);
+        assert!(root == tree.node_hash(tree.leaf_hash(b"a").unwrap(), tree.leaf_hash(b"b").unwrap()).unwrap());
+        assert!(format!("{:?}", tree).contains("size: 2"));
+    }
+}
@@ -7,14 +7,21 @@
 //! These are accessible through the Hash trait, which implements the `hash` function.
 //! They also implement the trait `Digest`, which defines the general interface for Hash functions.
 
-use std::io::Error;
+use crate::error::Error;
+use crate::utilities::ToLeBytes;
 
 /// Trait for Hash functions,
 /// with public, general-purpose functions `update` and `finalize`.
 pub trait Digest
 {
     /// Type of the output of the Hash function.
-    type Output;
+    type Output: ToLeBytes;
+
+    /// Size in bytes of the input blocks processed by the Hash function, as used by HMAC.
+    fn block_size(&self) -> usize;
+
+    /// Size of the output in bytes.
+    fn output_size(&self) -> usize;
 
     /// Reset the Hash function to its initial state.
     fn reset(&mut self);
@@ -24,6 +31,41 @@ pub trait Digest
 
     /// Finalize the Hash function and return the output.
     fn finalize(&mut self) -> Result<Self::Output, Error>;
+
+    /// Finalize the Hash function and return the output as bytes.
+    fn finalize_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        Ok(self.finalize()?.to_le_bytes())
+    }
+}
+
+/// Trait for reading the output of an extendable-output function (XOF).
+pub trait XofReader
+{
+    /// Fill `buffer` with the next output bytes.
+    fn read(&mut self, buffer: &mut [u8]);
+
+    /// Return the next `n` output bytes.
+    fn read_vec(&mut self, n: usize) -> Vec<u8> {
+        let mut buffer = vec![0_u8; n];
+        self.read(&mut buffer);
+        buffer
+    }
+}
+
+/// Trait for Hash functions with an extendable output.
+pub trait ExtendableOutput: Digest
+{
+    /// Type of the output reader.
+    type Reader: XofReader;
+
+    /// Finalize the Hash function and return a reader for an output of any length.
+    fn finalize_xof(&mut self) -> Result<Self::Reader, Error>;
 }
 
-pub mod siphash;
\ No newline at end of file
+pub mod siphash;
+pub mod sponge;
+pub mod sha2;
+pub mod sha3;
+pub mod blake2;
+pub mod hmac;
+pub mod merkle;
\ No newline at end of file
@@ -0,0 +1,455 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the SHA-2 family of Hash functions.
+//!
+//! Implements SHA-224, SHA-256, SHA-384, SHA-512 and SHA-512/t from [FIPS 180-4](https://doi.org/10.6028/NIST.FIPS.180-4).
+//! SHA-224 and SHA-256 share the 32-bit compression function, the others the 64-bit one,
+//! and the variants only differ by their initial hash value and the truncation of the output.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::utilities::zeroize::Zeroize;
+
+/// Round constants of SHA-256, first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+const K256: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Round constants of SHA-512, first 64 bits of the fractional parts of the cube roots of the first 80 primes.
+const K512: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+/// Initial hash value of SHA-224.
+const IV224: [u32; 8] = [0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4];
+
+/// Initial hash value of SHA-256.
+pub(crate) const IV256: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+/// Initial hash value of SHA-384.
+const IV384: [u64; 8] = [
+    0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+    0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4,
+];
+
+/// Initial hash value of SHA-512.
+pub(crate) const IV512: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+/// Trait for the words of the SHA-2 functions, implemented for `u32` (SHA-256) and `u64` (SHA-512).
+pub trait SHA2Word: Copy + Zeroize
+{
+    /// Size of the input blocks in bytes.
+    const BLOCK_SIZE: usize;
+
+    /// Process one input block of `BLOCK_SIZE` bytes.
+    fn compress(state: &mut [Self; 8], block: &[u8]);
+
+    /// Serialize the state in big-endian order.
+    fn to_be_vec(state: &[Self; 8]) -> Vec<u8>;
+
+    /// Deserialize a state from big-endian bytes.
+    fn from_be_slice(bytes: &[u8]) -> [Self; 8];
+}
+
+/// Implements `SHA2Word` with the given round constants and rotation amounts of Σ0, Σ1, σ0 and σ1,
+/// the last amount of σ0 and σ1 being a shift.
+macro_rules! impl_sha2_word {
+    ($t:ty, $K:ident, $S0:expr, $S1:expr, $s0:expr, $s1:expr) => {
+        impl SHA2Word for $t {
+            const BLOCK_SIZE: usize = 16*size_of::<$t>();
+
+            fn compress(state: &mut [$t; 8], block: &[u8]) {
+                // Message schedule
+                let mut w = [0 as $t; $K.len()];
+                for (i, word) in block.chunks_exact(size_of::<$t>()).enumerate() {
+                    w[i] = <$t>::from_be_bytes(word.try_into().unwrap());
+                }
+                for i in 16..$K.len() {
+                    let (x, y) = (w[i - 15], w[i - 2]);
+                    let s0 = x.rotate_right($s0[0]) ^ x.rotate_right($s0[1]) ^ (x >> $s0[2]);
+                    let s1 = y.rotate_right($s1[0]) ^ y.rotate_right($s1[1]) ^ (y >> $s1[2]);
+                    w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+                }
+
+                // Rounds
+                let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+                for i in 0..$K.len() {
+                    let S1 = e.rotate_right($S1[0]) ^ e.rotate_right($S1[1]) ^ e.rotate_right($S1[2]);
+                    let ch = (e & f) ^ (!e & g);
+                    let t1 = h.wrapping_add(S1).wrapping_add(ch).wrapping_add($K[i]).wrapping_add(w[i]);
+                    let S0 = a.rotate_right($S0[0]) ^ a.rotate_right($S0[1]) ^ a.rotate_right($S0[2]);
+                    let maj = (a & b) ^ (a & c) ^ (b & c);
+                    let t2 = S0.wrapping_add(maj);
+                    (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+                }
+
+                for (word, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+                    *word = word.wrapping_add(v);
+                }
+                w.zeroize();
+            }
+
+            fn to_be_vec(state: &[$t; 8]) -> Vec<u8> {
+                state.iter().flat_map(|word| word.to_be_bytes()).collect()
+            }
+
+            fn from_be_slice(bytes: &[u8]) -> [$t; 8] {
+                let mut state = [0 as $t; 8];
+                for (word, chunk) in state.iter_mut().zip(bytes.chunks_exact(size_of::<$t>())) {
+                    *word = <$t>::from_be_bytes(chunk.try_into().unwrap());
+                }
+                state
+            }
+        }
+    };
+}
+
+impl_sha2_word!(u32, K256, [2, 13, 22], [6, 11, 25], [7, 18, 3], [17, 19, 10]);
+impl_sha2_word!(u64, K512, [28, 34, 39], [14, 18, 41], [1, 8, 7], [19, 61, 6]);
+
+// SHA2 structure.
+
+#[derive(Clone)]
+/// Structure implementing the SHA-2 Hash functions of [FIPS 180-4](https://doi.org/10.6028/NIST.FIPS.180-4),
+/// over 32-bit words (SHA-224, SHA-256) or 64-bit words (SHA-384, SHA-512, SHA-512/t).
+pub struct SHA2<W>
+where
+    W: SHA2Word
+{
+    /// Size of the output in bytes.
+    output_size: usize,
+
+    /// Initial hash value.
+    iv: [W; 8],
+
+    /// Inner state.
+    state: [W; 8],
+
+    /// Input bytes not yet forming a full block.
+    buffer: Vec<u8>,
+
+    /// Number of input bytes absorbed since the last reset.
+    length: u128,
+}
+
+impl<W> Zeroize for SHA2<W>
+where
+    W: SHA2Word
+{
+    /// Wipes the inner state and the buffered inputs.
+    fn zeroize(&mut self) {
+        self.state.zeroize();
+        self.buffer.zeroize();
+        self.length.zeroize();
+    }
+}
+
+impl<W> Drop for SHA2<W>
+where
+    W: SHA2Word
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<W> Debug for SHA2<W>
+where
+    W: SHA2Word
+{
+    /// Prints the sizes, with the inner state and the buffered inputs redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SHA2")
+            .field("block_size", &W::BLOCK_SIZE)
+            .field("output_size", &self.output_size)
+            .field("state", &format_args!("<redacted>"))
+            .field("buffer", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<W> SHA2<W>
+where
+    W: SHA2Word
+{
+    /// Build a Hash function from its initial hash value and output size.
+    fn with_iv(iv: [W; 8], output_size: usize) -> Self {
+        Self { output_size, iv, state: iv, buffer: Vec::with_capacity(W::BLOCK_SIZE), length: 0 }
+    }
+
+    /// Pad the buffered inputs with a 1 bit, zeros and the message length in bits, then return the last state.
+    /// The length field takes 8 bytes for SHA-256 and 16 bytes for SHA-512.
+    fn pad(&mut self) -> [W; 8] {
+        let len_size = W::BLOCK_SIZE/8;
+        let bits = self.length.wrapping_mul(8).to_be_bytes();
+
+        let mut tail = std::mem::take(&mut self.buffer);
+        tail.push(0x80);
+        while tail.len() % W::BLOCK_SIZE != W::BLOCK_SIZE - len_size {
+            tail.push(0x00);
+        }
+        tail.extend(&bits[16 - len_size..]);
+
+        for block in tail.chunks_exact(W::BLOCK_SIZE) {
+            W::compress(&mut self.state, block);
+        }
+        tail.zeroize();
+        self.state
+    }
+}
+
+impl SHA2<u32>
+{
+    /// SHA-224.
+    pub fn sha224() -> Self {
+        Self::with_iv(IV224, 28)
+    }
+
+    /// SHA-256.
+    pub fn sha256() -> Self {
+        Self::with_iv(IV256, 32)
+    }
+}
+
+impl SHA2<u64>
+{
+    /// SHA-384.
+    pub fn sha384() -> Self {
+        Self::with_iv(IV384, 48)
+    }
+
+    /// SHA-512.
+    pub fn sha512() -> Self {
+        Self::with_iv(IV512, 64)
+    }
+
+    /// SHA-512/224.
+    pub fn sha512_224() -> Self {
+        Self::with_iv(Self::sha512_t_iv(224), 28)
+    }
+
+    /// SHA-512/256.
+    pub fn sha512_256() -> Self {
+        Self::with_iv(Self::sha512_t_iv(256), 32)
+    }
+
+    /// SHA-512/t, for an output size of `t` bits.
+    /// `t` must be a multiple of 8 below 512, other than 384.
+    pub fn sha512_t(t: usize) -> Result<Self, Error> {
+        if t == 0 || t >= 512 || t == 384 || !t.is_multiple_of(8) {
+            return Err(Error::invalid("t", "SHA-512/t: t must be a multiple of 8 between 8 and 504, other than 384"));
+        }
+
+        Ok(Self::with_iv(Self::sha512_t_iv(t), t/8))
+    }
+
+    /// Initial hash value of SHA-512/t (Section 5.3.6 of FIPS 180-4).
+    /// The string "SHA-512/t" is hashed with SHA-512 under the initial hash value XORed with `a5` bytes.
+    fn sha512_t_iv(t: usize) -> [u64; 8] {
+        let mut generator = Self::with_iv(IV512.map(|word| word ^ 0xa5a5a5a5a5a5a5a5), 64);
+        generator.update(format!("SHA-512/{}", t));
+        generator.pad()
+    }
+}
+
+impl<W> Digest for SHA2<W>
+where
+    W: SHA2Word
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        W::BLOCK_SIZE
+    }
+
+    fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    fn reset(&mut self) {
+        self.state = self.iv;
+        self.buffer.zeroize();
+        self.length = 0;
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let mut data = data.as_ref();
+        self.length = self.length.wrapping_add(data.len() as u128);
+
+        // Complete the buffered block first
+        if !self.buffer.is_empty() {
+            let take = data.len().min(W::BLOCK_SIZE - self.buffer.len());
+            self.buffer.extend(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < W::BLOCK_SIZE {
+                return;
+            }
+            W::compress(&mut self.state, &self.buffer);
+            self.buffer.zeroize();
+        }
+
+        let mut blocks = data.chunks_exact(W::BLOCK_SIZE);
+        for block in &mut blocks {
+            W::compress(&mut self.state, block);
+        }
+        self.buffer.extend(blocks.remainder());
+    }
+
+    /// Return the big-endian digest, truncated to the output size, and reset.
+    fn finalize(&mut self) -> Result<Vec<u8>, Error> {
+        let mut output = W::to_be_vec(&self.pad());
+        output.truncate(self.output_size);
+        self.reset();
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::{rsp::parse_rsp, hex::from_hex};
+
+    fn check_vectors<W: SHA2Word>(mut digest: SHA2<W>, content: &str) {
+        let records = parse_rsp(content);
+        assert!(!records.is_empty());
+        for record in records {
+            let length: usize = record.get("Len").unwrap().parse().unwrap();
+            let message = record.get_hex("Msg").unwrap();
+            digest.update(&message[..length/8]);
+            assert!(digest.finalize().unwrap() == record.get_hex("MD").unwrap(), "Len = {}", length);
+        }
+    }
+
+    #[test]
+    fn shavs_vectors() {
+        // Records of the NIST SHAVS byte-oriented response files
+        check_vectors(SHA2::sha224(), include_str!("../../vectors/sha2/SHA224ShortMsg.rsp"));
+        check_vectors(SHA2::sha224(), include_str!("../../vectors/sha2/SHA224LongMsg.rsp"));
+        check_vectors(SHA2::sha256(), include_str!("../../vectors/sha2/SHA256ShortMsg.rsp"));
+        check_vectors(SHA2::sha256(), include_str!("../../vectors/sha2/SHA256LongMsg.rsp"));
+        check_vectors(SHA2::sha384(), include_str!("../../vectors/sha2/SHA384ShortMsg.rsp"));
+        check_vectors(SHA2::sha384(), include_str!("../../vectors/sha2/SHA384LongMsg.rsp"));
+        check_vectors(SHA2::sha512(), include_str!("../../vectors/sha2/SHA512ShortMsg.rsp"));
+        check_vectors(SHA2::sha512(), include_str!("../../vectors/sha2/SHA512LongMsg.rsp"));
+        check_vectors(SHA2::sha512_224(), include_str!("../../vectors/sha2/SHA512_224ShortMsg.rsp"));
+        check_vectors(SHA2::sha512_256(), include_str!("../../vectors/sha2/SHA512_256ShortMsg.rsp"));
+    }
+
+    #[test]
+    fn hashlib_vectors() {
+        // Generated with Python hashlib, not taken from the NIST SHAVS files
+        check_vectors(SHA2::sha224(), include_str!("../../vectors/sha2/sha224_short.rsp"));
+        check_vectors(SHA2::sha224(), include_str!("../../vectors/sha2/sha224_long.rsp"));
+        check_vectors(SHA2::sha256(), include_str!("../../vectors/sha2/sha256_short.rsp"));
+        check_vectors(SHA2::sha256(), include_str!("../../vectors/sha2/sha256_long.rsp"));
+        check_vectors(SHA2::sha384(), include_str!("../../vectors/sha2/sha384_short.rsp"));
+        check_vectors(SHA2::sha384(), include_str!("../../vectors/sha2/sha384_long.rsp"));
+        check_vectors(SHA2::sha512(), include_str!("../../vectors/sha2/sha512_short.rsp"));
+        check_vectors(SHA2::sha512(), include_str!("../../vectors/sha2/sha512_long.rsp"));
+        check_vectors(SHA2::sha512_224(), include_str!("../../vectors/sha2/sha512_224_short.rsp"));
+        check_vectors(SHA2::sha512_224(), include_str!("../../vectors/sha2/sha512_224_long.rsp"));
+        check_vectors(SHA2::sha512_256(), include_str!("../../vectors/sha2/sha512_256_short.rsp"));
+        check_vectors(SHA2::sha512_256(), include_str!("../../vectors/sha2/sha512_256_long.rsp"));
+    }
+
+    #[test]
+    fn fips_examples() {
+        // Examples of the FIPS 180-4 appendix
+        let mut sha256 = SHA2::sha256();
+        sha256.update(b"abc");
+        assert!(sha256.finalize().unwrap()
+            == from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad").unwrap());
+        sha256.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        assert!(sha256.finalize().unwrap()
+            == from_hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1").unwrap());
+
+        let mut sha512 = SHA2::sha512();
+        sha512.update(b"abc");
+        assert!(sha512.finalize().unwrap() == from_hex("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+            2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f").unwrap());
+
+        // One million repetitions of "a", fed in uneven pieces
+        let million = vec![b'a'; 1_000_000];
+        for piece in million.chunks(999) {
+            sha256.update(piece);
+        }
+        assert!(sha256.finalize().unwrap()
+            == from_hex("cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0").unwrap());
+    }
+
+    #[test]
+    fn streaming() {
+        let data: Vec<u8> = (0..300).map(|i| (i*7) as u8).collect();
+        let mut digest = SHA2::sha384();
+        digest.update(&data);
+        let expected = digest.finalize().unwrap();
+
+        for split in [0, 1, 111, 112, 128, 129, 255, 300] {
+            digest.update(&data[..split]);
+            digest.update(&data[split..]);
+            assert!(digest.finalize().unwrap() == expected);
+        }
+
+        digest.update(b"discarded");
+        digest.reset();
+        digest.update(&data);
+        assert!(digest.finalize().unwrap() == expected);
+        assert!(format!("{:?}", digest).contains("<redacted>"));
+    }
+
+    #[test]
+    fn sizes_and_truncation() {
+        assert!(SHA2::sha224().block_size() == 64 && SHA2::sha224().output_size() == 28);
+        assert!(SHA2::sha256().block_size() == 64 && SHA2::sha256().output_size() == 32);
+        assert!(SHA2::sha384().block_size() == 128 && SHA2::sha384().output_size() == 48);
+        assert!(SHA2::sha512_224().block_size() == 128 && SHA2::sha512_224().output_size() == 28);
+
+        let mut a = SHA2::sha512_t(256).unwrap();
+        let mut b = SHA2::sha512_256();
+        a.update(b"abc");
+        b.update(b"abc");
+        assert!(a.finalize().unwrap() == b.finalize().unwrap());
+
+        // SHA-512/t is not a truncation of SHA-512
+        let mut c = SHA2::sha512();
+        c.update(b"abc");
+        b.update(b"abc");
+        assert!(c.finalize().unwrap()[..32] != b.finalize().unwrap());
+
+        for t in [0, 7, 384, 512] {
+            assert!(matches!(SHA2::sha512_t(t), Err(Error::InvalidParameter { field: "t", .. })));
+        }
+    }
+}
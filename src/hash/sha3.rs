@@ -0,0 +1,512 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing SHA-3, SHAKE, cSHAKE and KMAC.
+//!
+//! Implements the Keccak-f[1600] permutation and the SHA-3 and SHAKE functions from [FIPS 202](https://doi.org/10.6028/NIST.FIPS.202)
+//! over the Sponge of the crate, and cSHAKE and KMAC from [SP 800-185](https://doi.org/10.6028/NIST.SP.800-185).
+//! The 1600-bit state is stored as 25 lanes, lane `(x, y)` being the register `x + 5y`,
+//! so that the least significant bit first absorption of the Sponge matches the bit order of FIPS 202.
+//! The permutation implementation is public.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::{Digest, ExtendableOutput, XofReader, sponge::{Sponge, SpongeParams, SpongeReader}};
+use crate::utilities::{ustates::UxN, zeroize::Zeroize};
+
+/// State of the Keccak-f[1600] permutation.
+pub type KeccakState = UxN<u64, 25>;
+
+/// Number of rounds of Keccak-f[1600].
+const ROUNDS: usize = 24;
+
+/// Round constants of the iota step.
+const ROUND_CONSTANTS: [u64; ROUNDS] = [
+    0x0000000000000001, 0x0000000000008082, 0x800000000000808a, 0x8000000080008000,
+    0x000000000000808b, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+    0x000000000000008a, 0x0000000000000088, 0x0000000080008009, 0x000000008000000a,
+    0x000000008000808b, 0x800000000000008b, 0x8000000000008089, 0x8000000000008003,
+    0x8000000000008002, 0x8000000000000080, 0x000000000000800a, 0x800000008000000a,
+    0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// Rotation offsets of the rho step, for lane `x + 5y`.
+const RHO: [u32; 25] = [
+    0, 1, 62, 28, 27,
+    36, 44, 6, 55, 20,
+    3, 10, 43, 25, 39,
+    41, 45, 15, 21, 8,
+    18, 2, 61, 56, 14,
+];
+
+/// Keccak-f[1600] permutation.
+pub fn Keccak_f1600(state: &KeccakState) -> KeccakState {
+    let mut a = state.0;
+    for rc in ROUND_CONSTANTS {
+        // Theta
+        let c: [u64; 5] = std::array::from_fn(|x| a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20]);
+        for i in 0..25 {
+            a[i] ^= c[(i + 4) % 5] ^ c[(i + 1) % 5].rotate_left(1);
+        }
+
+        // Rho and pi: lane (x, y) moves to (y, 2x + 3y)
+        let mut b = [0_u64; 25];
+        for i in 0..25 {
+            let (x, y) = (i % 5, i / 5);
+            b[y + 5*((2*x + 3*y) % 5)] = a[i].rotate_left(RHO[i]);
+        }
+
+        // Chi
+        for i in 0..25 {
+            let row = i - i % 5;
+            a[i] = b[i] ^ (!b[row + (i + 1) % 5] & b[row + (i + 2) % 5]);
+        }
+
+        // Iota
+        a[0] ^= rc;
+    }
+    UxN(a)
+}
+
+/// Permutation of the Keccak Sponges.
+fn keccak(state: KeccakState) -> KeccakState {
+    Keccak_f1600(&state)
+}
+
+impl Sponge<KeccakState>
+{
+    /// Sponge over Keccak-f[1600] with capacity `c`, a default output of `d` bits
+    /// and the given domain separation bits.
+    fn keccak(c: usize, d: usize, domain: u8, domain_len: usize) -> Result<Self, Error> {
+        let params = SpongeParams::builder().b(1600).r(1600 - c).d(d).domain(domain, domain_len).build()?;
+        Self::new(params, keccak)
+    }
+
+    /// SHA3-224.
+    pub fn sha3_224() -> Result<Self, Error> {
+        Self::keccak(448, 224, 0b10, 2)
+    }
+
+    /// SHA3-256.
+    pub fn sha3_256() -> Result<Self, Error> {
+        Self::keccak(512, 256, 0b10, 2)
+    }
+
+    /// SHA3-384.
+    pub fn sha3_384() -> Result<Self, Error> {
+        Self::keccak(768, 384, 0b10, 2)
+    }
+
+    /// SHA3-512.
+    pub fn sha3_512() -> Result<Self, Error> {
+        Self::keccak(1024, 512, 0b10, 2)
+    }
+
+    /// SHAKE128, with a 256-bit output for `finalize` and any output length through `finalize_xof`.
+    pub fn shake128() -> Result<Self, Error> {
+        Self::keccak(256, 256, 0b1111, 4)
+    }
+
+    /// SHAKE256, with a 512-bit output for `finalize` and any output length through `finalize_xof`.
+    pub fn shake256() -> Result<Self, Error> {
+        Self::keccak(512, 512, 0b1111, 4)
+    }
+}
+
+// Encodings of SP 800-185.
+
+/// Minimal big-endian encoding of `x`, at least one byte.
+fn be_bytes(x: usize) -> Vec<u8> {
+    let bytes = x.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&byte| byte == 0).count().min(bytes.len() - 1);
+    bytes[skip..].to_vec()
+}
+
+/// `left_encode(x)`: the byte length of `x` followed by its big-endian encoding.
+pub fn left_encode(x: usize) -> Vec<u8> {
+    let bytes = be_bytes(x);
+    let mut output = vec![bytes.len() as u8];
+    output.extend(bytes);
+    output
+}
+
+/// `right_encode(x)`: the big-endian encoding of `x` followed by its byte length.
+pub fn right_encode(x: usize) -> Vec<u8> {
+    let mut output = be_bytes(x);
+    output.push(output.len() as u8);
+    output
+}
+
+/// `encode_string(s)`: the bit length of `s` with `left_encode`, followed by `s`.
+pub fn encode_string(s: &[u8]) -> Vec<u8> {
+    let mut output = left_encode(s.len()*8);
+    output.extend(s);
+    output
+}
+
+/// `bytepad(x, w)`: `left_encode(w)` followed by `x`, padded with zeros to a multiple of `w` bytes.
+pub fn bytepad(x: &[u8], w: usize) -> Vec<u8> {
+    let mut output = left_encode(w);
+    output.extend(x);
+    output.resize(output.len().div_ceil(w)*w, 0x00);
+    output
+}
+
+// cSHAKE structure.
+
+#[derive(Clone)]
+/// Structure implementing cSHAKE from [SP 800-185](https://doi.org/10.6028/NIST.SP.800-185).
+///
+/// The encoded function name and customization string are absorbed again after each reset,
+/// so that the same instance can hash several messages.
+/// With an empty name and customization string, cSHAKE is SHAKE.
+pub struct CShake
+{
+    /// Underlying Keccak Sponge.
+    sponge: Sponge<KeccakState>,
+
+    /// Prefix absorbed before the message.
+    prefix: Vec<u8>,
+}
+
+impl Zeroize for CShake
+{
+    /// Wipes the Sponge and the prefix.
+    fn zeroize(&mut self) {
+        self.sponge.zeroize();
+        self.prefix.zeroize();
+    }
+}
+
+impl Drop for CShake
+{
+    /// Wipes the prefix, which holds the key for KMAC. The Sponge wipes itself.
+    fn drop(&mut self) {
+        self.prefix.zeroize();
+    }
+}
+
+impl Debug for CShake
+{
+    /// Prints the Sponge parameters, with the prefix redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CShake")
+            .field("sponge", &self.sponge)
+            .field("prefix", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl CShake
+{
+    /// Setup function, for capacity `c`, function name `name` and customization string `custom`.
+    fn new(c: usize, name: &[u8], custom: &[u8]) -> Result<Self, Error> {
+        let d = c;
+        let mut cshake = if name.is_empty() && custom.is_empty() {
+            Self { sponge: Sponge::keccak(c, d, 0b1111, 4)?, prefix: Vec::new() }
+        } else {
+            let sponge = Sponge::keccak(c, d, 0b00, 2)?;
+            let prefix = bytepad(&[encode_string(name), encode_string(custom)].concat(), sponge.block_size());
+            Self { sponge, prefix }
+        };
+        cshake.reset();
+        Ok(cshake)
+    }
+
+    /// cSHAKE128, with a 256-bit output for `finalize`.
+    pub fn cshake128(name: impl AsRef<[u8]>, custom: impl AsRef<[u8]>) -> Result<Self, Error> {
+        Self::new(256, name.as_ref(), custom.as_ref())
+    }
+
+    /// cSHAKE256, with a 512-bit output for `finalize`.
+    pub fn cshake256(name: impl AsRef<[u8]>, custom: impl AsRef<[u8]>) -> Result<Self, Error> {
+        Self::new(512, name.as_ref(), custom.as_ref())
+    }
+}
+
+impl Digest for CShake
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        self.sponge.block_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.sponge.output_size()
+    }
+
+    /// Reset the Sponge and absorb the prefix.
+    fn reset(&mut self) {
+        self.sponge.reset();
+        self.sponge.update(&self.prefix);
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.sponge.update(data);
+    }
+
+    fn finalize(&mut self) -> Result<Vec<u8>, Error> {
+        let output = self.sponge.finalize()?;
+        self.reset();
+        Ok(output)
+    }
+}
+
+impl ExtendableOutput for CShake
+{
+    type Reader = SpongeReader<KeccakState>;
+
+    fn finalize_xof(&mut self) -> Result<Self::Reader, Error> {
+        let reader = self.sponge.finalize_xof()?;
+        self.reset();
+        Ok(reader)
+    }
+}
+
+// KMAC structure.
+
+#[derive(Clone, Debug)]
+/// Structure implementing KMAC from [SP 800-185](https://doi.org/10.6028/NIST.SP.800-185).
+///
+/// `finalize` returns the KMAC tag of `output_size` bytes,
+/// and `finalize_xof` gives the output of KMACXOF, which does not depend on the output length.
+pub struct KMAC
+{
+    /// Underlying cSHAKE, with the encoded key in its prefix.
+    cshake: CShake,
+
+    /// Size of the tag in bytes.
+    output_size: usize,
+}
+
+impl KMAC
+{
+    /// Setup function, for capacity `c`.
+    fn new(c: usize, key: &[u8], custom: &[u8], output_size: usize) -> Result<Self, Error> {
+        if output_size == 0 {
+            return Err(Error::invalid("output_size", "KMAC: output size must be greater than 0"));
+        }
+
+        let mut cshake = CShake::new(c, b"KMAC", custom)?;
+        let mut encoded = encode_string(key);
+        let mut padded = bytepad(&encoded, cshake.block_size());
+        cshake.prefix.extend(&padded);
+        encoded.zeroize();
+        padded.zeroize();
+        cshake.reset();
+
+        Ok(Self { cshake, output_size })
+    }
+
+    /// KMAC128 with key `key` and customization string `custom`, for tags of `output_size` bytes.
+    pub fn kmac128(key: impl AsRef<[u8]>, custom: impl AsRef<[u8]>, output_size: usize) -> Result<Self, Error> {
+        Self::new(256, key.as_ref(), custom.as_ref(), output_size)
+    }
+
+    /// KMAC256 with key `key` and customization string `custom`, for tags of `output_size` bytes.
+    pub fn kmac256(key: impl AsRef<[u8]>, custom: impl AsRef<[u8]>, output_size: usize) -> Result<Self, Error> {
+        Self::new(512, key.as_ref(), custom.as_ref(), output_size)
+    }
+}
+
+impl Digest for KMAC
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        self.cshake.block_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    fn reset(&mut self) {
+        self.cshake.reset();
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.cshake.update(data);
+    }
+
+    /// Return the tag, bound to its length in bits.
+    fn finalize(&mut self) -> Result<Vec<u8>, Error> {
+        self.cshake.update(right_encode(self.output_size*8));
+        Ok(self.cshake.finalize_xof()?.read_vec(self.output_size))
+    }
+}
+
+impl ExtendableOutput for KMAC
+{
+    type Reader = SpongeReader<KeccakState>;
+
+    /// KMACXOF: the output length is encoded as 0.
+    fn finalize_xof(&mut self) -> Result<Self::Reader, Error> {
+        self.cshake.update(right_encode(0));
+        self.cshake.finalize_xof()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::{rsp::parse_rsp, hex::from_hex};
+
+    fn check_vectors(mut sponge: Sponge<KeccakState>, content: &str) {
+        let records = parse_rsp(content);
+        assert!(!records.is_empty());
+        for record in records {
+            let length: usize = record.get("Len").unwrap().parse().unwrap();
+            let message = record.get_hex("Msg").unwrap();
+            sponge.update(&message[..length/8]);
+            match record.get_hex("MD") {
+                Ok(digest) => assert!(sponge.finalize().unwrap() == digest, "Len = {}", length),
+                Err(_) => {
+                    let output = record.get_hex("Output").unwrap();
+                    assert!(sponge.finalize_xof().unwrap().read_vec(output.len()) == output, "Len = {}", length);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keccak_permutation() {
+        let state = Keccak_f1600(&KeccakState::zero());
+        assert!(state.0[0] == 0xf1258f7940e1dde7 && state.0[24] == 0xeaf1ff7b5ceca249);
+    }
+
+    #[test]
+    fn hashlib_vectors() {
+        // Generated with Python hashlib, not taken from the NIST SHA3VS files
+        check_vectors(Sponge::sha3_224().unwrap(), include_str!("../../vectors/sha3/sha3_224_short.rsp"));
+        check_vectors(Sponge::sha3_256().unwrap(), include_str!("../../vectors/sha3/sha3_256_short.rsp"));
+        check_vectors(Sponge::sha3_384().unwrap(), include_str!("../../vectors/sha3/sha3_384_short.rsp"));
+        check_vectors(Sponge::sha3_512().unwrap(), include_str!("../../vectors/sha3/sha3_512_short.rsp"));
+        check_vectors(Sponge::shake128().unwrap(), include_str!("../../vectors/sha3/shake128_short.rsp"));
+        check_vectors(Sponge::shake256().unwrap(), include_str!("../../vectors/sha3/shake256_short.rsp"));
+
+        for (mut shake, content) in [
+            (Sponge::shake128().unwrap(), include_str!("../../vectors/sha3/shake128_variable_out.rsp")),
+            (Sponge::shake256().unwrap(), include_str!("../../vectors/sha3/shake256_variable_out.rsp")),
+        ] {
+            for record in parse_rsp(content) {
+                let output = record.get_hex("Output").unwrap();
+                shake.update(record.get_hex("Msg").unwrap());
+                assert!(shake.finalize_xof().unwrap().read_vec(output.len()) == output);
+            }
+        }
+    }
+
+    #[test]
+    fn fips_examples() {
+        let mut sha3 = Sponge::sha3_256().unwrap();
+        assert!(sha3.block_size() == 136 && sha3.output_size() == 32);
+        assert!(sha3.finalize().unwrap()
+            == from_hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a").unwrap());
+        sha3.update(b"abc");
+        assert!(sha3.finalize().unwrap()
+            == from_hex("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532").unwrap());
+    }
+
+    #[test]
+    fn encodings() {
+        assert!(left_encode(0) == vec![1, 0] && right_encode(0) == vec![0, 1]);
+        assert!(left_encode(255) == vec![1, 255] && left_encode(256) == vec![2, 1, 0]);
+        assert!(right_encode(256) == vec![1, 0, 2]);
+        assert!(encode_string(b"") == vec![1, 0] && encode_string(b"ab") == vec![1, 16, b'a', b'b']);
+        assert!(bytepad(b"abc", 8) == vec![1, 8, b'a', b'b', b'c', 0, 0, 0]);
+        assert!(bytepad(&[0; 6], 8).len() == 8 && bytepad(&[0; 7], 8).len() == 16);
+    }
+
+    #[test]
+    fn cshake_samples() {
+        // Samples #1 to #4 of the NIST cSHAKE examples
+        let short = [0_u8, 1, 2, 3];
+        let long: Vec<u8> = (0..=0xc7).collect();
+        let cases: [(&[u8], &str); 2] = [
+            (&short, "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5"),
+            (&long, "c5221d50e4f822d96a2e8881a961420f294b7b24fe3d2094baed2c6524cc166b"),
+        ];
+        let mut cshake = CShake::cshake128(b"", b"Email Signature").unwrap();
+        for (data, output) in cases {
+            cshake.update(data);
+            assert!(cshake.finalize().unwrap() == from_hex(output).unwrap());
+        }
+
+        let cases: [(&[u8], &str); 2] = [
+            (&short, "d008828e2b80ac9d2218ffee1d070c48b8e4c87bff32c9699d5b6896eee0edd1\
+                64020e2be0560858d9c00c037e34a96937c561a74c412bb4c746469527281c8c"),
+            (&long, "07dc27b11e51fbac75bc7b3c1d983e8b4b85fb1defaf218912ac864302730917\
+                27f42b17ed1df63e8ec118f04b23633c1dfb1574c8fb55cb45da8e25afb092bb"),
+        ];
+        let mut cshake = CShake::cshake256(b"", b"Email Signature").unwrap();
+        for (data, output) in cases {
+            cshake.update(data);
+            assert!(cshake.finalize().unwrap() == from_hex(output).unwrap());
+        }
+
+        // Without name and customization string, cSHAKE is SHAKE
+        let mut shake = Sponge::shake128().unwrap();
+        let mut cshake = CShake::cshake128(b"", b"").unwrap();
+        shake.update(b"abc");
+        cshake.update(b"abc");
+        assert!(shake.finalize_xof().unwrap().read_vec(200) == cshake.finalize_xof().unwrap().read_vec(200));
+    }
+
+    #[test]
+    fn kmac_samples() {
+        // Samples #1 to #6 of the NIST KMAC examples
+        let key: Vec<u8> = (0x40..=0x5f).collect();
+        let short = [0_u8, 1, 2, 3];
+        let long: Vec<u8> = (0..=0xc7).collect();
+        let tagged = b"My Tagged Application";
+
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (&short, b"", "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e"),
+            (&short, tagged, "3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5"),
+            (&long, tagged, "1f5b4e6cca02209e0dcb5ca635b89a15e271ecc760071dfd805faa38f9729230"),
+        ];
+        for (data, custom, tag) in cases {
+            let mut kmac = KMAC::kmac128(&key, custom, 32).unwrap();
+            kmac.update(data);
+            assert!(kmac.finalize().unwrap() == from_hex(tag).unwrap());
+        }
+
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (&short, tagged, "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7\
+                f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd"),
+            (&long, b"", "75358cf39e41494e949707927cee0af20a3ff553904c86b08f21cc414bcfd691\
+                589d27cf5e15369cbbff8b9a4c2eb17800855d0235ff635da82533ec6b759b69"),
+            (&long, tagged, "b58618f71f92e1d56c1b8c55ddd7cd188b97b4ca4d99831eb2699a837da2e4d9\
+                70fbacfde50033aea585f1a2708510c32d07880801bd182898fe476876fc8965"),
+        ];
+        for (data, custom, tag) in cases {
+            let mut kmac = KMAC::kmac256(&key, custom, 64).unwrap();
+            kmac.update(data);
+            assert!(kmac.finalize().unwrap() == from_hex(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn kmac_reuse_and_xof() {
+        let mut kmac = KMAC::kmac128(b"key", b"", 16).unwrap();
+        kmac.update(b"message");
+        let tag = kmac.finalize().unwrap();
+        assert!(tag.len() == 16);
+        kmac.update(b"message");
+        assert!(kmac.finalize().unwrap() == tag);
+
+        // The tag length is bound to the output, unlike KMACXOF
+        let mut longer = KMAC::kmac128(b"key", b"", 32).unwrap();
+        longer.update(b"message");
+        assert!(longer.finalize().unwrap()[..16] != tag);
+        kmac.update(b"message");
+        longer.update(b"message");
+        assert!(kmac.finalize_xof().unwrap().read_vec(32) == longer.finalize_xof().unwrap().read_vec(32));
+
+        assert!(matches!(KMAC::kmac256(b"key", b"", 0), Err(Error::InvalidParameter { field: "output_size", .. })));
+        assert!(format!("{:?}", kmac).contains("<redacted>"));
+    }
+}
@@ -8,22 +8,24 @@
 //! as found in [LINUXv6.11](https://elixir.bootlin.com/linux/v6.11.5/source/include/linux/siphash.h#L157).
 //! Both permutation implementations are public.
 
-use std::io::Error;
+use crate::error::Error;
 use std::num::Wrapping;
 use getset::Getters;
-use std::ops::{BitOr, BitXor, Add, Sub, Shl, Shr};
+use std::ops::{BitXor, Add};
+use std::fmt::{self, Debug, Formatter};
 
-use crate::utilities::{ustates::Ux4, bitops::urot};
+use crate::utilities::{ustates::Ux4, bitops::Rotate, zeroize::Zeroize};
 use crate::hash::Digest;
+use crate::mac::Mac;
 
 // SipHash structure.
 
 #[allow(dead_code)]
-#[derive(Getters, Clone, Debug)]
+#[derive(Getters, Clone)]
 /// Structure implementing [SipHash](https://doi.org/10.1007/978-3-642-34931-7_28)
 pub struct SipHash<U>
 where
-    U: Clone
+    U: Clone + Zeroize
 {
     /// Size of the state in bits.
     #[getset(get = "pub")]
@@ -43,13 +45,57 @@ where
     /// Permutation function.
     perm: fn(Ux4<U>) -> Ux4<U>,
 
-    /// Input buffer.
-    inputs: Vec<u8>
+    /// Key, as two words. Zero when used as an unkeyed Hash function.
+    key: [U; 2],
+
+    /// Input bytes not forming a full word yet.
+    inputs: Vec<u8>,
+
+    /// Number of input bytes since the last reset.
+    length: usize,
+}
+
+impl<U> Zeroize for SipHash<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the key, the inner state and the buffered inputs.
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+        self.state.zeroize();
+        self.inputs.zeroize();
+    }
+}
+
+impl<U> Drop for SipHash<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<U> Debug for SipHash<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the parameters, with the key, the inner state and the buffered inputs redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SipHash")
+            .field("n", &self.n)
+            .field("c", &self.c)
+            .field("d", &self.d)
+            .field("key", &format_args!("<redacted>"))
+            .field("state", &format_args!("<redacted>"))
+            .field("inputs", &format_args!("<redacted>"))
+            .finish()
+    }
 }
 
 impl<U> Default for SipHash<U>
 where
-    U: Clone + Default,
+    U: Clone + Default + Zeroize,
 {
     /// Default constructor for SipHash.
     fn default() -> Self {
@@ -60,7 +106,9 @@ where
             d: 4,
             state: Ux4::default(),
             perm: identity_fn::<U>,
+            key: [U::default(), U::default()],
             inputs: Vec::new(),
+            length: 0,
         }
     }
 }
@@ -70,9 +118,7 @@ where
 /// General SipHash permutation function for Ux4 states.
 fn SipRound<U>(state: &Ux4<U>, params: [usize;5]) -> Ux4<U>
 where 
-    U: Copy + From<u8> + BitOr<Output = U> + BitXor<Output = U>
-    + Sub<Output = U> + Add<Output = U> + Shl<usize, Output = U>
-    + Shr<usize, Output = U> + std::fmt::UpperHex,
+    U: Copy + From<u8> + Rotate + BitXor<Output = U> + std::fmt::UpperHex,
     Wrapping<U>: Add<Output = Wrapping<U>>
 {
     let [mut p0, mut p1, mut p2, mut p3] = state.get();
@@ -80,15 +126,15 @@ where
 
     // Wrapping is used to avoid overflow checks
     p0 = (Wrapping(p0) + Wrapping(p1)).0;
-    p1 = urot::<U>(p1, a) ^ p0;
-    p0 = urot::<U>(p0, e);
+    p1 = p1.rotl(a) ^ p0;
+    p0 = p0.rotl(e);
     p2 = (Wrapping(p2) + Wrapping(p3)).0;
-    p3 = urot::<U>(p3, b) ^ p2;
+    p3 = p3.rotl(b) ^ p2;
     p0 = (Wrapping(p0) + Wrapping(p3)).0;
-    p3 = urot::<U>(p3, d) ^ p0;
+    p3 = p3.rotl(d) ^ p0;
     p2 = (Wrapping(p2) + Wrapping(p1)).0;
-    p1 = urot::<U>(p1, c) ^ p2;
-    p2 = urot::<U>(p2, e);
+    p1 = p1.rotl(c) ^ p2;
+    p2 = p2.rotl(e);
 
     Ux4::<U>::new([p0, p1, p2, p3])
 }
@@ -111,21 +157,29 @@ impl SipHash<u32>
     /// Setup function for Half-SipHash with u32x4 state.
     /// The initial state is set using the constants from the Linux kernel [LINUXv6.11].
     pub fn new(params: Vec<usize>) -> Result<Self, Error> {
-        assert!(params.len() == 2, "SipHash Setup: wrong number of parameters. Expected 2, got {}.", params.len());
+        if params.len() != 2 {
+            return Err(Error::BadLength { field: "params", expected: 2, got: params.len() });
+        }
         let (c, d) = (params[0], params[1]);
-        assert!(c > 0, "SipHash Setup: number of compression rounds c must be greater than 0.");
-        assert!(d > 0, "SipHash Setup: number of finalization rounds d must be greater than 0.");
+        if c == 0 {
+            return Err(Error::invalid("c", "number of compression rounds c must be greater than 0"));
+        }
+        if d == 0 {
+            return Err(Error::invalid("d", "number of finalization rounds d must be greater than 0"));
+        }
 
         fn perm (state: Ux4::<u32>) -> Ux4::<u32> {Half_SipHash_perm(&state)}
         let state = Ux4::<u32>::new([0x0, 0x0, 0x6c796765, 0x74656462]);
 
         Ok(Self{
             n: 32_usize,
-            c: c,
-            d: d,
-            state: state,
-            perm: perm,
-            inputs: Vec::new()
+            c,
+            d,
+            state,
+            perm,
+            key: [0, 0],
+            inputs: Vec::new(),
+            length: 0,
         })
     }
 }
@@ -134,13 +188,19 @@ impl SipHash<u32>
 
 impl SipHash<u64>
 {
-    /// Setup function for SipHash with u64x4 state.
+    /// Setup function for SipHash with u64x4 state, with a zero key.
     /// The initial state is set using the constants from [AB2012](https://doi.org/10.1007/978-3-642-34931-7_28).
     pub fn new(params: Vec<usize>) -> Result<Self, Error> {
-        assert!(params.len() == 2, "SipHash Setup: wrong number of parameters. Expected 2, got {}.", params.len());
+        if params.len() != 2 {
+            return Err(Error::BadLength { field: "params", expected: 2, got: params.len() });
+        }
         let (c, d) = (params[0], params[1]);
-        assert!(c > 0, "SipHash Setup: number of compression rounds c must be greater than 0.");
-        assert!(d > 0, "SipHash Setup: number of finalization rounds d must be greater than 0.");
+        if c == 0 {
+            return Err(Error::invalid("c", "number of compression rounds c must be greater than 0"));
+        }
+        if d == 0 {
+            return Err(Error::invalid("d", "number of finalization rounds d must be greater than 0"));
+        }
 
         fn perm (state: Ux4::<u64>) -> Ux4::<u64> {SipHash_perm(&state)}
         let state = Ux4::<u64>::new(
@@ -150,31 +210,161 @@ impl SipHash<u64>
 
         Ok(Self{
             n: 64_usize,
-            c: c,
-            d: d,
-            state: state,
-            perm: perm,
-            inputs: Vec::new()
+            c,
+            d,
+            state,
+            perm,
+            key: [0, 0],
+            inputs: Vec::new(),
+            length: 0,
         })
     }
+
+    /// Compression of one message word `m`, with c rounds.
+    fn compress(&mut self, m: u64) {
+        let [v0, v1, v2, v3] = self.state.get();
+        self.state.set([v0, v1, v2, v3 ^ m]);
+        for _ in 0..self.c {
+            self.state = (self.perm)(self.state);
+        }
+        let [v0, v1, v2, v3] = self.state.get();
+        self.state.set([v0 ^ m, v1, v2, v3]);
+    }
 }
+
 impl Digest for SipHash<u64>
 {
     type Output = u64;
 
+    fn block_size(&self) -> usize {
+        8
+    }
+
+    fn output_size(&self) -> usize {
+        8
+    }
+
+    /// Reset to the initial state under the current key.
     fn reset(&mut self) {
+        let [k0, k1] = self.key;
         self.state.set(
-            [0x736f6d6570736575, 0x646f72616e646f6d,
-            0x6c7967656e657261, 0x7465646279746573]
+            [k0 ^ 0x736f6d6570736575, k1 ^ 0x646f72616e646f6d,
+            k0 ^ 0x6c7967656e657261, k1 ^ 0x7465646279746573]
         );
+        self.inputs.zeroize();
         self.inputs.clear();
+        self.length = 0;
     }
 
+    /// Collect inputs, compressing each full 8-byte little-endian word.
     fn update(&mut self, data: impl AsRef<[u8]>) {
-        self.inputs.extend(data.as_ref());
+        let data = data.as_ref();
+        self.length = self.length.wrapping_add(data.len());
+        self.inputs.extend(data);
+
+        let full = self.inputs.len() - self.inputs.len() % 8;
+        for i in (0..full).step_by(8) {
+            let mut word = [0_u8; 8];
+            word.copy_from_slice(&self.inputs[i..i + 8]);
+            self.compress(u64::from_le_bytes(word));
+            word.zeroize();
+        }
+        self.inputs.drain(..full).for_each(drop);
     }
 
+    /// Return v0 ^ v1 ^ v2 ^ v3 after the last word, holding the input length modulo 256,
+    /// and d finalization rounds. Reset afterwards.
     fn finalize(&mut self) -> Result<Self::Output, Error> {
-        todo!();
+        let mut last = [0_u8; 8];
+        last[..self.inputs.len()].copy_from_slice(&self.inputs);
+        last[7] = self.length as u8;
+        self.compress(u64::from_le_bytes(last));
+        last.zeroize();
+
+        let [v0, v1, v2, v3] = self.state.get();
+        self.state.set([v0, v1, v2 ^ 0xff, v3]);
+        for _ in 0..self.d {
+            self.state = (self.perm)(self.state);
+        }
+        let [v0, v1, v2, v3] = self.state.get();
+
+        self.reset();
+        Ok(v0 ^ v1 ^ v2 ^ v3)
+    }
+}
+
+impl Mac for SipHash<u64>
+{
+    /// Numbers of compression and finalization rounds, as `[c, d]`.
+    type Params = Vec<usize>;
+
+    /// Setup function for SipHash-c-d keyed with a 16-byte `key`.
+    fn new_with_key(params: Vec<usize>, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let key = key.as_ref();
+        if key.len() != 16 {
+            return Err(Error::BadLength { field: "key", expected: 16, got: key.len() });
+        }
+
+        let mut siphash = Self::new(params)?;
+        let mut word = [0_u8; 8];
+        for (i, chunk) in key.chunks_exact(8).enumerate() {
+            word.copy_from_slice(chunk);
+            siphash.key[i] = u64::from_le_bytes(word);
+        }
+        word.zeroize();
+        siphash.reset();
+        Ok(siphash)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::hex::from_hex;
+
+    fn siphash24() -> SipHash<u64> {
+        SipHash::new_with_key(vec![2, 4], (0x00..=0x0f).collect::<Vec<u8>>()).unwrap()
+    }
+
+    #[test]
+    fn reference_vectors() {
+        // Key 00..0f and messages 00..(len-1), from the SipHash-2-4 reference implementation
+        let cases = [
+            (0, "310e0edd47db6f72"),
+            (8, "6224939a79f5f593"),
+            (15, "e545be4961ca29a1"),
+            (63, "724506eb4c328a95"),
+        ];
+
+        let mut siphash = siphash24();
+        for (len, tag) in cases {
+            let message: Vec<u8> = (0..len).collect();
+            siphash.update(&message);
+            assert!(siphash.finalize_bytes().unwrap() == from_hex(tag).unwrap());
+
+            // Byte by byte, with a reset to the keyed state in between
+            message.iter().for_each(|byte| siphash.update([*byte]));
+            siphash.verify(from_hex(tag).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn keying() {
+        let mut siphash = siphash24();
+        siphash.update(b"message");
+        let tag = siphash.finalize_bytes().unwrap();
+        siphash.update(b"message");
+        assert!(matches!(siphash.verify([&tag[..7], &[tag[7] ^ 1]].concat()), Err(Error::AuthenticationFailure)));
+
+        // The unkeyed Hash function uses the zero key
+        let mut unkeyed = SipHash::<u64>::new(vec![2, 4]).unwrap();
+        let mut zero = SipHash::new_with_key(vec![2, 4], [0_u8; 16]).unwrap();
+        unkeyed.update(b"message");
+        zero.update(b"message");
+        assert!(unkeyed.finalize().unwrap() == zero.finalize().unwrap());
+        assert!(format!("{:?}", zero).contains("<redacted>"));
+
+        assert!(matches!(SipHash::new_with_key(vec![2, 4], [0_u8; 15]), Err(Error::BadLength { field: "key", .. })));
+        assert!(matches!(SipHash::new_with_key(vec![0, 4], [0_u8; 16]), Err(Error::InvalidParameter { field: "c", .. })));
     }
 }
\ No newline at end of file
@@ -0,0 +1,537 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the Sponge construction as a Hash function and an extendable-output function.
+//!
+//! Based on the Sponge construction of [BDPV2011](https://keccak.team/files/SpongeFunctions.pdf),
+//! over any permutation of a state type `U` and any rate `r`.
+//! Inputs are absorbed least significant bit first in the outer part of the state,
+//! which is stored in the lower bits as in the other constructions of the crate.
+//! The Sponge is instantiated with the SipHash and Half-SipHash permutations,
+//! and with Keccak-f[1600] for SHA-3 in the `sha3` module.
+
+use crate::error::Error;
+use getset::Getters;
+use std::{ops::{BitXor, BitAnd, BitOr, Not, Sub, Shl}, convert::From};
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::{Digest, ExtendableOutput, XofReader, siphash::{SipHash_perm, Half_SipHash_perm}};
+use crate::utilities::{ToLeBytes, StateWidth, ustates::Ux4, bitbuf::{BitBuf, BitOrder}, zeroize::Zeroize};
+use crate::utilities::bitops::{low_mask, check_width};
+
+/// Number of SipHash rounds per call of the built-in Sponge permutations,
+/// as in the finalization of SipHash-2-4.
+const SIPHASH_ROUNDS: usize = 4;
+
+/// Permutation of the SipHash Sponge.
+fn siphash_rounds(mut state: Ux4<u64>) -> Ux4<u64> {
+    for _ in 0..SIPHASH_ROUNDS {state = SipHash_perm(&state);}
+    state
+}
+
+/// Permutation of the Half-SipHash Sponge.
+fn half_siphash_rounds(mut state: Ux4<u32>) -> Ux4<u32> {
+    for _ in 0..SIPHASH_ROUNDS {state = Half_SipHash_perm(&state);}
+    state
+}
+
+// Sponge parameters.
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+/// Padding rule applied after the domain separation bits.
+pub enum Padding {
+    /// Simple padding `10*`: a 1 bit followed by the minimum number of 0 bits.
+    Pad10,
+
+    /// Multi-rate padding `10*1`: a 1 bit, the minimum number of 0 bits and a final 1 bit, as in Keccak.
+    #[default]
+    Pad101,
+}
+
+#[derive(Getters, Clone, Copy, Debug, PartialEq, Eq)]
+/// Parameters of the Sponge, built with [`SpongeParams::builder`] or taken from a preset.
+pub struct SpongeParams
+{
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    b: usize,
+
+    /// Size of the outer part (b = c + r).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Size of the digest in bits.
+    #[getset(get = "pub")]
+    d: usize,
+
+    /// Padding rule.
+    #[getset(get = "pub")]
+    padding: Padding,
+
+    /// Domain separation bits, appended to the message before the padding, least significant bit first.
+    #[getset(get = "pub")]
+    domain: u8,
+
+    /// Number of domain separation bits.
+    #[getset(get = "pub")]
+    domain_len: usize,
+}
+
+impl SpongeParams
+{
+    /// Start building parameters. The padding defaults to `Pad101` and the domain to no bits,
+    /// the other fields must be set.
+    pub fn builder() -> SpongeParamsBuilder {
+        SpongeParamsBuilder::default()
+    }
+
+    /// 256-bit state with r = 64 and a 256-bit digest, for the SipHash permutation.
+    pub fn siphash_256() -> Self {
+        Self { b: 256, r: 64, d: 256, padding: Padding::Pad101, domain: 0, domain_len: 0 }
+    }
+
+    /// 128-bit state with r = 32 and a 128-bit digest, for the Half-SipHash permutation.
+    pub fn half_siphash_128() -> Self {
+        Self { b: 128, r: 32, d: 128, padding: Padding::Pad101, domain: 0, domain_len: 0 }
+    }
+
+    /// Check the relations between the parameters.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.r == 0 {
+            return Err(Error::invalid("r", "rate r must be greater than 0"));
+        }
+        if self.r >= self.b {
+            return Err(Error::invalid("r", "rate r must be less than the state size b"));
+        }
+        if self.d == 0 || !self.d.is_multiple_of(8) {
+            return Err(Error::invalid("d", "digest size d must be a positive multiple of 8"));
+        }
+        if self.domain_len > 8 || (self.domain_len < 8 && self.domain >> self.domain_len != 0) {
+            return Err(Error::invalid("domain", "domain separation must fit in domain_len bits, at most 8"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Builder for [`SpongeParams`].
+pub struct SpongeParamsBuilder
+{
+    b: Option<usize>,
+    r: Option<usize>,
+    d: Option<usize>,
+    padding: Padding,
+    domain: u8,
+    domain_len: usize,
+}
+
+impl Default for SpongeParamsBuilder
+{
+    fn default() -> Self {
+        Self { b: None, r: None, d: None, padding: Padding::Pad101, domain: 0, domain_len: 0 }
+    }
+}
+
+impl SpongeParamsBuilder
+{
+    /// Set the size of the state in bits.
+    pub fn b(mut self, b: usize) -> Self {
+        self.b = Some(b);
+        self
+    }
+
+    /// Set the rate.
+    pub fn r(mut self, r: usize) -> Self {
+        self.r = Some(r);
+        self
+    }
+
+    /// Set the size of the digest in bits.
+    pub fn d(mut self, d: usize) -> Self {
+        self.d = Some(d);
+        self
+    }
+
+    /// Set the padding rule.
+    pub fn padding(mut self, padding: Padding) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the `len` domain separation bits, taken from `bits` least significant bit first.
+    pub fn domain(mut self, bits: u8, len: usize) -> Self {
+        self.domain = bits;
+        self.domain_len = len;
+        self
+    }
+
+    /// Check the parameters and build them.
+    pub fn build(self) -> Result<SpongeParams, Error> {
+        let params = SpongeParams {
+            b: self.b.ok_or_else(|| Error::invalid("b", "state size b must be set"))?,
+            r: self.r.ok_or_else(|| Error::invalid("r", "rate r must be set"))?,
+            d: self.d.ok_or_else(|| Error::invalid("d", "digest size d must be set"))?,
+            padding: self.padding,
+            domain: self.domain,
+            domain_len: self.domain_len,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+// Sponge structure.
+
+#[derive(Getters, Clone)]
+/// Structure implementing the Sponge construction of [BDPV2011](https://keccak.team/files/SpongeFunctions.pdf).
+///
+/// Note that the state is reversed for easier use of the outputs.
+/// The outer part is stored in the lower bits.
+pub struct Sponge<U>
+where
+    U: Clone + Zeroize
+{
+    /// Parameters of the Sponge.
+    #[getset(get = "pub")]
+    params: SpongeParams,
+
+    /// Outer part mask.
+    mask: U,
+
+    /// Permutation function.
+    perm: fn(U) -> U,
+
+    /// Inner state.
+    state: U,
+
+    /// Input bits not absorbed yet, less than `r`.
+    pending: BitBuf,
+}
+
+impl<U> Zeroize for Sponge<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the inner state and the pending inputs.
+    fn zeroize(&mut self) {
+        self.state.zeroize();
+        self.pending.zeroize();
+    }
+}
+
+impl<U> Drop for Sponge<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<U> Debug for Sponge<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the parameters, with the inner state and the pending inputs redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sponge")
+            .field("params", &self.params)
+            .field("state", &format_args!("<redacted>"))
+            .field("pending", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<U> Sponge<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    /// Setup function.
+    pub fn new(params: SpongeParams, perm: fn(U) -> U) -> Result<Self, Error> {
+        params.validate()?;
+        check_width::<U>("b", params.b)?;
+
+        Ok(Self {
+            params,
+            mask: low_mask(params.r),
+            perm,
+            state: 0_u8.into(),
+            pending: BitBuf::new(BitOrder::LsbFirst),
+        })
+    }
+
+    /// Absorb one block of at most `r` bits.
+    fn absorb(&mut self, block: &BitBuf) {
+        self.state = (self.perm)(self.state ^ block.to_uint::<U>());
+    }
+
+    /// Append the domain separation bits and the padding to the pending inputs, and absorb them.
+    fn pad(&mut self) {
+        let r = self.params.r;
+        let mut last = std::mem::take(&mut self.pending);
+        last.append(&self.params.domain, self.params.domain_len);
+
+        last.push(true);
+        let end = match self.params.padding {
+            Padding::Pad10 => 0,
+            Padding::Pad101 => 1,
+        };
+        while !(last.len() + end).is_multiple_of(r) {
+            last.push(false);
+        }
+        if end == 1 {
+            last.push(true);
+        }
+
+        for block in last.chunks(r) {
+            self.absorb(&block);
+        }
+    }
+}
+
+impl Sponge<Ux4<u64>>
+{
+    /// Sponge over the SipHash permutation, with the `siphash_256` parameters.
+    pub fn siphash() -> Result<Self, Error> {
+        Self::new(SpongeParams::siphash_256(), siphash_rounds)
+    }
+}
+
+impl Sponge<Ux4<u32>>
+{
+    /// Sponge over the Half-SipHash permutation, with the `half_siphash_128` parameters.
+    pub fn half_siphash() -> Result<Self, Error> {
+        Self::new(SpongeParams::half_siphash_128(), half_siphash_rounds)
+    }
+}
+
+impl<U> Digest for Sponge<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    type Output = Vec<u8>;
+
+    /// Rate in bytes, rounded up.
+    fn block_size(&self) -> usize {
+        self.params.r.div_ceil(8)
+    }
+
+    fn output_size(&self) -> usize {
+        self.params.d / 8
+    }
+
+    fn reset(&mut self) {
+        self.state.zeroize();
+        self.pending.zeroize();
+    }
+
+    /// Absorb all the complete blocks, and keep the remaining bits for the next call.
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        let mut inputs = std::mem::take(&mut self.pending);
+        inputs.append_bytes(data, data.len() * 8);
+
+        for block in inputs.chunks(self.params.r) {
+            if block.len() == self.params.r {
+                self.absorb(&block);
+            } else {
+                self.pending = block;
+            }
+        }
+    }
+
+    /// Return the first `d` bits of the output. The Sponge is reset afterwards.
+    fn finalize(&mut self) -> Result<Self::Output, Error> {
+        let d = self.params.d;
+        Ok(self.finalize_xof()?.read_vec(d / 8))
+    }
+}
+
+impl<U> ExtendableOutput for Sponge<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    type Reader = SpongeReader<U>;
+
+    /// Pad the inputs and return a reader squeezing the state. The Sponge is reset afterwards.
+    fn finalize_xof(&mut self) -> Result<Self::Reader, Error> {
+        self.pad();
+        let reader = SpongeReader {
+            r: self.params.r,
+            mask: self.mask,
+            perm: self.perm,
+            state: self.state,
+            started: false,
+            buffer: BitBuf::new(BitOrder::LsbFirst),
+        };
+        self.reset();
+        Ok(reader)
+    }
+}
+
+// Sponge output reader.
+
+#[derive(Clone)]
+/// Reader squeezing `r` bits of the Sponge state per permutation call.
+///
+/// The output blocks are concatenated without gaps, so that reads of any size give the same stream.
+pub struct SpongeReader<U>
+where
+    U: Clone + Zeroize
+{
+    /// Size of the outer part.
+    r: usize,
+
+    /// Outer part mask.
+    mask: U,
+
+    /// Permutation function.
+    perm: fn(U) -> U,
+
+    /// Inner state.
+    state: U,
+
+    /// Whether the first block has been output.
+    started: bool,
+
+    /// Output bits not read yet.
+    buffer: BitBuf,
+}
+
+impl<U> Drop for SpongeReader<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.state.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+impl<U> Debug for SpongeReader<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the rate, with the inner state and the buffered output redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpongeReader")
+            .field("r", &self.r)
+            .field("state", &format_args!("<redacted>"))
+            .field("buffer", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<U> XofReader for SpongeReader<U>
+where
+    U: Copy + Zeroize + BitAnd<Output = U> + ToLeBytes
+{
+    fn read(&mut self, buffer: &mut [u8]) {
+        while self.buffer.len() < buffer.len() * 8 {
+            if self.started {
+                self.state = (self.perm)(self.state);
+            }
+            self.started = true;
+            self.buffer.append(&(self.state & self.mask), self.r);
+        }
+        buffer.copy_from_slice(&self.buffer.take_bytes(buffer.len()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn identity(state: u64) -> u64 {state}
+
+    fn toy(padding: Padding, domain: u8, domain_len: usize) -> Sponge<u64> {
+        let params = SpongeParams::builder().b(64).r(8).d(16).padding(padding).domain(domain, domain_len).build();
+        Sponge::new(params.unwrap(), identity).unwrap()
+    }
+
+    #[test]
+    fn padding_and_domain() {
+        // With the identity permutation, the output is the last padded block
+        assert!(toy(Padding::Pad101, 0, 0).finalize().unwrap() == vec![0x81, 0x81]);
+        assert!(toy(Padding::Pad10, 0, 0).finalize().unwrap() == vec![0x01, 0x01]);
+        assert!(toy(Padding::Pad101, 0b10, 2).finalize().unwrap() == vec![0x86, 0x86]);
+        assert!(toy(Padding::Pad10, 0b10, 2).finalize().unwrap() == vec![0x06, 0x06]);
+
+        // Seven bits of padding do not fit in the block: a second block is absorbed
+        let mut sponge = toy(Padding::Pad101, 0b1111111, 7);
+        assert!(sponge.finalize().unwrap() == vec![0xff ^ 0x80, 0xff ^ 0x80]);
+    }
+
+    #[test]
+    fn streaming() {
+        let message: Vec<u8> = (0..200).map(|i| (i * 7) as u8).collect();
+        let params = SpongeParams::builder().b(256).r(61).d(256).build().unwrap();
+
+        for mut sponge in [Sponge::siphash().unwrap(), Sponge::new(params, siphash_rounds).unwrap()] {
+            sponge.update(&message);
+            let reference = sponge.finalize().unwrap();
+            assert!(reference.len() == 32);
+
+            for split in [0, 1, 7, 8, 61, 100, 199] {
+                sponge.update(&message[..split]);
+                sponge.update(&message[split..]);
+                assert!(sponge.finalize().unwrap() == reference);
+            }
+            assert!(sponge.finalize().unwrap() != reference);
+        }
+    }
+
+    #[test]
+    fn extendable_output() {
+        let mut sponge = Sponge::half_siphash().unwrap();
+        sponge.update(b"extendable output");
+        let digest = sponge.finalize().unwrap();
+        assert!(digest.len() == 16);
+
+        sponge.update(b"extendable output");
+        let mut reader = sponge.finalize_xof().unwrap();
+        let long = reader.read_vec(100);
+        assert!(long[..16] == digest);
+
+        sponge.update(b"extendable output");
+        let mut reader = sponge.finalize_xof().unwrap();
+        let mut parts = reader.read_vec(3);
+        parts.extend(reader.read_vec(0));
+        parts.extend(reader.read_vec(61));
+        parts.extend(reader.read_vec(36));
+        assert!(parts == long);
+    }
+
+    #[test]
+    fn separation() {
+        let hash = |params: SpongeParams, data: &[u8]| {
+            let mut sponge = Sponge::new(params, siphash_rounds).unwrap();
+            sponge.update(data);
+            sponge.finalize().unwrap()
+        };
+        let base = SpongeParams::siphash_256();
+        let reference = hash(base, b"");
+        assert!(hash(base, b"\0") != reference);
+        assert!(hash(SpongeParams::builder().b(256).r(64).d(256).domain(1, 1).build().unwrap(), b"") != reference);
+        assert!(hash(SpongeParams::builder().b(256).r(64).d(256).padding(Padding::Pad10).build().unwrap(), b"")
+            != reference);
+    }
+
+    #[test]
+    fn setup_errors() {
+        let result = SpongeParams::builder().b(256).r(256).d(256).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+        let result = SpongeParams::builder().b(256).r(64).d(12).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "d", .. })));
+        let result = SpongeParams::builder().b(256).r(64).d(256).domain(0b100, 2).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "domain", .. })));
+        let result = Sponge::new(SpongeParams::siphash_256(), identity);
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "b", .. })));
+
+        let debug = format!("{:?}", Sponge::siphash().unwrap());
+        assert!(debug.contains("<redacted>"));
+    }
+}
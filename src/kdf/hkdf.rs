@@ -0,0 +1,161 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing HKDF (HMAC-based Key Derivation Function).
+//!
+//! Based on [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869), generic over the `Digest` trait.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::{Digest, hmac::HMAC};
+use crate::kdf::KDF;
+
+// HKDF structure.
+
+#[derive(Clone)]
+/// Structure implementing HKDF from [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869).
+///
+/// The Hash function is only used as a template for the HMAC instances,
+/// the pseudorandom key is a byte string of `HashLen` bytes.
+pub struct HKDF<D>
+{
+    /// Underlying Hash function.
+    digest: D,
+}
+
+impl<D> Debug for HKDF<D>
+where
+    D: Digest
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HKDF")
+            .field("block_size", &self.digest.block_size())
+            .field("output_size", &self.digest.output_size())
+            .finish()
+    }
+}
+
+impl<D> HKDF<D>
+where
+    D: Digest + Clone
+{
+    /// Setup function, over the Hash function `digest`.
+    pub fn new(digest: D) -> Result<Self, Error> {
+        if digest.output_size() == 0 {
+            return Err(Error::invalid("digest", "output size of the Hash function must be greater than 0"));
+        }
+
+        Ok(Self { digest })
+    }
+}
+
+impl<D> KDF for HKDF<D>
+where
+    D: Digest + Clone
+{
+    type Key = Vec<u8>;
+
+    /// Extract function.
+    /// PRK = HMAC(salt, IKM), an empty salt being replaced by `HashLen` zero bytes.
+    fn extract(&mut self, salt: impl AsRef<[u8]>, ikm: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let salt = salt.as_ref();
+        if salt.is_empty() {
+            HMAC::mac(self.digest.clone(), vec![0x00; self.digest.output_size()], ikm)
+        } else {
+            HMAC::mac(self.digest.clone(), salt, ikm)
+        }
+    }
+
+    /// Expand function.
+    /// T(i) = HMAC(PRK, T(i-1) || info || i), for at most 255 blocks of `HashLen` bytes.
+    fn expand(&mut self, prk: &Vec<u8>, info: impl AsRef<[u8]>, length: usize) -> Result<Vec<u8>, Error> {
+        let hash_len = self.digest.output_size();
+        if prk.len() < hash_len {
+            return Err(Error::invalid("prk", format!("HKDF: pseudorandom key must be at least {} bytes", hash_len)));
+        }
+        if length > 255*hash_len {
+            return Err(Error::invalid("length", format!("HKDF: length must be at most {} bytes", 255*hash_len)));
+        }
+
+        let mut hmac = HMAC::new(self.digest.clone(), prk)?;
+        let mut output = Vec::with_capacity(length);
+        let mut block: Vec<u8> = Vec::new();
+        let mut i = 1_u8;
+        while output.len() < length {
+            hmac.update(&block);
+            hmac.update(info.as_ref());
+            hmac.update([i]);
+            block = hmac.finalize()?;
+            output.extend(&block);
+            i = i.wrapping_add(1);
+        }
+
+        output.truncate(length);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::{sponge::Sponge, sha2::SHA2};
+    use crate::utilities::hex::from_hex;
+
+    fn hkdf() -> HKDF<Sponge<crate::utilities::ustates::Ux4<u32>>> {
+        HKDF::new(Sponge::half_siphash().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn definition() {
+        let mut kdf = hkdf();
+        let prk = kdf.extract(b"salt", b"input key material").unwrap();
+        assert!(prk == HMAC::mac(Sponge::half_siphash().unwrap(), b"salt", b"input key material").unwrap());
+
+        // Two blocks of 16 bytes: T(1) = HMAC(PRK, info || 1), T(2) = HMAC(PRK, T(1) || info || 2)
+        let okm = kdf.expand(&prk, b"info", 32).unwrap();
+        let t1 = HMAC::mac(Sponge::half_siphash().unwrap(), &prk, b"info\x01").unwrap();
+        let t2 = HMAC::mac(Sponge::half_siphash().unwrap(), &prk, [&t1[..], b"info\x02"].concat()).unwrap();
+        assert!(okm == [t1, t2].concat());
+
+        // Shorter outputs are prefixes, and an empty salt is HashLen zero bytes
+        assert!(kdf.expand(&prk, b"info", 20).unwrap() == okm[..20]);
+        assert!(kdf.extract(b"", b"ikm").unwrap() == kdf.extract([0_u8; 16], b"ikm").unwrap());
+        assert!(kdf.derive(b"salt", b"input key material", b"info", 32).unwrap() == okm);
+    }
+
+    #[test]
+    fn setup_errors() {
+        let mut kdf = hkdf();
+        let prk = kdf.extract(b"salt", b"ikm").unwrap();
+        assert!(kdf.expand(&prk, b"", 255*16).unwrap().len() == 255*16);
+        assert!(matches!(kdf.expand(&prk, b"", 255*16 + 1), Err(Error::InvalidParameter { field: "length", .. })));
+        assert!(matches!(kdf.expand(&prk[..15].to_vec(), b"", 16), Err(Error::InvalidParameter { field: "prk", .. })));
+    }
+
+    #[test]
+    fn rfc5869_vectors() {
+        // Test cases 1 to 3 of RFC 5869, with SHA-256: IKM, salt, info, PRK and OKM
+        type Case = (Vec<u8>, Vec<u8>, Vec<u8>, &'static str, &'static str);
+        let cases: [Case; 3] = [
+            (vec![0x0b; 22], (0x00..=0x0c).collect(), (0xf0..=0xf9).collect(),
+                "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5",
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"),
+            ((0x00..=0x4f).collect(), (0x60..=0xaf).collect(), (0xb0..=0xff).collect(),
+                "06a6b88c5853361a06104c9ceb35b45cef760014904671014a193f40c15fc244",
+                "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c59045a99cac7827271cb41c65e590e09\
+                da3275600c2f09b8367793a9aca3db71cc30c58179ec3e87c14c01d5c1f3434f1d87"),
+            (vec![0x0b; 22], vec![], vec![],
+                "19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04",
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"),
+        ];
+
+        let mut kdf = HKDF::new(SHA2::sha256()).unwrap();
+        for (ikm, salt, info, prk, okm) in cases {
+            let computed = kdf.extract(&salt, &ikm).unwrap();
+            assert!(computed == from_hex(prk).unwrap());
+            let okm = from_hex(okm).unwrap();
+            assert!(kdf.expand(&computed, &info, okm.len()).unwrap() == okm);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing Key Derivation Functions.
+//!
+//! This module groups all Key Derivation Function (KDF) implementations.
+//! These are accessible through the KDF trait, which implements the `extract` and `expand` functions,
+//! following the extract-then-expand paradigm of HKDF.
+
+use crate::error::Error;
+
+/// Trait for Key Derivation Functions,
+/// with public, general-purpose functions `extract` and `expand`.
+pub trait KDF
+{
+    /// Type of the pseudorandom key produced by `extract`.
+    type Key;
+
+    /// General `extract` function.
+    /// Concentrate the entropy of the source key material `ikm` into a pseudorandom key, using the `salt`.
+    fn extract(&mut self, salt: impl AsRef<[u8]>, ikm: impl AsRef<[u8]>) -> Result<Self::Key, Error>;
+
+    /// General `expand` function.
+    /// Expand the pseudorandom key `prk` into `length` bytes of output key material bound to the context `info`.
+    fn expand(&mut self, prk: &Self::Key, info: impl AsRef<[u8]>, length: usize) -> Result<Vec<u8>, Error>;
+
+    /// Extract-then-expand in a single call.
+    fn derive(&mut self, salt: impl AsRef<[u8]>, ikm: impl AsRef<[u8]>, info: impl AsRef<[u8]>, length: usize)
+        -> Result<Vec<u8>, Error>
+    {
+        let prk = self.extract(salt, ikm)?;
+        self.expand(&prk, info, length)
+    }
+}
+
+pub mod skdf;
+pub mod hkdf;
@@ -0,0 +1,221 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing SKDF (Sponge-based Key Derivation Function).
+//!
+//! Based on the KDF of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4).
+
+use crate::error::Error;
+use getset::Getters;
+use std::{ops::{BitXor, BitAnd, BitOr, Not, Sub, Shl}, convert::From};
+
+use crate::kdf::KDF;
+use crate::utilities::{ToLeBytes, StateWidth, bitbuf::{BitBuf, BitOrder}, bitops::{low_mask, check_width}};
+
+// SKDF structure.
+
+#[allow(dead_code)]
+#[derive(Getters, Clone, Debug)]
+/// Structure implementing the Sponge-based KDF of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4).
+///
+/// The extraction absorbs the salt then the source key material, each padded to r-bit blocks,
+/// with a domain separation bit in the inner part between them, and the expansion absorbs the context
+/// before squeezing the outputs like the `next` function of the SPRNG.
+/// Note that the state of the sponge is reversed for easier use of the outputs.
+/// The outer part is stored in the lower bits.
+pub struct SKDF<U>
+where
+    U: Clone
+{
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    n: usize,
+
+    /// Size of the outer part (rate).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Number of permutation calls per output block.
+    #[getset(get = "pub")]
+    t: usize,
+
+    /// Outer part mask.
+    #[getset(get = "pub")]
+    mask: U,
+
+    /// Permutation function.
+    perm: fn(U) -> U,
+}
+
+impl<U> SKDF<U>
+where
+    U: Copy + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U> + BitOr<Output = U>
+    + Sub<Output = U>
+{
+    /// Setup function.
+    pub fn new(params: Vec<usize>, func: fn(U) -> U) -> Result<Self, Error> {
+        if params.len() != 3 {
+            return Err(Error::BadLength { field: "params", expected: 3, got: params.len() });
+        }
+        let (n, r, t) = (params[0], params[1], params[2]);
+        check_width::<U>("n", n)?;
+        if r == 0 {
+            return Err(Error::invalid("r", "rate r must be greater than 0"));
+        }
+        if r >= n {
+            return Err(Error::invalid("r", "rate r must be less than the state size n"));
+        }
+        if t == 0 {
+            return Err(Error::invalid("t", "number of permutation calls t must be greater than 0"));
+        }
+
+        // Generate the mask
+        let mask: U = low_mask(r);
+
+        Ok(Self{
+            n,
+            r,
+            t,
+            mask,
+            perm: func,
+        })
+    }
+}
+
+impl<U> SKDF<U>
+where
+    U: Copy + From<u8> + Shl<usize, Output = U> + BitOr<Output = U>
+{
+    /// Split `data` into r-bit blocks, with `10*` padding so that distinct inputs give distinct blocks.
+    fn pad(&self, data: &[u8]) -> Vec<U> {
+        let mut bits = BitBuf::from_bytes(data, BitOrder::LsbFirst);
+        bits.push(true);
+        while !bits.len().is_multiple_of(self.r) {
+            bits.push(false);
+        }
+
+        bits.chunks(self.r).map(|block| block.to_uint::<U>()).collect()
+    }
+}
+
+impl<U> KDF for SKDF<U>
+where
+    U: Copy + From<u8> + Shl<usize, Output = U> + Not<Output = U> + BitAnd<Output = U> + BitOr<Output = U>
+    + BitXor<Output = U> + ToLeBytes
+{
+    type Key = U;
+
+    /// Extract function.
+    /// All the salt blocks are absorbed first, then the top bit of the inner part is flipped
+    /// so that the boundary with the source key material cannot be moved, which is absorbed next.
+    fn extract(&mut self, salt: impl AsRef<[u8]>, ikm: impl AsRef<[u8]>) -> Result<U, Error> {
+        let mut state: U = 0_u8.into();
+        for input in self.pad(salt.as_ref()) {
+            state = (self.perm)(state ^ (input & self.mask));
+        }
+
+        state = state ^ (U::from(1) << (self.n - 1));
+        for input in self.pad(ikm.as_ref()) {
+            state = (self.perm)(state ^ (input & self.mask));
+        }
+
+        Ok(state)
+    }
+
+    /// Expand function.
+    /// The context is absorbed in the pseudorandom key, then r bits are output per block,
+    /// the outer part being erased between the t permutation calls of each block.
+    fn expand(&mut self, prk: &U, info: impl AsRef<[u8]>, length: usize) -> Result<Vec<u8>, Error> {
+        let mut state = *prk;
+        for input in self.pad(info.as_ref()) {
+            state = (self.perm)(state ^ (input & self.mask));
+        }
+
+        // Note: The bits are collected in LSB-first order
+        let mut output = BitBuf::new(BitOrder::LsbFirst);
+        while output.len() < length*8 {
+            // Permute and output the outer part
+            state = (self.perm)(state);
+            output.append(&(state & self.mask), self.r);
+
+            // Truncate t-1 times
+            for _ in 1..self.t {
+                state = (self.perm)(state) & !self.mask;
+            }
+        }
+
+        output.truncate(length*8);
+        Ok(output.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{utilities::ustates::Ux4, hash::siphash::SipHash_perm};
+
+    fn perm(mut state: Ux4<u64>) -> Ux4<u64> {
+        for _ in 0..4 {state = SipHash_perm(&state);}
+        state
+    }
+
+    fn perm_64(state: u64) -> u64 {
+        state.rotate_left(17).wrapping_mul(0x9E3779B97F4A7C15) ^ 0x5DEECE66D
+    }
+
+    #[test]
+    fn output_lengths() {
+        let mut skdf = SKDF::<Ux4<u64>>::new(vec![256, 32, 2], perm).unwrap();
+        let prk = skdf.extract(b"salt", b"input key material").unwrap();
+        for length in [0, 1, 3, 4, 5, 31, 32, 33, 100] {
+            assert!(skdf.expand(&prk, b"info", length).unwrap().len() == length);
+        }
+    }
+
+    #[test]
+    fn output_prefix() {
+        // Rate not multiple of 8, so that blocks are concatenated across bytes
+        let mut skdf = SKDF::<u64>::new(vec![64, 5, 1], perm_64).unwrap();
+        let prk = skdf.extract(b"salt", b"input key material").unwrap();
+        let long = skdf.expand(&prk, b"info", 64).unwrap();
+        for length in [1, 7, 13, 63] {
+            assert!(skdf.expand(&prk, b"info", length).unwrap() == long[..length]);
+        }
+    }
+
+    #[test]
+    fn width_edge_cases() {
+        assert!(matches!(SKDF::<u64>::new(vec![256, 32, 1], perm_64), Err(Error::InvalidParameter { field: "n", .. })));
+        assert!(matches!(SKDF::<u64>::new(vec![64, 64, 1], perm_64), Err(Error::InvalidParameter { field: "r", .. })));
+        assert!(*SKDF::<u64>::new(vec![64, 63, 1], perm_64).unwrap().mask() == u64::MAX >> 1);
+
+        let mut skdf = SKDF::<u64>::new(vec![64, 1, 1], perm_64).unwrap();
+        assert!(*skdf.mask() == 1);
+        assert!(skdf.derive(b"salt", b"ikm", b"info", 3).unwrap().len() == 3);
+    }
+
+    #[test]
+    fn domain_separation() {
+        let mut skdf = SKDF::<Ux4<u64>>::new(vec![256, 32, 1], perm).unwrap();
+        let reference = skdf.derive(b"salt", b"ikm", b"info", 32).unwrap();
+        assert!(skdf.derive(b"salt", b"ikm", b"info", 32).unwrap() == reference);
+        assert!(skdf.derive(b"sal", b"ikm", b"info", 32).unwrap() != reference);
+        assert!(skdf.derive(b"salt", b"ikm\0", b"info", 32).unwrap() != reference);
+        assert!(skdf.derive(b"salt", b"ikm", b"", 32).unwrap() != reference);
+        assert!(skdf.derive(b"", b"saltikm", b"info", 32).unwrap() != skdf.derive(b"saltikm", b"", b"info", 32).unwrap());
+    }
+
+    #[test]
+    fn long_salt() {
+        // Every block of a salt longer than the source key material is absorbed
+        let mut skdf = SKDF::<Ux4<u64>>::new(vec![256, 32, 1], perm).unwrap();
+        let salt = [0x5a_u8; 64];
+        let reference = skdf.extract(salt, b"k").unwrap();
+        for i in [0, 4, 31, 63] {
+            let mut other = salt;
+            other[i] ^= 1;
+            assert!(skdf.extract(other, b"k").unwrap() != reference);
+        }
+        assert!(skdf.extract(&salt[..60], b"k").unwrap() != reference);
+    }
+}
@@ -3,8 +3,12 @@
 
 //! Crypto Tools.
 
+pub mod error;
 pub mod hash;
+pub mod kdf;
+pub mod mac;
 pub mod prng;
+pub mod sign;
 pub mod stream;
 pub mod other;
 pub mod utilities;
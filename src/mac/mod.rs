@@ -0,0 +1,40 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing Message Authentication Codes.
+//!
+//! This module groups the MAC implementations which are not Hash function constructions.
+//! All MACs are accessible through the Mac trait, which extends `Digest` with a keyed setup
+//! and a constant-time `verify` function. It is also implemented by keyed SipHash and HMAC.
+
+use crate::error::Error;
+use crate::hash::Digest;
+use crate::utilities::{ct::ct_eq, zeroize::Zeroize};
+
+/// Trait for Message Authentication Codes,
+/// keyed at setup and then used through the `Digest` functions `update` and `finalize`.
+pub trait Mac: Digest + Sized
+{
+    /// Parameters of the MAC other than the key.
+    type Params;
+
+    /// Setup function, keying the MAC with `key`.
+    fn new_with_key(params: Self::Params, key: impl AsRef<[u8]>) -> Result<Self, Error>;
+
+    /// Finalize the MAC and compare the output with `tag` in constant time.
+    /// The MAC is reset afterwards, as with `finalize`.
+    fn verify(&mut self, tag: impl AsRef<[u8]>) -> Result<(), Error> {
+        let mut computed = self.finalize_bytes()?;
+        let valid = ct_eq(&computed, tag.as_ref());
+        computed.zeroize();
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::AuthenticationFailure)
+        }
+    }
+}
+
+pub mod poly1305;
+pub mod sponge_mac;
@@ -0,0 +1,307 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing Poly1305.
+//!
+//! Based on [RFC 8439](https://www.rfc-editor.org/rfc/rfc8439), with the accumulator and `r`
+//! held in five 26-bit limbs so that products fit in 64-bit words.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::mac::Mac;
+use crate::utilities::zeroize::Zeroize;
+
+/// Mask of a 26-bit limb.
+const LIMB: u32 = 0x3ffffff;
+
+/// Little-endian 32-bit word starting at byte `i` of `bytes`.
+fn le32(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+// Poly1305 structure.
+
+#[derive(Clone)]
+/// Structure implementing Poly1305 from [RFC 8439](https://www.rfc-editor.org/rfc/rfc8439).
+///
+/// Poly1305 is a one-time authenticator: a key (r, s) must never be used for more than one message.
+/// `reset` and `finalize` keep the key so that the `Digest` interface is usable,
+/// but authenticating a second message under the same key lets an attacker forge tags.
+pub struct Poly1305
+{
+    /// Clamped `r`, in 26-bit limbs.
+    r: [u32; 5],
+
+    /// `s`, added to the accumulator at the end.
+    s: [u32; 4],
+
+    /// Accumulator, in 26-bit limbs.
+    h: [u32; 5],
+
+    /// Input bytes not forming a full block yet.
+    buffer: Vec<u8>,
+}
+
+impl Zeroize for Poly1305
+{
+    /// Wipes the key, the accumulator and the buffered inputs.
+    fn zeroize(&mut self) {
+        self.r.zeroize();
+        self.s.zeroize();
+        self.h.zeroize();
+        self.buffer.zeroize();
+    }
+}
+
+impl Drop for Poly1305
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Debug for Poly1305
+{
+    /// Prints nothing but redacted fields.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Poly1305")
+            .field("key", &format_args!("<redacted>"))
+            .field("state", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl Poly1305
+{
+    /// Setup function, with the 32-byte one-time `key` = r || s. `r` is clamped as in RFC 8439.
+    pub fn new(key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let key = key.as_ref();
+        if key.len() != 32 {
+            return Err(Error::BadLength { field: "key", expected: 32, got: key.len() });
+        }
+
+        let r = [
+            le32(key, 0) & 0x3ffffff,
+            (le32(key, 3) >> 2) & 0x3ffff03,
+            (le32(key, 6) >> 4) & 0x3ffc0ff,
+            (le32(key, 9) >> 6) & 0x3f03fff,
+            (le32(key, 12) >> 8) & 0x00fffff,
+        ];
+        let s = [le32(key, 16), le32(key, 20), le32(key, 24), le32(key, 28)];
+
+        Ok(Self { r, s, h: [0; 5], buffer: Vec::new() })
+    }
+
+    /// One-shot Poly1305 of `data` under the one-time `key`.
+    pub fn mac(key: impl AsRef<[u8]>, data: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let mut poly = Self::new(key)?;
+        poly.update(data);
+        poly.finalize()
+    }
+
+    /// h = (h + m) * r mod 2^130 - 5, for a 16-byte `block` with `hibit` the 2^128 bit of m.
+    fn block(&mut self, block: &[u8], hibit: u32) {
+        let [r0, r1, r2, r3, r4] = self.r.map(u64::from);
+        let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+
+        let h = &mut self.h;
+        h[0] += le32(block, 0) & LIMB;
+        h[1] += (le32(block, 3) >> 2) & LIMB;
+        h[2] += (le32(block, 6) >> 4) & LIMB;
+        h[3] += (le32(block, 9) >> 6) & LIMB;
+        h[4] += (le32(block, 12) >> 8) | hibit;
+        let [h0, h1, h2, h3, h4] = h.map(u64::from);
+
+        let d = [
+            h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1,
+            h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2,
+            h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3,
+            h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4,
+            h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0,
+        ];
+
+        // Partial reduction, the carry out of the top limb wrapping around times 5
+        let mut carry = 0_u64;
+        for i in 0..5 {
+            let limb = d[i] + carry;
+            h[i] = (limb as u32) & LIMB;
+            carry = limb >> 26;
+        }
+        h[0] += (carry as u32) * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB;
+    }
+}
+
+impl Digest for Poly1305
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn output_size(&self) -> usize {
+        16
+    }
+
+    /// Reset the accumulator. The one-time key is kept, and must not authenticate another message.
+    fn reset(&mut self) {
+        self.h.zeroize();
+        self.buffer.zeroize();
+        self.buffer.clear();
+    }
+
+    /// Process all the full blocks, and keep the remaining bytes for the next call.
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.buffer.extend(data.as_ref());
+        let full = self.buffer.len() - self.buffer.len() % 16;
+        let mut block = [0_u8; 16];
+        for i in (0..full).step_by(16) {
+            block.copy_from_slice(&self.buffer[i..i + 16]);
+            self.block(&block, 1 << 24);
+        }
+        block.zeroize();
+        self.buffer.drain(..full).for_each(drop);
+    }
+
+    /// Return (h mod 2^130 - 5) + s mod 2^128, the last partial block being padded with a 0x01 byte.
+    /// The accumulator is reset afterwards.
+    fn finalize(&mut self) -> Result<Self::Output, Error> {
+        if !self.buffer.is_empty() {
+            let mut block = [0_u8; 16];
+            block[..self.buffer.len()].copy_from_slice(&self.buffer);
+            block[self.buffer.len()] = 0x01;
+            self.block(&block, 0);
+            block.zeroize();
+        }
+
+        // Full carry of h
+        let mut h = self.h;
+        for i in 1..5 {
+            h[i] += h[i - 1] >> 26;
+            h[i - 1] &= LIMB;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= LIMB;
+        h[1] += h[0] >> 26;
+        h[0] &= LIMB;
+
+        // g = h + 5 - 2^130, selected in constant time if h >= 2^130 - 5
+        let mut g = [0_u32; 5];
+        let mut carry = 5_u32;
+        for i in 0..4 {
+            let limb = h[i] + carry;
+            g[i] = limb & LIMB;
+            carry = limb >> 26;
+        }
+        g[4] = (h[4] + carry).wrapping_sub(1 << 26);
+        let select = (g[4] >> 31).wrapping_sub(1);
+        for i in 0..5 {
+            h[i] = (h[i] & !select) | (g[i] & select);
+        }
+
+        // h mod 2^128 in four 32-bit words, plus s
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut tag = Vec::with_capacity(16);
+        let mut sum = 0_u64;
+        for (word, s) in words.iter().zip(self.s) {
+            sum = u64::from(*word) + u64::from(s) + (sum >> 32);
+            tag.extend((sum as u32).to_le_bytes());
+        }
+
+        h.zeroize();
+        g.zeroize();
+        self.reset();
+        Ok(tag)
+    }
+}
+
+impl Mac for Poly1305
+{
+    /// Poly1305 has no parameters other than the key.
+    type Params = ();
+
+    fn new_with_key(_params: (), key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        Self::new(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::hex::from_hex;
+
+    fn key(r: &str, s: &str) -> Vec<u8> {
+        [from_hex(r).unwrap(), from_hex(s).unwrap()].concat()
+    }
+
+    #[test]
+    fn rfc8439_vectors() {
+        // Section 2.5.2, and test vectors 1 and 6 of Appendix A.3
+        let jabberwocky = b"'Twas brillig, and the slithy toves\nDid gyre and gimble in the wabe:\n\
+            All mimsy were the borogoves,\nAnd the mome raths outgrabe.";
+        let cases: [(Vec<u8>, Vec<u8>, &str); 3] = [
+            (from_hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b").unwrap(),
+                b"Cryptographic Forum Research Group".to_vec(), "a8061dc1305136c6c22b8baf0c0127a9"),
+            (vec![0x00; 32], vec![0x00; 64], "00000000000000000000000000000000"),
+            (from_hex("1c9240a5eb55d38af333888604f6b5f0473917c1402b80099dca5cbc207075c0").unwrap(),
+                jabberwocky.to_vec(), "4541669a7eaaee61e708dc7cbcc5eb62"),
+        ];
+
+        for (key, message, tag) in cases {
+            let tag = from_hex(tag).unwrap();
+            assert!(Poly1305::mac(&key, &message).unwrap() == tag);
+
+            // Streaming, across block boundaries
+            let mut poly = Poly1305::new_with_key((), &key).unwrap();
+            for chunk in message.chunks(7) {
+                poly.update(chunk);
+            }
+            poly.verify(&tag).unwrap();
+        }
+    }
+
+    #[test]
+    fn reduction_edge_cases() {
+        // Edge-case vectors of Appendix A.3, exercising the carries and the final reduction
+        let r1 = "01000000000000000000000000000000";
+        let r2 = "02000000000000000000000000000000";
+        let zero = "00000000000000000000000000000000";
+        let cases = [
+            (key(r2, zero), "ffffffffffffffffffffffffffffffff".to_string(), "03000000000000000000000000000000"),
+            (key(r2, "ffffffffffffffffffffffffffffffff"), "02000000000000000000000000000000".to_string(),
+                "03000000000000000000000000000000"),
+            (key(r1, zero), format!("{}{}{}", "ffffffffffffffffffffffffffffffff", "f0ffffffffffffffffffffffffffffff",
+                "11000000000000000000000000000000"), "05000000000000000000000000000000"),
+            (key(r1, zero), format!("{}{}{}", "ffffffffffffffffffffffffffffffff", "fbfefefefefefefefefefefefefefefe",
+                "01010101010101010101010101010101"), zero),
+            (key(r2, zero), "fdffffffffffffffffffffffffffffff".to_string(), "faffffffffffffffffffffffffffffff"),
+        ];
+
+        for (key, message, tag) in cases {
+            assert!(Poly1305::mac(&key, from_hex(&message).unwrap()).unwrap() == from_hex(tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn verify_and_errors() {
+        let key = [0x42_u8; 32];
+        let tag = Poly1305::mac(key, b"message").unwrap();
+        let mut poly = Poly1305::new(key).unwrap();
+        poly.update(b"messagf");
+        assert!(matches!(poly.verify(&tag), Err(Error::AuthenticationFailure)));
+        poly.update(b"message");
+        assert!(matches!(poly.verify(&tag[..8]), Err(Error::AuthenticationFailure)));
+
+        assert!(matches!(Poly1305::new([0_u8; 31]), Err(Error::BadLength { field: "key", expected: 32, got: 31 })));
+        assert!(format!("{:?}", poly).contains("<redacted>"));
+    }
+}
@@ -0,0 +1,172 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing a keyed-sponge MAC.
+//!
+//! The key is absorbed as a prefix before the message, which is a secure MAC for sponges
+//! as shown in [BDPV2011](https://keccak.team/files/SpongeDuplex.pdf).
+//! It works over any permutation supported by `Sponge`, such as SipHash rounds or Keccak-f\[1600\].
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::{BitAnd, BitOr, BitXor, Not, Shl, Sub};
+
+use crate::hash::{Digest, sponge::Sponge};
+use crate::mac::Mac;
+use crate::utilities::{ToLeBytes, StateWidth, zeroize::Zeroize};
+
+// SpongeMAC structure.
+
+#[derive(Clone)]
+/// Structure implementing a keyed-sponge MAC.
+///
+/// The prefix is the key length, as 8 little-endian bytes, followed by the key,
+/// so that keys of different lengths never produce the same prefix.
+pub struct SpongeMAC<U>
+where
+    U: Clone + Zeroize
+{
+    /// Underlying Sponge, after absorbing the prefix.
+    sponge: Sponge<U>,
+
+    /// Key length and key, absorbed again at each reset.
+    prefix: Vec<u8>,
+}
+
+impl<U> Drop for SpongeMAC<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.prefix.zeroize();
+    }
+}
+
+impl<U> Debug for SpongeMAC<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the Sponge, whose state is redacted, and redacts the key.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpongeMAC")
+            .field("sponge", &self.sponge)
+            .field("key", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<U> SpongeMAC<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    /// Setup function, keying the Sponge `sponge` with a non-empty `key`.
+    pub fn new(sponge: Sponge<U>, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let key = key.as_ref();
+        if key.is_empty() {
+            return Err(Error::invalid("key", "key of the sponge MAC must not be empty"));
+        }
+
+        let mut prefix = (key.len() as u64).to_le_bytes().to_vec();
+        prefix.extend(key);
+        let mut mac = Self { sponge, prefix };
+        mac.reset();
+        Ok(mac)
+    }
+}
+
+impl<U> Digest for SpongeMAC<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    type Output = Vec<u8>;
+
+    fn block_size(&self) -> usize {
+        self.sponge.block_size()
+    }
+
+    fn output_size(&self) -> usize {
+        self.sponge.output_size()
+    }
+
+    /// Reset to the keyed initial state.
+    fn reset(&mut self) {
+        self.sponge.reset();
+        self.sponge.update(&self.prefix);
+    }
+
+    fn update(&mut self, data: impl AsRef<[u8]>) {
+        self.sponge.update(data);
+    }
+
+    /// Return the tag and reset to the keyed initial state.
+    fn finalize(&mut self) -> Result<Self::Output, Error> {
+        let tag = self.sponge.finalize()?;
+        self.reset();
+        Ok(tag)
+    }
+}
+
+impl<U> Mac for SpongeMAC<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + BitXor<Output = U> + Sub<Output = U> + ToLeBytes
+{
+    /// Underlying Sponge.
+    type Params = Sponge<U>;
+
+    fn new_with_key(sponge: Sponge<U>, key: impl AsRef<[u8]>) -> Result<Self, Error> {
+        Self::new(sponge, key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::siphash::SipHash;
+    use crate::mac::poly1305::Poly1305;
+    use crate::hash::hmac::HMAC;
+
+    // Any MAC through the trait only
+    fn tag<M: Mac>(params: M::Params, key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = M::new_with_key(params, key).unwrap();
+        mac.update(data);
+        mac.finalize_bytes().unwrap()
+    }
+
+    #[test]
+    fn definition() {
+        // Same as hashing the prefix and the message
+        let key = b"sponge key";
+        let mut sponge = Sponge::siphash().unwrap();
+        sponge.update([&(key.len() as u64).to_le_bytes()[..], key, b"message"].concat());
+        let expected = sponge.finalize().unwrap();
+        assert!(tag::<SpongeMAC<_>>(Sponge::siphash().unwrap(), key, b"message") == expected);
+
+        let mut mac = SpongeMAC::new(Sponge::sha3_256().unwrap(), key).unwrap();
+        mac.update(b"mess");
+        mac.update(b"age");
+        let keccak = mac.finalize().unwrap();
+        assert!(keccak.len() == 32);
+        mac.update(b"message");
+        mac.verify(&keccak).unwrap();
+        mac.update(b"message!");
+        assert!(matches!(mac.verify(&keccak), Err(Error::AuthenticationFailure)));
+
+        // Keys differing only by trailing zeros give different tags
+        assert!(tag::<SpongeMAC<_>>(Sponge::siphash().unwrap(), b"k", b"m")
+            != tag::<SpongeMAC<_>>(Sponge::siphash().unwrap(), b"k\0", b"m"));
+        assert!(matches!(SpongeMAC::new(Sponge::siphash().unwrap(), b""), Err(Error::InvalidParameter { field: "key", .. })));
+        assert!(format!("{:?}", mac).contains("<redacted>"));
+    }
+
+    #[test]
+    fn generic_macs() {
+        let key = [0x5a_u8; 32];
+        assert!(tag::<SipHash<u64>>(vec![2, 4], &key[..16], b"data").len() == 8);
+        assert!(tag::<HMAC<_>>(Sponge::half_siphash().unwrap(), &key, b"data").len() == 16);
+        assert!(tag::<Poly1305>((), &key, b"data") == Poly1305::mac(key, b"data").unwrap());
+        assert!(tag::<SpongeMAC<_>>(Sponge::shake128().unwrap(), &key, b"data").len() == 32);
+    }
+}
@@ -5,7 +5,7 @@
 //!
 //! The sandbox area of the main function allows for direct use of the cryptographic tools.
 
-use std::io::Error;
+use CryptoTools::error::Error;
 use std::time::Instant;
 
 
@@ -4,22 +4,154 @@
 //!
 //! Based on the Duplex construction of [DM2019](https://doi.org/10.1007/978-3-030-34618-8_8).
 
-use std::io::Error;
+use crate::error::Error;
 use getset::Getters;
 use rand::{Rng, thread_rng, distributions::Standard, prelude::Distribution};
-use std::{ops::{BitXor, BitAnd, BitOr, Not, Add, Sub, Shl, Shr}, convert::From};
-use crate::utilities::bitops::urot;
+use std::{ops::{BitXor, BitAnd, BitOr, Not, Add, Sub, Shl}, convert::From};
+use std::fmt::{self, Debug, Formatter};
+use crate::utilities::{bitops::{Rotate, low_mask, check_width}, StateWidth, zeroize::Zeroize};
+
+// Duplex parameters.
+
+#[derive(Getters, Clone, Copy, Debug, PartialEq, Eq)]
+/// Parameters of the Duplex, built with [`DuplexParams::builder`] or taken from a preset.
+pub struct DuplexParams
+{
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    b: usize,
+
+    /// Size of the outer part (b = c + r).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Size of the key (k <= b).
+    #[getset(get = "pub")]
+    k: usize,
+
+    /// Number of keys (u >= 1).
+    #[getset(get = "pub")]
+    u: usize,
+
+    /// Rotation value (alpha).
+    #[getset(get = "pub")]
+    alpha: usize,
+}
+
+impl DuplexParams
+{
+    /// Start building parameters. `u` defaults to 1 and `alpha` to 0, the other fields must be set.
+    pub fn builder() -> DuplexParamsBuilder {
+        DuplexParamsBuilder::default()
+    }
+
+    /// 64-bit state with r = 4, k = 4, u = 3 and alpha = 17, as in the `duplex_64` demo.
+    pub fn duplex_64() -> Self {
+        Self { b: 64, r: 4, k: 4, u: 3, alpha: 17 }
+    }
+
+    /// 256-bit state with r = 32, k = 32, u = 3 and alpha = 17, as in the `duplex_256` demo.
+    pub fn duplex_256() -> Self {
+        Self { b: 256, r: 32, k: 32, u: 3, alpha: 17 }
+    }
+
+    /// 320-bit state with r = 64, k = 128, u = 2 and alpha = 7, for Ascon-sized permutations.
+    pub fn duplex_320() -> Self {
+        Self { b: 320, r: 64, k: 128, u: 2, alpha: 7 }
+    }
+
+    /// Check the relations between the parameters.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.r == 0 {
+            return Err(Error::invalid("r", "rate r must be greater than 0"));
+        }
+        if self.r > self.b {
+            return Err(Error::invalid("r", "rate r must be less than or equal to the state size b"));
+        }
+        if self.k > self.b {
+            return Err(Error::invalid("k", "key size k must be less than or equal to the state size b"));
+        }
+        if self.u == 0 {
+            return Err(Error::invalid("u", "number of keys u must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Builder for [`DuplexParams`].
+pub struct DuplexParamsBuilder
+{
+    b: Option<usize>,
+    r: Option<usize>,
+    k: Option<usize>,
+    u: usize,
+    alpha: usize,
+}
+
+impl Default for DuplexParamsBuilder
+{
+    fn default() -> Self {
+        Self { b: None, r: None, k: None, u: 1, alpha: 0 }
+    }
+}
+
+impl DuplexParamsBuilder
+{
+    /// Set the size of the state in bits.
+    pub fn b(mut self, b: usize) -> Self {
+        self.b = Some(b);
+        self
+    }
+
+    /// Set the rate.
+    pub fn r(mut self, r: usize) -> Self {
+        self.r = Some(r);
+        self
+    }
+
+    /// Set the size of the key.
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = Some(k);
+        self
+    }
+
+    /// Set the number of keys.
+    pub fn u(mut self, u: usize) -> Self {
+        self.u = u;
+        self
+    }
+
+    /// Set the rotation value.
+    pub fn alpha(mut self, alpha: usize) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Check the parameters and build them.
+    pub fn build(self) -> Result<DuplexParams, Error> {
+        let params = DuplexParams {
+            b: self.b.ok_or_else(|| Error::invalid("b", "state size b must be set"))?,
+            r: self.r.ok_or_else(|| Error::invalid("r", "rate r must be set"))?,
+            k: self.k.ok_or_else(|| Error::invalid("k", "key size k must be set"))?,
+            u: self.u,
+            alpha: self.alpha,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
 
 // Duplex structure.
 
 #[allow(dead_code)]
-#[derive(Getters, Clone, Debug)]
+#[derive(Getters, Clone)]
 /// Structure implementing [DM2019](https://doi.org/10.1007/978-3-030-34618-8_8).
 /// Note that the state is reversed for easier use of the outputs.
 /// The outer part is stored in the lower bits.
 pub struct Duplex<U>
 where
-    U: Clone
+    U: Clone + Zeroize
 {
     /// Size of the state in bits.
     #[getset(get = "pub")]
@@ -57,26 +189,62 @@ where
     state: U
 }
 
+impl<U> Zeroize for Duplex<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the keys and the inner state.
+    /// The instance cannot be used after being wiped: `reset` and `duplex` return `KeyNotSet`.
+    fn zeroize(&mut self) {
+        self.keys.zeroize();
+        self.state.zeroize();
+    }
+}
+
+impl<U> Drop for Duplex<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<U> Debug for Duplex<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the parameters, with the keys and the inner state redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Duplex")
+            .field("b", &self.b)
+            .field("r", &self.r)
+            .field("k", &self.k)
+            .field("u", &self.u)
+            .field("alpha", &self.alpha)
+            .field("keys", &format_args!("<redacted>"))
+            .field("state", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
 impl<U> Duplex<U>
 where
-    U: Copy + From<u8> + Not<Output = U> + Shl<usize, Output = U> + Shr<usize, Output = U> + Add<Output = U>
+    U: Copy + Zeroize + Rotate + StateWidth + From<u8> + Not<Output = U> + Shl<usize, Output = U> + Add<Output = U>
     + Sub<Output = U> + BitAnd<Output = U> + BitOr<Output = U> + BitXor<Output = U>,
     Standard: Distribution<U>
 {
     /// Setup function, part of the init phase.
-    pub fn new(params: Vec<usize>, func: fn(U) -> U) -> Result<Self, Error> {
-        let (b, r, k) = (params[0], params[1], params[2]); // block size, rate, key size
-        assert!(r <= b, "Invalid parameters: State size b must be greater than rate r.");
-        assert!(k <= b, "Invalid parameters: State size b must be greater than key size k.");
-        let u = params[3];
+    pub fn new(params: DuplexParams, func: fn(U) -> U) -> Result<Self, Error> {
+        params.validate()?;
+        let DuplexParams { b, r, k, u, alpha } = params; // block size, rate, key size, number of keys, rotation
+        check_width::<U>("b", b)?;
 
         // Generate the state mask
-        let mut mask: U = 1_u8.into();
-        mask = (mask << r) - 1_u8.into();
+        let mask: U = low_mask(r);
 
         // Generate the key mask
-        let mut kmask: U = 1_u8.into();
-        kmask = (kmask << k) - 1_u8.into();
+        let kmask: U = low_mask(k);
 
         // Generate the keys using rand
         let mut rng = thread_rng();
@@ -89,34 +257,41 @@ where
         let state: U = 0_u8.into();
 
         Ok(Self{
-            b: b,
-            r: r,
-            k: k,
-            u: u,
-            alpha: params[4],
+            b,
+            r,
+            k,
+            u,
+            alpha,
             perm:  func,
-            keys:  keys,
-            kmask: kmask,
-            mask:  mask,
-            state: state
+            keys,
+            kmask,
+            mask,
+            state
         })
     }
 
     /// Reset function, part of the init phase.
     /// Reset allows to reuse the same keys and parameters.
-    pub fn reset(&mut self, delta: usize) {
+    pub fn reset(&mut self, delta: usize) -> Result<(), Error> {
+        if self.keys.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
+
         // Generate IV
         let mut rng = thread_rng();
         let initialization_vector = rng.gen::<U>() & !self.kmask;
 
-        self.state = urot::<U>(
-            self.keys[delta % self.u] | initialization_vector, self.alpha
-        );
+        self.state = (self.keys[delta % self.u] | initialization_vector).rotl(self.alpha);
         self.state = (self.perm)(self.state);
+        Ok(())
     }
 
     /// General duplex function.
-    pub fn duplex(&mut self, flag: bool, input: U) -> U {
+    pub fn duplex(&mut self, flag: bool, input: U) -> Result<U, Error> {
+        if self.keys.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
+
         let output: U = self.state & self.mask;
 
         if flag {
@@ -124,6 +299,78 @@ where
         }
 
         self.state = (self.perm)(self.state ^ input);
-        output
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::ustates::{UxN, test::perm_320};
+
+    #[test]
+    fn duplex_320() {
+        let mut duplex = Duplex::<UxN<u64, 5>>::new(DuplexParams::duplex_320(), perm_320).unwrap();
+        duplex.reset(1).unwrap();
+
+        let mask = *duplex.mask();
+        for i in 0..4 {
+            let output = duplex.duplex(i % 2 == 0, UxN::from(i as u8)).unwrap();
+            assert!(output & !mask == UxN::zero());
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn params_builder() {
+        let params = DuplexParams::builder().b(320).r(64).k(128).u(2).alpha(7).build().unwrap();
+        assert!(params == DuplexParams::duplex_320());
+        assert!(*DuplexParams::builder().b(64).r(8).k(8).build().unwrap().u() == 1);
+
+        let result = DuplexParams::builder().b(64).r(8).k(8).u(0).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "u", .. })));
+        let result = DuplexParams::builder().b(64).k(8).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+        let result = Duplex::<UxN<u64, 5>>::new(DuplexParams::duplex_256(), perm_320);
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "b", .. })));
+    }
+
+    #[test]
+    fn width_edge_cases() {
+        fn perm_64(state: u64) -> u64 {state.rotate_left(17) ^ 0x9E3779B97F4A7C15}
+
+        // r = b and k = b
+        let params = DuplexParams::builder().b(64).r(64).k(64).build().unwrap();
+        let mut duplex = Duplex::<u64>::new(params, perm_64).unwrap();
+        assert!(*duplex.mask() == u64::MAX && duplex.kmask == u64::MAX);
+        duplex.reset(0).unwrap();
+        duplex.duplex(true, 1).unwrap();
+
+        // r = 1
+        let params = DuplexParams::builder().b(64).r(1).k(8).build().unwrap();
+        let mut duplex = Duplex::<u64>::new(params, perm_64).unwrap();
+        duplex.reset(0).unwrap();
+        assert!(duplex.duplex(false, 1).unwrap() <= 1);
+
+        let result = DuplexParams::builder().b(64).r(0).k(8).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+        let result = DuplexParams::builder().b(64).r(8).k(65).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "k", .. })));
+        let result = Duplex::<u64>::new(DuplexParams::duplex_320(), perm_64);
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "b", .. })));
+    }
+
+    #[test]
+    fn wipe_and_redact() {
+        let mut duplex = Duplex::<UxN<u64, 5>>::new(DuplexParams::duplex_320(), perm_320).unwrap();
+        duplex.reset(0).unwrap();
+        let keys = duplex.keys.clone();
+        let debug = format!("{:?}", duplex);
+        assert!(debug.contains("<redacted>") && !debug.contains(&format!("{}", keys[0].0[0])));
+
+        duplex.zeroize();
+        assert!(duplex.keys.is_empty());
+        assert!(duplex.state == UxN::zero());
+        assert!(matches!(duplex.reset(0), Err(Error::KeyNotSet)));
+        assert!(matches!(duplex.duplex(true, UxN::zero()), Err(Error::KeyNotSet)));
+    }
+}
@@ -0,0 +1,364 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing an entropy accumulator with automatic reseeding for SPRNG.
+//!
+//! Based on the accumulator and reseed scheduler of Fortuna from [FS2003](https://www.schneier.com/wp-content/uploads/2015/12/fortuna.pdf).
+//! Events from named sources are spread over several pools in a round-robin fashion,
+//! and pool `i` is only used every `2^i` reseeds, so that an attacker with partial knowledge
+//! of the inputs cannot keep up with all the pools.
+
+use crate::error::Error;
+use getset::Getters;
+use std::{ops::{BitXor, BitAnd, BitOr, Not, Shl}, convert::From};
+
+use crate::prng::{PRNG, sprng::SPRNG};
+use crate::utilities::{bitbuf::{BitBuf, BitOrder}, zeroize::Zeroize};
+
+/// Trait for entropy sources feeding the accumulator.
+pub trait EntropySource
+{
+    /// Name of the source.
+    fn name(&self) -> &str;
+
+    /// Collect a new event from the source.
+    fn poll(&mut self) -> Result<Vec<u8>, Error>;
+}
+
+// Fake source.
+
+#[derive(Clone, Debug)]
+/// Deterministic entropy source, for testing only.
+///
+/// Events are produced by a SplitMix64 generator, so that the same name and starting value
+/// always give the same sequence of events.
+pub struct FakeSource
+{
+    /// Name of the source.
+    name: String,
+
+    /// Generator state.
+    state: u64,
+
+    /// Size of each event in bytes.
+    event_size: usize,
+}
+
+impl FakeSource
+{
+    /// Create a fake source producing events of `event_size` bytes from the starting value `state`.
+    pub fn new(name: &str, state: u64, event_size: usize) -> Self {
+        Self { name: name.to_string(), state, event_size }
+    }
+}
+
+impl EntropySource for FakeSource
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn poll(&mut self) -> Result<Vec<u8>, Error> {
+        let mut event = Vec::with_capacity(self.event_size);
+        while event.len() < self.event_size {
+            self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^= z >> 31;
+            event.extend(z.to_le_bytes());
+        }
+        event.truncate(self.event_size);
+        Ok(event)
+    }
+}
+
+// Accumulator structure.
+
+#[derive(Getters)]
+/// Structure implementing a Fortuna-style entropy accumulator driving the `refresh` function of an SPRNG.
+pub struct EntropyAccumulator<U>
+where
+    U: Clone + Zeroize
+{
+    /// Number of pools.
+    #[getset(get = "pub")]
+    p: usize,
+
+    /// Minimum size of pool 0 (in bytes) before reseeding.
+    #[getset(get = "pub")]
+    min_pool_size: usize,
+
+    /// Minimum number of `next` calls between two reseeds.
+    #[getset(get = "pub")]
+    min_interval: usize,
+
+    /// Number of reseeds performed so far.
+    #[getset(get = "pub")]
+    reseed_count: usize,
+
+    /// Number of `next` calls since the last reseed.
+    since_reseed: usize,
+
+    /// Entropy pools.
+    pools: Vec<Vec<u8>>,
+
+    /// Registered sources, with the index of the next pool they will feed.
+    sources: Vec<(Box<dyn EntropySource>, usize)>,
+
+    /// Underlying SPRNG.
+    sprng: SPRNG<U>,
+}
+
+impl<U> Drop for EntropyAccumulator<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the entropy pools, the SPRNG wipes itself.
+    fn drop(&mut self) {
+        self.pools.zeroize();
+    }
+}
+
+impl<U> EntropyAccumulator<U>
+where
+    U: Copy + Zeroize + From<u8> + Shl<usize, Output = U> + Not<Output = U> + BitAnd<Output = U> + BitOr<Output = U>
+    + BitXor<Output = U>
+{
+    /// Setup function.
+    /// The parameters are the number of pools `p`, the minimum pool size and the minimum reseed interval.
+    pub fn new(params: Vec<usize>, sprng: SPRNG<U>) -> Result<Self, Error> {
+        if params.len() != 3 {
+            return Err(Error::BadLength { field: "params", expected: 3, got: params.len() });
+        }
+        let (p, min_pool_size, min_interval) = (params[0], params[1], params[2]);
+        if !(1..=32).contains(&p) {
+            return Err(Error::invalid("p", "number of pools p must be between 1 and 32"));
+        }
+        if min_pool_size == 0 {
+            return Err(Error::invalid("min_pool_size", "minimum pool size must be greater than 0"));
+        }
+
+        Ok(Self {
+            p,
+            min_pool_size,
+            min_interval,
+            reseed_count: 0,
+            since_reseed: 0,
+            pools: vec![Vec::new(); p],
+            sources: Vec::new(),
+            sprng,
+        })
+    }
+
+    /// Register a new entropy source and return its identifier.
+    pub fn add_source(&mut self, source: Box<dyn EntropySource>) -> Result<usize, Error> {
+        if self.sources.len() > u8::MAX as usize {
+            return Err(Error::invalid("source", "Accumulator: too many sources"));
+        }
+        if self.sources.iter().any(|(s, _)| s.name() == source.name()) {
+            return Err(Error::invalid("source", "Accumulator: source name already registered"));
+        }
+
+        self.sources.push((source, 0));
+        Ok(self.sources.len() - 1)
+    }
+
+    /// Add an event from the source `id` to the next pool of this source.
+    /// Events are prefixed with the source identifier and their length, as in Fortuna.
+    pub fn add_event(&mut self, id: usize, data: impl AsRef<[u8]>) -> Result<(), Error> {
+        let data = data.as_ref();
+        if id >= self.sources.len() {
+            return Err(Error::invalid("id", "Accumulator: unknown source"));
+        }
+        if data.is_empty() || data.len() > u8::MAX as usize {
+            return Err(Error::invalid("data", "Accumulator: event size must be between 1 and 255 bytes"));
+        }
+
+        let pool = self.sources[id].1;
+        self.sources[id].1 = (pool + 1) % self.p;
+
+        self.pools[pool].push(id as u8);
+        self.pools[pool].push(data.len() as u8);
+        self.pools[pool].extend(data);
+        Ok(())
+    }
+
+    /// Poll every registered source once.
+    pub fn poll_sources(&mut self) -> Result<(), Error> {
+        for id in 0..self.sources.len() {
+            let event = self.sources[id].0.poll()?;
+            self.add_event(id, event)?;
+        }
+        Ok(())
+    }
+
+    /// Current size of each pool in bytes.
+    pub fn pool_sizes(&self) -> Vec<usize> {
+        self.pools.iter().map(|pool| pool.len()).collect()
+    }
+
+    /// Whether the next call to `next` will reseed the SPRNG.
+    pub fn reseed_ready(&self) -> bool {
+        self.pools[0].len() >= self.min_pool_size
+            && (self.reseed_count == 0 || self.since_reseed >= self.min_interval)
+    }
+
+    /// Reseed the SPRNG with the content of the scheduled pools, which are wiped.
+    /// Pool `i` is used if `2^i` divides the reseed counter.
+    /// Returns `InsufficientEntropy`, without counting a reseed, if all the scheduled pools are empty.
+    pub fn reseed(&mut self) -> Result<(), Error> {
+        let count = self.reseed_count + 1;
+        let mut data: Vec<u8> = Vec::new();
+        for i in 0..self.p {
+            if !count.is_multiple_of(1_usize << i) {
+                break;
+            }
+            data.extend(&self.pools[i]);
+            self.pools[i].zeroize();
+        }
+        if data.is_empty() {
+            return Err(Error::InsufficientEntropy { estimated: 0.0, required: (8 * self.min_pool_size) as f64 });
+        }
+
+        self.reseed_count = count;
+        self.since_reseed = 0;
+        self.refresh(data)
+    }
+}
+
+impl<U> PRNG for EntropyAccumulator<U>
+where
+    U: Copy + Zeroize + From<u8> + Shl<usize, Output = U> + Not<Output = U> + BitAnd<Output = U> + BitOr<Output = U>
+    + BitXor<Output = U>
+{
+    type Input = u8;
+    type Output = U;
+
+    /// Refresh function.
+    /// The bytes are split into r-bit blocks and passed to the `refresh` function of the SPRNG,
+    /// then both are wiped.
+    fn refresh(&mut self, mut inputs: Vec<u8>) -> Result<(), Error> {
+        if inputs.is_empty() {
+            return Ok(());
+        }
+
+        let mut bits = BitBuf::from_bytes(&inputs, BitOrder::LsbFirst);
+        inputs.zeroize();
+        let blocks: Vec<U> = bits
+            .chunks(*self.sprng.r())
+            .map(|block| block.to_uint::<U>())
+            .collect();
+        bits.zeroize();
+        self.sprng.refresh(blocks)
+    }
+
+    /// Next function.
+    /// Reseeds the SPRNG first if pool 0 holds enough data and the minimum interval has elapsed.
+    fn next(&mut self) -> Result<U, Error> {
+        if self.reseed_ready() {
+            self.reseed()?;
+        }
+        if self.reseed_count == 0 {
+            return Err(Error::StateNotInitialized);
+        }
+
+        self.since_reseed += 1;
+        self.sprng.next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prng::sprng::SPRNGParams;
+
+    fn perm(state: u64) -> u64 {
+        state.rotate_left(17).wrapping_mul(0x9E3779B97F4A7C15) ^ 0x5DEECE66D
+    }
+
+    fn accumulator(params: Vec<usize>) -> EntropyAccumulator<u64> {
+        let sprng = SPRNG::with_seed(SPRNGParams::builder().n(64).r(8).s(2).build().unwrap(), perm, vec![3, 5]).unwrap();
+        EntropyAccumulator::new(params, sprng).unwrap()
+    }
+
+    #[test]
+    fn fake_source_is_deterministic() {
+        let mut a = FakeSource::new("a", 42, 13);
+        let mut b = FakeSource::new("b", 42, 13);
+        let event = a.poll().unwrap();
+        assert!(event.len() == 13);
+        assert!(event == b.poll().unwrap());
+        assert!(event != a.poll().unwrap());
+    }
+
+    #[test]
+    fn unseeded_and_threshold() {
+        let mut acc = accumulator(vec![4, 36, 0]);
+        acc.add_source(Box::new(FakeSource::new("fake", 1, 16))).unwrap();
+        assert!(acc.next().is_err());
+
+        // Each poll adds 18 bytes to a single pool, round-robin over the 4 pools
+        for _ in 0..4 {acc.poll_sources().unwrap();}
+        assert!(acc.pool_sizes() == vec![18, 18, 18, 18]);
+        assert!(!acc.reseed_ready());
+
+        for _ in 0..4 {acc.poll_sources().unwrap();}
+        assert!(acc.reseed_ready());
+        acc.next().unwrap();
+        assert!(*acc.reseed_count() == 1);
+        assert!(acc.pool_sizes() == vec![0, 36, 36, 36]);
+    }
+
+    #[test]
+    fn pool_schedule() {
+        let mut acc = accumulator(vec![3, 1, 0]);
+        let id = acc.add_source(Box::new(FakeSource::new("fake", 7, 8))).unwrap();
+        assert!(acc.add_source(Box::new(FakeSource::new("fake", 8, 8))).is_err());
+
+        let mut used = Vec::new();
+        for _ in 0..4 {
+            for _ in 0..3 {acc.add_event(id, [1_u8; 8]).unwrap();}
+            let before = acc.pool_sizes();
+            acc.reseed().unwrap();
+            let after = acc.pool_sizes();
+            used.push((0..3).filter(|&i| before[i] > 0 && after[i] == 0).count());
+        }
+        // Reseeds 1 to 4 use pools {0}, {0, 1}, {0}, {0, 1, 2}
+        assert!(used == vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn empty_pools() {
+        let mut acc = accumulator(vec![2, 4, 0]);
+        assert!(matches!(acc.reseed(), Err(Error::InsufficientEntropy { required, .. }) if required == 32.0));
+        assert!(*acc.reseed_count() == 0);
+        assert!(acc.next().is_err());
+
+        // Reseed 2 uses pools 0 and 1, so an event in pool 1 is enough
+        let id = acc.add_source(Box::new(FakeSource::new("fake", 5, 4))).unwrap();
+        acc.add_event(id, [1_u8; 4]).unwrap();
+        acc.reseed().unwrap();
+        acc.add_event(id, [2_u8; 4]).unwrap();
+        assert!(acc.pool_sizes() == vec![0, 6]);
+        assert!(acc.reseed().is_ok() && *acc.reseed_count() == 2);
+        assert!(acc.pool_sizes() == vec![0, 0]);
+    }
+
+    #[test]
+    fn min_interval() {
+        let mut acc = accumulator(vec![2, 1, 3]);
+        let id = acc.add_source(Box::new(FakeSource::new("fake", 3, 4))).unwrap();
+        acc.add_event(id, [1_u8; 4]).unwrap();
+        acc.next().unwrap();
+
+        for _ in 0..2 {
+            acc.add_event(id, [2_u8; 4]).unwrap();
+            acc.add_event(id, [3_u8; 4]).unwrap();
+            acc.next().unwrap();
+        }
+        assert!(*acc.reseed_count() == 1);
+        acc.next().unwrap();
+        assert!(*acc.reseed_count() == 2);
+    }
+}
@@ -0,0 +1,700 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the Hash_DRBG and HMAC_DRBG deterministic random bit generators.
+//!
+//! Based on [NIST SP 800-90A Rev. 1](https://doi.org/10.6028/NIST.SP.800-90Ar1).
+//! Both generators are generic over the `Digest` trait, and can draw fresh entropy
+//! from an `EntropySource` for automatic reseeding and prediction resistance.
+
+use crate::error::Error;
+use getset::Getters;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::{Digest, hmac::HMAC};
+use crate::prng::{PRNG, accumulator::EntropySource};
+use crate::utilities::zeroize::Zeroize;
+
+/// Maximum number of bytes per `generate` request (2^19 bits).
+pub const MAX_REQUEST_BYTES: usize = 1 << 16;
+
+/// Hash the concatenation of `inputs` with a fresh copy of `digest`.
+fn hash<D>(digest: &D, inputs: &[&[u8]]) -> Result<Vec<u8>, Error>
+where
+    D: Digest + Clone
+{
+    let mut digest = digest.clone();
+    digest.reset();
+    for input in inputs {
+        digest.update(input);
+    }
+    digest.finalize_bytes()
+}
+
+/// Add `b` to `a` modulo 2^(8 * a.len()), both being big-endian.
+fn add_be(a: &mut [u8], b: &[u8]) {
+    let mut carry = 0_u16;
+    let mut j = b.len();
+    for i in (0..a.len()).rev() {
+        let rhs = if j > 0 {j -= 1; b[j] as u16} else {0};
+        let sum = a[i] as u16 + rhs + carry;
+        a[i] = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+/// Replace the secret `target` by `value`, wiping the previous value.
+fn replace(target: &mut Vec<u8>, mut value: Vec<u8>) {
+    std::mem::swap(target, &mut value);
+    value.zeroize();
+}
+
+// Hash_DRBG structure.
+
+#[derive(Getters)]
+/// Structure implementing Hash_DRBG from [SP800-90A](https://doi.org/10.6028/NIST.SP.800-90Ar1), Section 10.1.1.
+pub struct HashDRBG<D>
+{
+    /// Output length of the hash function in bytes.
+    #[getset(get = "pub")]
+    outlen: usize,
+
+    /// Seed length in bytes.
+    #[getset(get = "pub")]
+    seedlen: usize,
+
+    /// Maximum number of requests between reseeds.
+    #[getset(get = "pub")]
+    reseed_interval: usize,
+
+    /// Number of requests since the last (re)seeding.
+    #[getset(get = "pub")]
+    reseed_counter: usize,
+
+    /// Prediction resistance flag.
+    #[getset(get = "pub")]
+    prediction_resistance: bool,
+
+    /// Hash function.
+    digest: D,
+
+    /// Entropy source used for automatic reseeding.
+    source: Option<Box<dyn EntropySource>>,
+
+    /// Secret value V.
+    V: Vec<u8>,
+
+    /// Secret constant C.
+    C: Vec<u8>,
+}
+
+impl<D> Zeroize for HashDRBG<D>
+{
+    /// Wipes V and C.
+    /// The generator must be instantiated again: `reseed` and `generate` return `StateNotInitialized`.
+    fn zeroize(&mut self) {
+        self.V.zeroize();
+        self.C.zeroize();
+        self.reseed_counter = 0;
+    }
+}
+
+impl<D> Drop for HashDRBG<D>
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<D> Debug for HashDRBG<D>
+{
+    /// Prints the parameters, with V and C redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashDRBG")
+            .field("outlen", &self.outlen)
+            .field("seedlen", &self.seedlen)
+            .field("reseed_interval", &self.reseed_interval)
+            .field("reseed_counter", &self.reseed_counter)
+            .field("prediction_resistance", &self.prediction_resistance)
+            .field("V", &format_args!("<redacted>"))
+            .field("C", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<D> HashDRBG<D>
+where
+    D: Digest + Clone
+{
+    /// Setup function over the Hash function `digest`, with the given reseed interval.
+    /// The output length is taken from the `Digest` metadata, and the seed length from Table 2 of SP 800-90A:
+    /// 440 bits for outputs up to 256 bits, 888 bits for outputs up to 512 bits. The generator must then be instantiated.
+    pub fn new(reseed_interval: usize, digest: D) -> Result<Self, Error> {
+        let outlen = digest.output_size();
+        let seedlen = match outlen {
+            0 => return Err(Error::invalid("digest", "output size of the Hash function must be greater than 0")),
+            1..=32 => 55,
+            33..=64 => 111,
+            _ => return Err(Error::invalid("digest", "Hash_DRBG: output size of the Hash function must be at most 512 bits")),
+        };
+        if reseed_interval == 0 {
+            return Err(Error::invalid("reseed_interval", "reseed interval must be greater than 0"));
+        }
+
+        Ok(Self {
+            outlen,
+            seedlen,
+            reseed_interval,
+            reseed_counter: 0,
+            prediction_resistance: false,
+            digest,
+            source: None,
+            V: Vec::new(),
+            C: Vec::new(),
+        })
+    }
+
+    /// Set the entropy source used for automatic reseeding and prediction resistance.
+    pub fn set_source(&mut self, source: Box<dyn EntropySource>) {
+        self.source = Some(source);
+    }
+
+    /// Enable or disable prediction resistance. Requires an entropy source.
+    pub fn set_prediction_resistance(&mut self, flag: bool) -> Result<(), Error> {
+        if flag && self.source.is_none() {
+            return Err(Error::invalid("prediction_resistance", "Hash_DRBG: prediction resistance requires an entropy source"));
+        }
+        self.prediction_resistance = flag;
+        Ok(())
+    }
+
+    /// Hash derivation function (Hash_df), returning `length` bytes.
+    fn hash_df(&self, inputs: &[&[u8]], length: usize) -> Result<Vec<u8>, Error> {
+        let nb_bits = ((length * 8) as u32).to_be_bytes();
+        let mut output: Vec<u8> = Vec::with_capacity(length + self.outlen);
+        let mut counter = 1_u8;
+
+        while output.len() < length {
+            let counter_byte = [counter];
+            let mut data: Vec<&[u8]> = vec![&counter_byte, &nb_bits];
+            data.extend(inputs);
+            output.extend(hash(&self.digest, &data)?);
+            counter = counter.wrapping_add(1);
+        }
+
+        output.truncate(length);
+        Ok(output)
+    }
+
+    /// Derive V and C from the seed material and reset the reseed counter.
+    fn seed(&mut self, inputs: &[&[u8]]) -> Result<(), Error> {
+        let V = self.hash_df(inputs, self.seedlen)?;
+        replace(&mut self.V, V);
+        let C = self.hash_df(&[&[0x00], &self.V], self.seedlen)?;
+        replace(&mut self.C, C);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Instantiate function.
+    pub fn instantiate(&mut self, entropy: impl AsRef<[u8]>, nonce: impl AsRef<[u8]>,
+        personalization: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        self.seed(&[entropy.as_ref(), nonce.as_ref(), personalization.as_ref()])
+    }
+
+    /// Reseed function.
+    pub fn reseed(&mut self, entropy: impl AsRef<[u8]>, additional: impl AsRef<[u8]>) -> Result<(), Error> {
+        if self.reseed_counter == 0 {
+            return Err(Error::StateNotInitialized);
+        }
+        let mut V = self.V.clone();
+        let result = self.seed(&[&[0x01], &V, entropy.as_ref(), additional.as_ref()]);
+        V.zeroize();
+        result
+    }
+
+    /// Reseed with entropy drawn from the entropy source.
+    fn reseed_from_source(&mut self, additional: &[u8]) -> Result<(), Error> {
+        let entropy = match self.source.as_mut() {
+            Some(source) => source.poll()?,
+            None => return Err(Error::ReseedRequired),
+        };
+        self.reseed(entropy, additional)
+    }
+
+    /// Generate function, returning `length` bytes.
+    pub fn generate(&mut self, length: usize, additional: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let mut additional = additional.as_ref();
+        if self.reseed_counter == 0 {
+            return Err(Error::StateNotInitialized);
+        }
+        if length > MAX_REQUEST_BYTES {
+            return Err(Error::invalid("length", "Hash_DRBG: requested length is too large"));
+        }
+
+        if self.prediction_resistance || self.reseed_counter > self.reseed_interval {
+            self.reseed_from_source(additional)?;
+            additional = &[];
+        }
+
+        if !additional.is_empty() {
+            let mut w = hash(&self.digest, &[&[0x02], &self.V, additional])?;
+            add_be(&mut self.V, &w);
+            w.zeroize();
+        }
+
+        // Hashgen
+        let mut data = self.V.clone();
+        let mut output: Vec<u8> = Vec::with_capacity(length + self.outlen);
+        while output.len() < length {
+            output.extend(hash(&self.digest, &[&data])?);
+            add_be(&mut data, &[0x01]);
+        }
+        output.truncate(length);
+        data.zeroize();
+
+        // Update the state
+        let mut H = hash(&self.digest, &[&[0x03], &self.V])?;
+        let mut C = self.C.clone();
+        add_be(&mut self.V, &H);
+        add_be(&mut self.V, &C);
+        add_be(&mut self.V, &self.reseed_counter.to_be_bytes());
+        H.zeroize();
+        C.zeroize();
+        self.reseed_counter += 1;
+
+        Ok(output)
+    }
+}
+
+impl<D> PRNG for HashDRBG<D>
+where
+    D: Digest + Clone
+{
+    type Input = u8;
+    type Output = Vec<u8>;
+
+    /// Refresh function, reseeding with the given entropy and no additional input.
+    fn refresh(&mut self, inputs: Vec<u8>) -> Result<(), Error> {
+        self.reseed(inputs, [])
+    }
+
+    /// Next function, generating one block of `outlen` bytes.
+    fn next(&mut self) -> Result<Vec<u8>, Error> {
+        self.generate(self.outlen, [])
+    }
+}
+
+// HMAC_DRBG structure.
+
+#[derive(Getters)]
+/// Structure implementing HMAC_DRBG from [SP800-90A](https://doi.org/10.6028/NIST.SP.800-90Ar1), Section 10.1.2.
+pub struct HMACDRBG<D>
+{
+    /// Output length of the hash function in bytes.
+    #[getset(get = "pub")]
+    outlen: usize,
+
+    /// Block size of the hash function in bytes.
+    #[getset(get = "pub")]
+    block_size: usize,
+
+    /// Maximum number of requests between reseeds.
+    #[getset(get = "pub")]
+    reseed_interval: usize,
+
+    /// Number of requests since the last (re)seeding.
+    #[getset(get = "pub")]
+    reseed_counter: usize,
+
+    /// Prediction resistance flag.
+    #[getset(get = "pub")]
+    prediction_resistance: bool,
+
+    /// Hash function.
+    digest: D,
+
+    /// Entropy source used for automatic reseeding.
+    source: Option<Box<dyn EntropySource>>,
+
+    /// Secret key K.
+    K: Vec<u8>,
+
+    /// Secret value V.
+    V: Vec<u8>,
+}
+
+impl<D> Zeroize for HMACDRBG<D>
+{
+    /// Wipes K and V.
+    /// The generator must be instantiated again: `reseed` and `generate` return `StateNotInitialized`.
+    fn zeroize(&mut self) {
+        self.K.zeroize();
+        self.V.zeroize();
+        self.reseed_counter = 0;
+    }
+}
+
+impl<D> Drop for HMACDRBG<D>
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<D> Debug for HMACDRBG<D>
+{
+    /// Prints the parameters, with K and V redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HMACDRBG")
+            .field("outlen", &self.outlen)
+            .field("block_size", &self.block_size)
+            .field("reseed_interval", &self.reseed_interval)
+            .field("reseed_counter", &self.reseed_counter)
+            .field("prediction_resistance", &self.prediction_resistance)
+            .field("K", &format_args!("<redacted>"))
+            .field("V", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<D> HMACDRBG<D>
+where
+    D: Digest + Clone
+{
+    /// Setup function over the Hash function `digest`, with the given reseed interval.
+    /// The output length and block size are taken from the `Digest` metadata. The generator must then be instantiated.
+    pub fn new(reseed_interval: usize, digest: D) -> Result<Self, Error> {
+        let (outlen, block_size) = (digest.output_size(), digest.block_size());
+        if outlen == 0 {
+            return Err(Error::invalid("digest", "output size of the Hash function must be greater than 0"));
+        }
+        if block_size == 0 {
+            return Err(Error::invalid("digest", "block size of the Hash function must be greater than 0"));
+        }
+        if reseed_interval == 0 {
+            return Err(Error::invalid("reseed_interval", "reseed interval must be greater than 0"));
+        }
+
+        Ok(Self {
+            outlen,
+            block_size,
+            reseed_interval,
+            reseed_counter: 0,
+            prediction_resistance: false,
+            digest,
+            source: None,
+            K: Vec::new(),
+            V: Vec::new(),
+        })
+    }
+
+    /// Set the entropy source used for automatic reseeding and prediction resistance.
+    pub fn set_source(&mut self, source: Box<dyn EntropySource>) {
+        self.source = Some(source);
+    }
+
+    /// Enable or disable prediction resistance. Requires an entropy source.
+    pub fn set_prediction_resistance(&mut self, flag: bool) -> Result<(), Error> {
+        if flag && self.source.is_none() {
+            return Err(Error::invalid("prediction_resistance", "HMAC_DRBG: prediction resistance requires an entropy source"));
+        }
+        self.prediction_resistance = flag;
+        Ok(())
+    }
+
+    /// HMAC of the concatenation of `inputs` under the current key K.
+    fn hmac(&self, inputs: &[&[u8]]) -> Result<Vec<u8>, Error> {
+        let mut hmac = HMAC::new(self.digest.clone(), &self.K)?;
+        for input in inputs {
+            hmac.update(input);
+        }
+        hmac.finalize()
+    }
+
+    /// Update function (HMAC_DRBG_Update).
+    fn update(&mut self, provided: &[&[u8]]) -> Result<(), Error> {
+        let is_empty = provided.iter().all(|p| p.is_empty());
+
+        for byte in [[0x00_u8], [0x01_u8]] {
+            let mut V = self.V.clone();
+            let mut data: Vec<&[u8]> = vec![&V, &byte];
+            data.extend(provided);
+            let K = self.hmac(&data);
+            V.zeroize();
+            replace(&mut self.K, K?);
+            let V = self.hmac(&[&self.V])?;
+            replace(&mut self.V, V);
+
+            if is_empty {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Instantiate function.
+    pub fn instantiate(&mut self, entropy: impl AsRef<[u8]>, nonce: impl AsRef<[u8]>,
+        personalization: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        replace(&mut self.K, vec![0x00; self.outlen]);
+        replace(&mut self.V, vec![0x01; self.outlen]);
+        self.update(&[entropy.as_ref(), nonce.as_ref(), personalization.as_ref()])?;
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Reseed function.
+    pub fn reseed(&mut self, entropy: impl AsRef<[u8]>, additional: impl AsRef<[u8]>) -> Result<(), Error> {
+        if self.reseed_counter == 0 {
+            return Err(Error::StateNotInitialized);
+        }
+        self.update(&[entropy.as_ref(), additional.as_ref()])?;
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    /// Reseed with entropy drawn from the entropy source.
+    fn reseed_from_source(&mut self, additional: &[u8]) -> Result<(), Error> {
+        let entropy = match self.source.as_mut() {
+            Some(source) => source.poll()?,
+            None => return Err(Error::ReseedRequired),
+        };
+        self.reseed(entropy, additional)
+    }
+
+    /// Generate function, returning `length` bytes.
+    pub fn generate(&mut self, length: usize, additional: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        let mut additional = additional.as_ref();
+        if self.reseed_counter == 0 {
+            return Err(Error::StateNotInitialized);
+        }
+        if length > MAX_REQUEST_BYTES {
+            return Err(Error::invalid("length", "HMAC_DRBG: requested length is too large"));
+        }
+
+        if self.prediction_resistance || self.reseed_counter > self.reseed_interval {
+            self.reseed_from_source(additional)?;
+            additional = &[];
+        }
+
+        if !additional.is_empty() {
+            self.update(&[additional])?;
+        }
+
+        let mut output: Vec<u8> = Vec::with_capacity(length + self.outlen);
+        while output.len() < length {
+            let V = self.hmac(&[&self.V])?;
+            replace(&mut self.V, V);
+            output.extend(&self.V);
+        }
+        output.truncate(length);
+
+        self.update(&[additional])?;
+        self.reseed_counter += 1;
+
+        Ok(output)
+    }
+}
+
+impl<D> PRNG for HMACDRBG<D>
+where
+    D: Digest + Clone
+{
+    type Input = u8;
+    type Output = Vec<u8>;
+
+    /// Refresh function, reseeding with the given entropy and no additional input.
+    fn refresh(&mut self, inputs: Vec<u8>) -> Result<(), Error> {
+        self.reseed(inputs, [])
+    }
+
+    /// Next function, generating one block of `outlen` bytes.
+    fn next(&mut self) -> Result<Vec<u8>, Error> {
+        self.generate(self.outlen, [])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prng::accumulator::FakeSource;
+    use crate::{utilities::ustates::Ux4, hash::{siphash::SipHash_perm, sha2::SHA2}};
+    use crate::utilities::rsp::parse_rsp;
+
+    /// Toy digest absorbing bytes into a SipHash permutation state, for testing only.
+    #[derive(Clone, Default)]
+    struct ToyDigest(Vec<u8>);
+
+    impl Digest for ToyDigest {
+        type Output = Ux4<u64>;
+
+        fn block_size(&self) -> usize {
+            64
+        }
+
+        fn output_size(&self) -> usize {
+            32
+        }
+
+        fn reset(&mut self) {
+            self.0.clear();
+        }
+
+        fn update(&mut self, data: impl AsRef<[u8]>) {
+            self.0.extend(data.as_ref());
+        }
+
+        fn finalize(&mut self) -> Result<Ux4<u64>, Error> {
+            let mut state = Ux4::new([self.0.len() as u64, 0, 0, 0]);
+            for chunk in self.0.chunks(8) {
+                let mut block = [0_u8; 8];
+                block[..chunk.len()].copy_from_slice(chunk);
+                state.0[1] ^= u64::from_le_bytes(block);
+                for _ in 0..2 {state = SipHash_perm(&state);}
+            }
+            for _ in 0..4 {state = SipHash_perm(&state);}
+            Ok(state)
+        }
+    }
+
+    #[test]
+    fn add_be_carry() {
+        let mut a = vec![0x00, 0xff, 0xff];
+        add_be(&mut a, &[0x01]);
+        assert!(a == vec![0x01, 0x00, 0x00]);
+        let mut b = vec![0xff, 0xff];
+        add_be(&mut b, &[0x01, 0x00, 0x01]);
+        assert!(b == vec![0x00, 0x00]);
+    }
+
+    #[test]
+    fn uninstantiated() {
+        let mut hash_drbg = HashDRBG::new(10, ToyDigest::default()).unwrap();
+        let mut hmac_drbg = HMACDRBG::new(10, ToyDigest::default()).unwrap();
+        assert!(*hmac_drbg.outlen() == 32 && *hmac_drbg.block_size() == 64);
+        assert!(*hash_drbg.outlen() == 32 && *hash_drbg.seedlen() == 55);
+        assert!(*HashDRBG::new(10, SHA2::sha384()).unwrap().seedlen() == 111);
+        assert!(*HashDRBG::new(10, SHA2::sha512_224()).unwrap().seedlen() == 55);
+        assert!(HashDRBG::new(0, ToyDigest::default()).is_err());
+        assert!(HMACDRBG::new(0, ToyDigest::default()).is_err());
+        assert!(matches!(hash_drbg.next(), Err(Error::StateNotInitialized)));
+        assert!(matches!(hmac_drbg.next(), Err(Error::StateNotInitialized)));
+        assert!(hash_drbg.refresh(vec![0; 32]).is_err());
+        assert!(hmac_drbg.refresh(vec![0; 32]).is_err());
+    }
+
+    #[test]
+    fn reseed_counter() {
+        let mut drbg = HMACDRBG::new(2, ToyDigest::default()).unwrap();
+        drbg.instantiate([1; 32], [2; 16], []).unwrap();
+        drbg.next().unwrap();
+        drbg.next().unwrap();
+        assert!(*drbg.reseed_counter() == 3);
+        assert!(matches!(drbg.next(), Err(Error::ReseedRequired)));
+
+        drbg.set_source(Box::new(FakeSource::new("fake", 5, 32)));
+        drbg.next().unwrap();
+        assert!(*drbg.reseed_counter() == 2);
+    }
+
+    #[test]
+    fn prediction_resistance() {
+        let mut a = HashDRBG::new(100, ToyDigest::default()).unwrap();
+        let mut b = HashDRBG::new(100, ToyDigest::default()).unwrap();
+        assert!(a.set_prediction_resistance(true).is_err());
+        for drbg in [&mut a, &mut b] {
+            drbg.instantiate([1; 32], [2; 16], b"pers").unwrap();
+            drbg.set_source(Box::new(FakeSource::new("fake", 5, 32)));
+        }
+        a.set_prediction_resistance(true).unwrap();
+
+        // Prediction resistance reseeds before every request
+        let (x, y) = (a.generate(40, b"add").unwrap(), b.generate(40, b"add").unwrap());
+        assert!(x.len() == 40 && x != y);
+        assert!(*a.reseed_counter() == 2 && *b.reseed_counter() == 2);
+
+        b.reseed(FakeSource::new("fake", 5, 32).poll().unwrap(), b"add").unwrap();
+        assert!(a.generate(40, []).unwrap() != b.generate(40, []).unwrap());
+    }
+
+    #[test]
+    fn determinism() {
+        let mut a = HashDRBG::new(100, ToyDigest::default()).unwrap();
+        let mut b = HashDRBG::new(100, ToyDigest::default()).unwrap();
+        a.instantiate([1; 32], [2; 16], []).unwrap();
+        b.instantiate([1; 32], [2; 16], []).unwrap();
+        assert!(a.generate(100, b"x").unwrap() == b.generate(100, b"x").unwrap());
+        assert!(a.generate(100, b"x").unwrap() != b.generate(100, b"y").unwrap());
+        assert!(a.generate(MAX_REQUEST_BYTES + 1, []).is_err());
+    }
+
+    #[test]
+    fn wipe_and_redact() {
+        let mut hash_drbg = HashDRBG::new(10, ToyDigest::default()).unwrap();
+        let mut hmac_drbg = HMACDRBG::new(10, ToyDigest::default()).unwrap();
+        hash_drbg.instantiate([1; 32], [2; 16], []).unwrap();
+        hmac_drbg.instantiate([1; 32], [2; 16], []).unwrap();
+        assert!(format!("{:?}", hash_drbg).contains("<redacted>"));
+        assert!(format!("{:?}", hmac_drbg).contains("<redacted>"));
+
+        hash_drbg.zeroize();
+        hmac_drbg.zeroize();
+        assert!(hash_drbg.V.is_empty() && hash_drbg.C.is_empty());
+        assert!(hmac_drbg.K.is_empty() && hmac_drbg.V.is_empty());
+        assert!(matches!(hash_drbg.next(), Err(Error::StateNotInitialized)));
+        assert!(matches!(hmac_drbg.refresh(vec![0; 32]), Err(Error::StateNotInitialized)));
+    }
+
+    /// Run the records of the response file `content` through a fresh generator each, comparing the last output.
+    /// With prediction resistance, each generate call is preceded by a reseed with the given entropy
+    /// and additional input, as done internally with an entropy source.
+    /// Without it, the generator is reseeded first unless the file has no reseed input.
+    macro_rules! check_drbg {
+        ($new:expr, $content:expr) => {
+            for record in parse_rsp($content) {
+                let mut drbg = $new;
+                let expected = record.get_hex("ReturnedBits").unwrap();
+                let additional = record.get_all_hex("AdditionalInput").unwrap();
+                drbg.instantiate(record.get_hex("EntropyInput").unwrap(), record.get_hex("Nonce").unwrap(),
+                    record.get_hex("PersonalizationString").unwrap()).unwrap();
+
+                let mut output = Vec::new();
+                if record.header("PredictionResistance") == Some("True") {
+                    for (entropy, add) in record.get_all_hex("EntropyInputPR").unwrap().iter().zip(&additional) {
+                        drbg.reseed(entropy, add).unwrap();
+                        output = drbg.generate(expected.len(), []).unwrap();
+                    }
+                } else {
+                    if let Ok(entropy) = record.get_hex("EntropyInputReseed") {
+                        drbg.reseed(entropy, record.get_hex("AdditionalInputReseed").unwrap()).unwrap();
+                    }
+                    for add in &additional {
+                        output = drbg.generate(expected.len(), add).unwrap();
+                    }
+                }
+                assert!(output == expected, "COUNT = {}", record.get("COUNT").unwrap());
+            }
+        };
+    }
+
+    #[test]
+    fn cavs_hmac_sha256() {
+        check_drbg!(HMACDRBG::new(100, SHA2::sha256()).unwrap(),
+            include_str!("../../vectors/drbg/HMAC_DRBG.rsp"));
+    }
+
+    #[test]
+    fn cavs_hash_sha256() {
+        check_drbg!(HashDRBG::new(100, SHA2::sha256()).unwrap(),
+            include_str!("../../vectors/drbg/Hash_DRBG_no_reseed.rsp"));
+    }
+
+    #[test]
+    fn reference_hash_sha256() {
+        // Not from CAVS, see the header of the file
+        check_drbg!(HashDRBG::new(100, SHA2::sha256()).unwrap(),
+            include_str!("../../vectors/drbg/hash_drbg_sha256.rsp"));
+    }
+}
@@ -0,0 +1,738 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing min-entropy estimators for noise source samples.
+//!
+//! Implements the non-IID estimators of [NIST SP 800-90B](https://doi.org/10.6028/NIST.SP.800-90B), Section 6.3:
+//! most common value, collision, Markov, compression, t-tuple, longest repeated substring (LRS),
+//! and the MultiMCW, Lag, MultiMMC and LZ78Y predictors.
+//! Samples are given as bytes holding `n` bits each (1 <= n <= 8).
+//! The collision, Markov and compression estimators only apply to binary samples,
+//! and are therefore run on the bitstring obtained by concatenating the samples.
+//!
+//! The `EntropyGate` structure uses these estimates to reject `refresh` inputs
+//! that do not carry enough min-entropy, as assumed by the robustness proof of the SPRNG.
+
+use crate::error::Error;
+use std::collections::{HashMap, BTreeMap};
+use getset::Getters;
+
+use crate::prng::PRNG;
+use crate::utilities::bitbuf::{BitBuf, BitOrder};
+
+/// Upper bound of the 99% confidence interval of a proportion `p` estimated on `n` samples.
+fn upper_bound(p: f64, n: usize) -> f64 {
+    (p + 2.576 * (p * (1.0 - p) / (n as f64 - 1.0)).sqrt()).min(1.0)
+}
+
+/// Check that the samples are binary.
+fn check_binary(bits: &[u8], estimator: &str) -> Result<(), Error> {
+    if bits.iter().any(|&b| b > 1) {
+        return Err(Error::invalid("samples", format!("SP800-90B {}: samples must be binary", estimator)));
+    }
+    Ok(())
+}
+
+/// Check that there are at least `min` samples.
+fn check_length(samples: &[u8], min: usize, estimator: &str) -> Result<usize, Error> {
+    if samples.len() < min {
+        return Err(Error::invalid("samples", format!("SP800-90B {}: at least {} samples are required, got {}",
+            estimator, min, samples.len())));
+    }
+    Ok(samples.len())
+}
+
+/// Convert samples of `n` bits into a bitstring, MSB-first.
+pub fn to_bitstring(samples: &[u8], n: usize) -> Vec<u8> {
+    let mut bits = BitBuf::new(BitOrder::MsbFirst);
+    for sample in samples {
+        bits.append(sample, n);
+    }
+    bits.to_bits()
+}
+
+// Estimators.
+
+/// Most common value estimate (Section 6.3.1).
+pub fn most_common_value(samples: &[u8]) -> Result<f64, Error> {
+    let L = check_length(samples, 2, "Most Common Value")?;
+    let mut counts = [0_usize; 256];
+    for &s in samples {
+        counts[s as usize] += 1;
+    }
+
+    let p = *counts.iter().max().unwrap_or(&0) as f64 / L as f64;
+    Ok(-upper_bound(p, L).log2())
+}
+
+/// Collision estimate (Section 6.3.2), for binary samples.
+///
+/// For binary samples, the expected collision time solved by the binary search of the standard
+/// reduces to `2 + 2p(1-p)`, which is inverted directly.
+pub fn collision(bits: &[u8]) -> Result<f64, Error> {
+    check_length(bits, 3, "Collision")?;
+    check_binary(bits, "Collision")?;
+
+    // Collision times: 2 if the next two samples are equal, 3 otherwise
+    let mut times: Vec<f64> = Vec::new();
+    let mut i = 0;
+    while i + 1 < bits.len() {
+        let t = if bits[i] == bits[i + 1] {
+            2
+        } else if i + 2 < bits.len() {
+            3
+        } else {
+            break;
+        };
+        times.push(t as f64);
+        i += t;
+    }
+
+    let v = times.len() as f64;
+    if v < 2.0 {
+        return Err(Error::invalid("samples", "SP800-90B Collision: not enough collisions"));
+    }
+    let mean = times.iter().sum::<f64>() / v;
+    let sigma = (times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (v - 1.0)).sqrt();
+    let mean_prime = mean - 2.576 * sigma / v.sqrt();
+
+    let p = if mean_prime >= 2.5 {
+        0.5
+    } else if mean_prime <= 2.0 {
+        1.0
+    } else {
+        (1.0 + (5.0 - 2.0 * mean_prime).sqrt()) / 2.0
+    };
+    Ok(-p.log2())
+}
+
+/// Markov estimate (Section 6.3.3), for binary samples.
+pub fn markov(bits: &[u8]) -> Result<f64, Error> {
+    let L = check_length(bits, 2, "Markov")?;
+    check_binary(bits, "Markov")?;
+
+    let ones = bits.iter().filter(|&&b| b == 1).count() as f64;
+    let P = [1.0 - ones / L as f64, ones / L as f64];
+
+    let mut transitions = [[0.0_f64; 2]; 2];
+    for w in bits.windows(2) {
+        transitions[w[0] as usize][w[1] as usize] += 1.0;
+    }
+    for row in transitions.iter_mut() {
+        let total = row[0] + row[1];
+        if total > 0.0 {
+            row[0] /= total;
+            row[1] /= total;
+        }
+    }
+    let T = |a: usize, b: usize| transitions[a][b].ln();
+
+    // Log-probabilities of the most likely sequences of 128 bits
+    let candidates = [
+        P[0].ln() + 127.0 * T(0, 0),
+        P[0].ln() + 64.0 * T(0, 1) + 63.0 * T(1, 0),
+        P[0].ln() + T(0, 1) + 126.0 * T(1, 1),
+        P[1].ln() + T(1, 0) + 126.0 * T(0, 0),
+        P[1].ln() + 64.0 * T(1, 0) + 63.0 * T(0, 1),
+        P[1].ln() + 127.0 * T(1, 1),
+    ];
+    let ln_max = candidates.iter().cloned().filter(|c| !c.is_nan()).fold(f64::NEG_INFINITY, f64::max);
+
+    Ok((-ln_max / 2.0_f64.ln() / 128.0).min(1.0))
+}
+
+/// Compression estimate (Section 6.3.4), for binary samples.
+pub fn compression(bits: &[u8]) -> Result<f64, Error> {
+    const B: usize = 6;
+    const D: usize = 1000;
+    check_binary(bits, "Compression")?;
+    check_length(bits, B * (D + 2), "Compression")?;
+
+    let blocks: Vec<usize> = bits.chunks_exact(B)
+        .map(|chunk| chunk.iter().fold(0, |acc, &b| (acc << 1) | b as usize))
+        .collect();
+    let L = blocks.len();
+    let nu = (L - D) as f64;
+
+    // Dictionary initialization with the first D blocks, then test on the remaining ones (1-based indices)
+    let mut dict = [0_usize; 1 << B];
+    for (i, &block) in blocks[..D].iter().enumerate() {
+        dict[block] = i + 1;
+    }
+    let (mut sum, mut sum2) = (0.0, 0.0);
+    for (i, &block) in blocks.iter().enumerate().skip(D) {
+        let distance = (i + 1 - dict[block]) as f64;
+        dict[block] = i + 1;
+        sum += distance.log2();
+        sum2 += distance.log2().powi(2);
+    }
+
+    let mean = sum / nu;
+    let sigma = 0.5907 * (sum2 / nu - mean * mean).max(0.0).sqrt();
+    let mean_prime = mean - 2.576 * sigma / nu.sqrt();
+
+    // G(z), with the double sum reduced to a single pass over the distances u
+    let G = |z: f64| -> f64 {
+        let mut total = 0.0;
+        let mut power = 1.0; // (1-z)^(u-1)
+        for u in 1..=L {
+            let log_u = (u as f64).log2();
+            let count = L - u.max(D); // t in [D+1, L] with t > u
+            total += log_u * z * z * power * count as f64;
+            if u > D {
+                total += log_u * z * power;
+            }
+            power *= 1.0 - z;
+        }
+        total / nu
+    };
+    let expected = |p: f64| -> f64 {
+        let q = (1.0 - p) / ((1 << B) - 1) as f64;
+        G(p) + ((1 << B) - 1) as f64 * G(q)
+    };
+
+    // The expected value decreases with p: binary search on [2^-B, 1]
+    let (mut lo, mut hi) = (1.0 / (1 << B) as f64, 1.0);
+    if mean_prime >= expected(lo) {
+        return Ok(1.0);
+    }
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.0;
+        if expected(mid) > mean_prime {lo = mid} else {hi = mid}
+    }
+    Ok((-lo.log2() / B as f64).min(1.0))
+}
+
+/// Suffix array and longest common prefix array (Kasai) of the samples.
+/// `lcp[i]` is the length of the common prefix of the suffixes `sa[i-1]` and `sa[i]`.
+fn suffix_array(samples: &[u8]) -> (Vec<usize>, Vec<usize>) {
+    let L = samples.len();
+    let mut sa: Vec<usize> = (0..L).collect();
+    let mut rank: Vec<usize> = samples.iter().map(|&s| s as usize).collect();
+    let mut next = vec![0_usize; L];
+
+    // Prefix doubling
+    let mut k = 1;
+    loop {
+        let key = |i: usize| (rank[i], if i + k < L {rank[i + k] + 1} else {0});
+        sa.sort_unstable_by_key(|&i| key(i));
+        next[sa[0]] = 0;
+        for w in 1..L {
+            next[sa[w]] = next[sa[w - 1]] + (key(sa[w - 1]) != key(sa[w])) as usize;
+        }
+        std::mem::swap(&mut rank, &mut next);
+        if rank[sa[L - 1]] == L - 1 || k >= L {
+            break;
+        }
+        k *= 2;
+    }
+
+    let mut lcp = vec![0_usize; L];
+    let mut h = 0;
+    for i in 0..L {
+        if rank[i] > 0 {
+            let j = sa[rank[i] - 1];
+            while i + h < L && j + h < L && samples[i + h] == samples[j + h] {
+                h += 1;
+            }
+            lcp[rank[i]] = h;
+            h = h.saturating_sub(1);
+        } else {
+            h = 0;
+        }
+    }
+    (sa, lcp)
+}
+
+/// Tuple statistics used by the t-tuple and LRS estimates, indexed by the tuple length `w`:
+/// the number of occurrences of the most common `w`-tuple,
+/// and the number of pairs of equal `w`-tuples.
+fn tuple_statistics(samples: &[u8]) -> (Vec<usize>, Vec<f64>) {
+    let L = samples.len();
+    let (_, lcp) = suffix_array(samples);
+    let v = *lcp.iter().max().unwrap_or(&0);
+
+    // Each lcp[i] is the minimum of the intervals lcp[left+1..right], with ties attributed
+    // to the leftmost index when counting pairs, and to every index when measuring extents
+    let mut occurrences = vec![1_usize; v + 2];
+    let mut pairs = vec![0.0_f64; v + 2];
+    let mut stack: Vec<usize> = Vec::new();
+    let mut left_strict = vec![0_usize; L];
+    let mut left_loose = vec![0_usize; L];
+    for i in 1..L {
+        while let Some(&top) = stack.last() {
+            if lcp[top] >= lcp[i] {stack.pop();} else {break;}
+        }
+        left_strict[i] = *stack.last().unwrap_or(&0);
+        stack.push(i);
+    }
+    stack.clear();
+    for i in 1..L {
+        while let Some(&top) = stack.last() {
+            if lcp[top] > lcp[i] {stack.pop();} else {break;}
+        }
+        left_loose[i] = *stack.last().unwrap_or(&0);
+        stack.push(i);
+    }
+    stack.clear();
+    for i in (1..L).rev() {
+        while let Some(&top) = stack.last() {
+            if lcp[top] >= lcp[i] {stack.pop();} else {break;}
+        }
+        let right = *stack.last().unwrap_or(&L);
+        stack.push(i);
+
+        let m = lcp[i];
+        occurrences[m] = occurrences[m].max(right - left_strict[i]);
+        pairs[m] += ((i - left_loose[i]) * (right - i)) as f64;
+    }
+
+    // Tuples of length w are shared by intervals with a minimum lcp of at least w
+    for w in (0..=v).rev() {
+        occurrences[w] = occurrences[w].max(occurrences[w + 1]);
+        pairs[w] += pairs[w + 1];
+    }
+    occurrences.truncate(v + 1);
+    pairs.truncate(v + 1);
+    (occurrences, pairs)
+}
+
+/// t-Tuple estimate (Section 6.3.5).
+/// Returns `None` when no value appears at least 35 times.
+pub fn t_tuple(samples: &[u8]) -> Result<Option<f64>, Error> {
+    let L = check_length(samples, 35, "t-Tuple")?;
+    let (Q, _) = tuple_statistics(samples);
+    let t = Q.iter().skip(1).take_while(|&&q| q >= 35).count();
+    if t == 0 {
+        return Ok(None);
+    }
+
+    let p_max = (1..=t)
+        .map(|i| (Q[i] as f64 / (L - i + 1) as f64).powf(1.0 / i as f64))
+        .fold(0.0, f64::max);
+    Ok(Some(-upper_bound(p_max, L).log2()))
+}
+
+/// Longest repeated substring estimate (Section 6.3.6).
+/// Returns `None` when no tuple longer than the t-tuple threshold is repeated.
+pub fn lrs(samples: &[u8]) -> Result<Option<f64>, Error> {
+    let L = check_length(samples, 35, "LRS")?;
+    let (Q, pairs) = tuple_statistics(samples);
+    let u = Q.iter().skip(1).take_while(|&&q| q >= 35).count() + 1;
+    let v = pairs.len() - 1;
+    if u > v {
+        return Ok(None);
+    }
+
+    let p_max = (u..=v)
+        .map(|W| {
+            let total = (L - W + 1) as f64;
+            (pairs[W] / (total * (total - 1.0) / 2.0)).powf(1.0 / W as f64)
+        })
+        .fold(0.0, f64::max);
+    Ok(Some(-upper_bound(p_max, L).log2()))
+}
+
+// Predictors.
+
+/// Probability bound `P_local` from the longest run `r - 1` of correct predictions among `N`.
+fn local_probability(N: usize, r: usize) -> f64 {
+    let r_f = r as f64;
+    let f = |p: f64| -> f64 {
+        let q = 1.0 - p;
+        let mut x: f64 = 1.0;
+        for _ in 0..10 {
+            x = 1.0 + q * p.powi(r as i32) * x.powi(r as i32 + 1);
+        }
+        let ln = (1.0 - p * x).ln() - ((r_f + 1.0 - r_f * x) * q).ln() - (N as f64 + 1.0) * x.ln();
+        ln.exp()
+    };
+
+    // Probability of no run of length r decreases with p: binary search for 0.99
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.0;
+        let value = f(mid);
+        if value.is_nan() || value < 0.99 {hi = mid} else {lo = mid}
+    }
+    lo
+}
+
+/// Min-entropy from the predictions of a predictor, for an alphabet of size `k` (Section 6.3.7, steps 4 to 7).
+fn predictor_estimate(correct: &[bool], k: usize) -> f64 {
+    let N = correct.len();
+    let C = correct.iter().filter(|&&c| c).count();
+
+    let p_global = C as f64 / N as f64;
+    let p_global_prime = if C == 0 {
+        1.0 - 0.01_f64.powf(1.0 / N as f64)
+    } else {
+        upper_bound(p_global, N)
+    };
+
+    let (mut run, mut longest) = (0, 0);
+    for &c in correct {
+        run = if c {run + 1} else {0};
+        longest = longest.max(run);
+    }
+    let p_local = local_probability(N, longest + 1);
+
+    -p_global_prime.max(p_local).max(1.0 / k as f64).log2()
+}
+
+/// Ensemble of subpredictors with a scoreboard, as used by all the predictor estimators.
+struct Scoreboard {
+    scores: Vec<usize>,
+    winner: usize,
+}
+
+impl Scoreboard {
+    fn new(d: usize) -> Self {
+        Self { scores: vec![0; d], winner: 0 }
+    }
+
+    /// Record the subpredictions for the actual value `s`, and return whether the winner predicted it.
+    fn update(&mut self, predictions: &[Option<u8>], s: u8) -> bool {
+        let correct = predictions[self.winner] == Some(s);
+        for (j, &prediction) in predictions.iter().enumerate() {
+            if prediction == Some(s) {
+                self.scores[j] += 1;
+                if self.scores[j] >= self.scores[self.winner] {
+                    self.winner = j;
+                }
+            }
+        }
+        correct
+    }
+}
+
+/// Value with the largest count, ties broken by the largest value.
+fn argmax(counts: &BTreeMap<u8, usize>) -> Option<(u8, usize)> {
+    counts.iter().fold(None, |best, (&y, &c)| match best {
+        Some((_, bc)) if bc > c => best,
+        _ => Some((y, c)),
+    })
+}
+
+/// Multi Most Common in Window prediction estimate (Section 6.3.7), for an alphabet of size `k`.
+pub fn multi_mcw(samples: &[u8], k: usize) -> Result<f64, Error> {
+    const W: [usize; 4] = [63, 255, 1023, 4095];
+    let L = check_length(samples, W[0] + 2, "MultiMCW")?;
+
+    let mut counts = vec![[0_usize; 256]; W.len()];
+    let mut last_seen = [0_usize; 256];
+    let mut scoreboard = Scoreboard::new(W.len());
+    let mut correct: Vec<bool> = Vec::with_capacity(L - W[0]);
+
+    for i in 0..L {
+        if i >= W[0] {
+            // Most frequent value in each window, ties broken by the most recent value
+            let predictions: Vec<Option<u8>> = W.iter().zip(counts.iter())
+                .map(|(&w, count)| {
+                    if i < w {
+                        return None;
+                    }
+                    (0..256_usize)
+                        .filter(|&y| count[y] > 0)
+                        .max_by_key(|&y| (count[y], last_seen[y]))
+                        .map(|y| y as u8)
+                })
+                .collect();
+            correct.push(scoreboard.update(&predictions, samples[i]));
+        }
+
+        // Slide the windows
+        for (&w, count) in W.iter().zip(counts.iter_mut()) {
+            count[samples[i] as usize] += 1;
+            if i >= w {
+                count[samples[i - w] as usize] -= 1;
+            }
+        }
+        last_seen[samples[i] as usize] = i;
+    }
+
+    Ok(predictor_estimate(&correct, k))
+}
+
+/// Lag prediction estimate (Section 6.3.8), for an alphabet of size `k`.
+pub fn lag(samples: &[u8], k: usize) -> Result<f64, Error> {
+    const D: usize = 128;
+    let L = check_length(samples, 3, "Lag")?;
+
+    let mut scoreboard = Scoreboard::new(D);
+    let correct: Vec<bool> = (1..L)
+        .map(|i| {
+            let predictions: Vec<Option<u8>> = (1..=D)
+                .map(|d| if i >= d {Some(samples[i - d])} else {None})
+                .collect();
+            scoreboard.update(&predictions, samples[i])
+        })
+        .collect();
+
+    Ok(predictor_estimate(&correct, k))
+}
+
+/// Multi Markov Model with Counting prediction estimate (Section 6.3.9), for an alphabet of size `k`.
+pub fn multi_mmc(samples: &[u8], k: usize) -> Result<f64, Error> {
+    const D: usize = 16;
+    let L = check_length(samples, 4, "MultiMMC")?;
+
+    let mut models: Vec<HashMap<&[u8], BTreeMap<u8, usize>>> = vec![HashMap::new(); D];
+    let mut scoreboard = Scoreboard::new(D);
+    let mut correct: Vec<bool> = Vec::with_capacity(L - 2);
+
+    for c in 2..L {
+        let mut predictions: Vec<Option<u8>> = vec![None; D];
+        for (j, model) in models.iter_mut().enumerate() {
+            let d = j + 1;
+            // Update with the transition ending at the previous sample
+            if c > d {
+                let entry = model.entry(&samples[c - d - 1..c - 1]).or_default();
+                *entry.entry(samples[c - 1]).or_insert(0) += 1;
+            }
+            // Predict from the last d samples
+            if c >= d {
+                predictions[j] = model.get(&samples[c - d..c]).and_then(argmax).map(|(y, _)| y);
+            }
+        }
+        correct.push(scoreboard.update(&predictions, samples[c]));
+    }
+
+    Ok(predictor_estimate(&correct, k))
+}
+
+/// LZ78Y prediction estimate (Section 6.3.10), for an alphabet of size `k`.
+pub fn lz78y(samples: &[u8], k: usize) -> Result<f64, Error> {
+    const B: usize = 16;
+    const MAX_DICTIONARY_SIZE: usize = 65536;
+    let L = check_length(samples, B + 3, "LZ78Y")?;
+
+    let mut dictionary: HashMap<&[u8], BTreeMap<u8, usize>> = HashMap::new();
+    let mut correct: Vec<bool> = Vec::with_capacity(L - B - 1);
+
+    for c in (B + 1)..L {
+        // Update the dictionary with the strings ending at the previous sample
+        for j in (1..=B).rev() {
+            let context = &samples[c - j - 1..c - 1];
+            if !dictionary.contains_key(context) && dictionary.len() < MAX_DICTIONARY_SIZE {
+                dictionary.insert(context, BTreeMap::new());
+            }
+            if let Some(entry) = dictionary.get_mut(context) {
+                *entry.entry(samples[c - 1]).or_insert(0) += 1;
+            }
+        }
+
+        // Predict with the longest context having the largest count
+        let mut prediction: Option<u8> = None;
+        let mut max_count = 0;
+        for j in (1..=B).rev() {
+            if let Some((y, count)) = dictionary.get(&samples[c - j..c]).and_then(argmax) {
+                if count > max_count {
+                    prediction = Some(y);
+                    max_count = count;
+                }
+            }
+        }
+        correct.push(prediction == Some(samples[c]));
+    }
+
+    Ok(predictor_estimate(&correct, k))
+}
+
+// Assessment.
+
+#[derive(Clone, Debug)]
+/// Result of the non-IID assessment of a sequence of samples.
+pub struct Assessment {
+    /// Estimates on the original samples, in bits per sample.
+    pub original: Vec<(&'static str, f64)>,
+
+    /// Estimates on the bitstring, in bits per bit.
+    pub bitstring: Vec<(&'static str, f64)>,
+
+    /// Minimum of the estimates on the original samples.
+    pub h_original: f64,
+
+    /// Minimum of the estimates on the bitstring.
+    pub h_bitstring: f64,
+
+    /// Assessed min-entropy per sample: `min(h_original, n * h_bitstring)`.
+    pub min_entropy: f64,
+}
+
+/// Estimators applying to samples of any size.
+fn general_estimates(samples: &[u8], k: usize) -> Result<Vec<(&'static str, f64)>, Error> {
+    let mut estimates = vec![("Most Common Value", most_common_value(samples)?)];
+    if let Some(h) = t_tuple(samples)? {
+        estimates.push(("t-Tuple", h));
+    }
+    if let Some(h) = lrs(samples)? {
+        estimates.push(("LRS", h));
+    }
+    estimates.extend([
+        ("MultiMCW", multi_mcw(samples, k)?),
+        ("Lag", lag(samples, k)?),
+        ("MultiMMC", multi_mmc(samples, k)?),
+        ("LZ78Y", lz78y(samples, k)?),
+    ]);
+    Ok(estimates)
+}
+
+/// Run all the non-IID estimators on samples of `n` bits.
+pub fn assess(samples: &[u8], n: usize) -> Result<Assessment, Error> {
+    if n == 0 || n > 8 {
+        return Err(Error::invalid("n", "SP800-90B: sample size must be between 1 and 8 bits"));
+    }
+    if samples.iter().any(|&s| (s as usize) >> n != 0) {
+        return Err(Error::invalid("samples", "SP800-90B: sample value out of range"));
+    }
+
+    let bits = to_bitstring(samples, n);
+    let mut bitstring = general_estimates(&bits, 2)?;
+    bitstring.extend([
+        ("Collision", collision(&bits)?),
+        ("Markov", markov(&bits)?),
+        ("Compression", compression(&bits)?),
+    ]);
+    let original = if n == 1 {bitstring.clone()} else {general_estimates(samples, 1 << n)?};
+
+    let minimum = |estimates: &[(&'static str, f64)]| estimates.iter().map(|&(_, h)| h).fold(f64::INFINITY, f64::min);
+    let (h_original, h_bitstring) = (minimum(&original), minimum(&bitstring));
+
+    Ok(Assessment {
+        original,
+        bitstring,
+        h_original,
+        h_bitstring,
+        min_entropy: h_original.min(n as f64 * h_bitstring),
+    })
+}
+
+// Entropy gate.
+
+#[derive(Getters, Clone, Debug)]
+/// Gate rejecting `refresh` inputs whose assessed min-entropy is too low.
+pub struct EntropyGate
+{
+    /// Number of bits per sample.
+    #[getset(get = "pub")]
+    n: usize,
+
+    /// Minimum total min-entropy required, in bits.
+    #[getset(get = "pub")]
+    required: usize,
+}
+
+impl EntropyGate
+{
+    /// Setup function.
+    /// The parameters are the number of bits per sample and the minimum total min-entropy in bits.
+    pub fn new(params: Vec<usize>) -> Result<Self, Error> {
+        if params.len() != 2 {
+            return Err(Error::BadLength { field: "params", expected: 2, got: params.len() });
+        }
+        let (n, required) = (params[0], params[1]);
+        if !(1..=8).contains(&n) {
+            return Err(Error::invalid("n", "sample size n must be between 1 and 8 bits"));
+        }
+
+        Ok(Self { n, required })
+    }
+
+    /// Assess the samples and return their total min-entropy, or an error if it is below the requirement.
+    pub fn check(&self, samples: &[u8]) -> Result<f64, Error> {
+        let total = assess(samples, self.n)?.min_entropy * samples.len() as f64;
+        if total < self.required as f64 {
+            return Err(Error::InsufficientEntropy { estimated: total, required: self.required as f64 });
+        }
+        Ok(total)
+    }
+
+    /// Refresh the PRNG with the samples if they carry enough min-entropy.
+    pub fn refresh<P>(&self, prng: &mut P, samples: Vec<u8>) -> Result<f64, Error>
+    where
+        P: PRNG<Input = u8>
+    {
+        let total = self.check(&samples)?;
+        prng.refresh(samples)?;
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use crate::prng::{sprng::{SPRNG, SPRNGParams}, accumulator::EntropyAccumulator};
+
+    fn random_bytes(L: usize, seed: u64) -> Vec<u8> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..L).map(|_| rng.gen::<u8>()).collect()
+    }
+
+    #[test]
+    fn constant_samples() {
+        let samples = vec![3_u8; 10_000];
+        assert!(most_common_value(&samples).unwrap() == 0.0);
+        assert!(t_tuple(&samples).unwrap() == Some(0.0));
+        assert!(lag(&samples, 256).unwrap() < 0.01);
+        assert!(multi_mcw(&samples, 256).unwrap() < 0.01);
+
+        let bits = vec![1_u8; 10_000];
+        assert!(collision(&bits).unwrap() == 0.0);
+        assert!(markov(&bits).unwrap() == 0.0);
+        assert!(compression(&bits).unwrap() < 0.01);
+    }
+
+    #[test]
+    fn uniform_bits() {
+        let bits = to_bitstring(&random_bytes(2_000, 1), 8);
+        for h in [collision(&bits), markov(&bits), compression(&bits), most_common_value(&bits)] {
+            let h = h.unwrap();
+            assert!(h > 0.6 && h <= 1.0);
+        }
+    }
+
+    #[test]
+    fn biased_bits() {
+        // P(1) = 3/4, i.e. min-entropy -log2(3/4) = 0.415 bits per bit
+        let mut rng = StdRng::seed_from_u64(2);
+        let bits: Vec<u8> = (0..20_000).map(|_| (rng.gen::<u8>() % 4 != 0) as u8).collect();
+        for h in [most_common_value(&bits), collision(&bits), markov(&bits)] {
+            let h = h.unwrap();
+            assert!(h > 0.35 && h < 0.45);
+        }
+    }
+
+    #[test]
+    fn periodic_samples() {
+        let samples: Vec<u8> = (0..5_000).map(|i| (i % 7) as u8 * 31).collect();
+        assert!(most_common_value(&samples).unwrap() > 2.5);
+        for h in [lag(&samples, 256), multi_mmc(&samples, 256), lz78y(&samples, 256)] {
+            assert!(h.unwrap() < 0.05);
+        }
+    }
+
+    #[test]
+    fn uniform_bytes() {
+        let samples = random_bytes(5_000, 3);
+        let assessment = assess(&samples, 8).unwrap();
+        assert!(assessment.original.len() == 7);
+        assert!(assessment.bitstring.len() == 10);
+        assert!(assessment.min_entropy > 5.0 && assessment.min_entropy <= 8.0);
+        assert!(assess(&samples, 4).is_err());
+    }
+
+    #[test]
+    fn gate() {
+        let gate = EntropyGate::new(vec![8, 4_000]).unwrap();
+        assert!(gate.check(&random_bytes(2_000, 4)).is_ok());
+        assert!(gate.check(&[0xAA_u8; 2_000]).is_err());
+
+        let mut prng = EntropyAccumulator::new(vec![4, 16, 0],
+            SPRNG::with_seed(SPRNGParams::builder().n(64).r(8).s(2).build().unwrap(), |x: u64| x.rotate_left(17) ^ 0x9E3779B97F4A7C15, vec![1, 2]).unwrap()
+        ).unwrap();
+        assert!(gate.refresh(&mut prng, vec![0_u8; 2_000]).is_err());
+        assert!(gate.refresh(&mut prng, random_bytes(2_000, 5)).is_ok());
+    }
+}
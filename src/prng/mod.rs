@@ -6,7 +6,7 @@
 //! This module groups all Pseudo Random Number Generators (PRNGs) implementations.
 //! These are accessible through the PRNG trait, which implements the `refresh` and `next` functions.
 
-use std::io::Error;
+use crate::error::Error;
 
 /// Trait for Pseudo Random Number Generators,
 /// with public, general-purpose functions `refresh` and `next`.
@@ -26,4 +26,7 @@ pub trait PRNG
     fn next(&mut self) -> Result<Self::Output, Error>;
 }
 
-pub mod sprng;
\ No newline at end of file
+pub mod sprng;
+pub mod accumulator;
+pub mod drbg;
+pub mod entropy;
\ No newline at end of file
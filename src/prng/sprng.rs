@@ -5,25 +5,156 @@
 //!
 //! Based on the PRNG of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4).
 
-use std::io::Error;
+use crate::error::Error;
 use getset::Getters;
-use rand::{Rng, thread_rng, distributions::Standard, prelude::Distribution};
+use rand::{Rng, RngCore, CryptoRng, SeedableRng, thread_rng, distributions::Standard, prelude::Distribution};
 use std::{ops::{BitXor, BitAnd, BitOr, Not, Sub, Shl}, convert::From};
+use std::fmt::{self, Debug, Formatter};
 
 use crate::prng::PRNG;
+use crate::utilities::{ToLeBytes, StateWidth, ustates::Ux4, bitbuf::{BitBuf, BitOrder}, zeroize::Zeroize};
+use crate::utilities::bitops::{low_mask, check_width};
+use crate::hash::siphash::SipHash_perm;
+
+// SPRNG parameters.
+
+#[derive(Getters, Clone, Copy, Debug, PartialEq, Eq)]
+/// Parameters of the SPRNG, built with [`SPRNGParams::builder`] or taken from a preset.
+pub struct SPRNGParams
+{
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    n: usize,
+
+    /// Size of the outer part (rate).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Number of permutation calls per `next`.
+    #[getset(get = "pub")]
+    t: usize,
+
+    /// Length of the seed.
+    #[getset(get = "pub")]
+    s: usize,
+}
+
+impl SPRNGParams
+{
+    /// Start building parameters. `t` defaults to 1, the other fields must be set.
+    pub fn builder() -> SPRNGParamsBuilder {
+        SPRNGParamsBuilder::default()
+    }
+
+    /// 64-bit state with r = 4, t = 1 and s = 3, as in the `sprng_64` demo.
+    pub fn sprng_64() -> Self {
+        Self { n: 64, r: 4, t: 1, s: 3 }
+    }
+
+    /// 256-bit state with r = 32, t = 1 and s = 3, as in the `sprng_256` demo and `SPRNGRng::from_seed`.
+    pub fn sprng_256() -> Self {
+        Self { n: 256, r: 32, t: 1, s: 3 }
+    }
+
+    /// 320-bit state with r = 64, t = 1 and s = 2, for Ascon-sized permutations.
+    pub fn sprng_320() -> Self {
+        Self { n: 320, r: 64, t: 1, s: 2 }
+    }
+
+    /// Check the relations between the parameters.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.r == 0 {
+            return Err(Error::invalid("r", "rate r must be greater than 0"));
+        }
+        if self.r > self.n {
+            return Err(Error::invalid("r", "rate r must be less than or equal to the state size n"));
+        }
+        if self.t == 0 {
+            return Err(Error::invalid("t", "number of permutation calls t must be greater than 0"));
+        }
+        if self.s <= 1 {
+            return Err(Error::invalid("s", "seed size s must be greater than 1"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Builder for [`SPRNGParams`].
+pub struct SPRNGParamsBuilder
+{
+    n: Option<usize>,
+    r: Option<usize>,
+    t: usize,
+    s: Option<usize>,
+}
+
+impl Default for SPRNGParamsBuilder
+{
+    fn default() -> Self {
+        Self { n: None, r: None, t: 1, s: None }
+    }
+}
+
+impl SPRNGParamsBuilder
+{
+    /// Set the size of the state in bits.
+    pub fn n(mut self, n: usize) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Set the rate.
+    pub fn r(mut self, r: usize) -> Self {
+        self.r = Some(r);
+        self
+    }
+
+    /// Set the number of permutation calls per `next`.
+    pub fn t(mut self, t: usize) -> Self {
+        self.t = t;
+        self
+    }
+
+    /// Set the length of the seed.
+    pub fn s(mut self, s: usize) -> Self {
+        self.s = Some(s);
+        self
+    }
+
+    /// Check the parameters and build them.
+    pub fn build(self) -> Result<SPRNGParams, Error> {
+        let params = SPRNGParams {
+            n: self.n.ok_or_else(|| Error::invalid("n", "state size n must be set"))?,
+            r: self.r.ok_or_else(|| Error::invalid("r", "rate r must be set"))?,
+            t: self.t,
+            s: self.s.ok_or_else(|| Error::invalid("s", "seed size s must be set"))?,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
 
 // SPRNG structure.
 
 #[allow(dead_code)]
-#[derive(Getters, Clone, Debug)]
+#[derive(Getters, Clone)]
 /// Structure implementing the Sponge-based PRNG of [GT2016](https://doi.org/10.1007/978-3-662-49890-3_4).
 /// Note that the state of the sponge is reversed for easier use of the outputs.
 /// The outer part is stored in the lower bits.
 pub struct SPRNG<U>
 where
-    U: Clone
+    U: Clone + Zeroize
 {
-    /// Number of `next` calls.
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    n: usize,
+
+    /// Size of the outer part (rate).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Number of permutation calls per `next`.
     #[getset(get = "pub")]
     t: usize,
 
@@ -49,48 +180,104 @@ where
     state: U
 }
 
+impl<U> Zeroize for SPRNG<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the seed vector and the inner state.
+    /// The instance cannot be used after being wiped: `refresh` and `next` return `KeyNotSet`.
+    fn zeroize(&mut self) {
+        self.seed.zeroize();
+        self.state.zeroize();
+    }
+}
+
+impl<U> Drop for SPRNG<U>
+where
+    U: Clone + Zeroize
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<U> Debug for SPRNG<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the parameters, with the seed vector and the inner state redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SPRNG")
+            .field("n", &self.n)
+            .field("r", &self.r)
+            .field("t", &self.t)
+            .field("s", &self.s)
+            .field("seed", &format_args!("<redacted>"))
+            .field("state", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
 impl<U> SPRNG<U>
 where
-    U: Copy + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U> + BitOr<Output = U>
-    + Sub<Output = U>, Standard: Distribution<U>
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + Sub<Output = U>, Standard: Distribution<U>
 {
     /// Setup function.
-    pub fn new(params: Vec<usize>, func: fn(U) -> U) -> Result<Self, Error> {
-        assert!(params.len() == 4, "SPRNG Setup: wrong number of parameters. Expected 4, got {}.", params.len());
-        let (n, r, t, s) = (params[0], params[1], params[2], params[3]);
-        assert!(r <= n, "SPRNG Setup: rate r must be less than or equal to the state size n.");
-        assert!(s > 1, "SPRNG Setup: seed size s must be greater than 1.");
-
-        // Generate the mask
-        let mut mask: U = 1_u8.into();
-        mask = (mask << r) - 1_u8.into();
+    pub fn new(params: SPRNGParams, func: fn(U) -> U) -> Result<Self, Error> {
+        let s = params.s;
 
         // Generate the seed using rand
         let mut rng = thread_rng();
         let mut seed_vec: Vec<U> = Vec::with_capacity(s);
         for _ in 0..s {
-            seed_vec.push(rng.gen::<U>() & mask);
+            seed_vec.push(rng.gen::<U>());
         }
 
         // Initial state is r `0` bits and c random bits (n=c+r)
-        let mut state: U = 0_u8.into();
-        state = state | (rng.gen::<U>() & !mask);
+        let mut sprng = Self::with_seed(params, func, seed_vec)?;
+        sprng.state = sprng.state | (rng.gen::<U>() & !sprng.mask);
+
+        Ok(sprng)
+    }
+}
+
+impl<U> SPRNG<U>
+where
+    U: Copy + Zeroize + StateWidth + From<u8> + Shl<usize, Output = U> + BitAnd<Output = U> + Not<Output = U>
+    + BitOr<Output = U> + Sub<Output = U>
+{
+    /// Deterministic setup function.
+    /// The seed vector is given by the caller and the initial state is set to zero,
+    /// so that all the entropy of the state comes from subsequent `refresh` calls.
+    pub fn with_seed(params: SPRNGParams, func: fn(U) -> U, seed: Vec<U>) -> Result<Self, Error> {
+        params.validate()?;
+        let SPRNGParams { n, r, t, s } = params;
+        check_width::<U>("n", n)?;
+        if seed.len() != s {
+            return Err(Error::BadLength { field: "seed", expected: s, got: seed.len() });
+        }
+
+        // Generate the mask
+        let mask: U = low_mask(r);
 
         Ok(Self{
-            t: t,
-            s: s,
+            n,
+            r,
+            t,
+            s,
             j: 1_usize,
-            mask: mask,
+            mask,
             perm: func,
-            seed: seed_vec,
-            state: state
+            seed: seed.into_iter().map(|x| x & mask).collect(),
+            state: 0_u8.into()
         })
     }
 }
 
 impl<U> PRNG for SPRNG<U>
 where
-    U: Copy + From<u8> + Not<Output = U> + BitAnd<Output = U> + BitXor<Output = U>
+    U: Copy + Zeroize + From<u8> + Not<Output = U> + BitAnd<Output = U> + BitXor<Output = U>
 {
     type Input = U;
     type Output = U;
@@ -98,12 +285,17 @@ where
     /// General refresh function.
     fn refresh(&mut self, inputs: Vec<U>) -> Result<(), Error> {
         let l = inputs.len();
-        assert!(l > 0, "SPRNG Refresh: no inputs provided.");
+        if l == 0 {
+            return Err(Error::invalid("inputs", "no inputs provided"));
+        }
+        if self.seed.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
 
         // Refresh the state using all inputs, cycling through the seed vector
-        for i in 0..l {
+        for input in inputs {
             self.state = (self.perm)(self.state ^
-                ((inputs[i] ^ self.seed[self.j]) & self.mask)
+                ((input ^ self.seed[self.j]) & self.mask)
             );
             self.j = (self.j + 1) % self.s;
         }
@@ -113,6 +305,10 @@ where
 
     /// General next function.
     fn next(&mut self) -> Result<U, Error> {
+        if self.seed.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
+
         // Permute
         self.state = (self.perm)(self.state);
 
@@ -131,3 +327,266 @@ where
         Ok(R)
     }
 }
+
+// SPRNG adapter for the rand traits.
+
+#[derive(Clone)]
+/// Wrapper around [`SPRNG`] implementing the `RngCore`, `CryptoRng` and `SeedableRng` traits of `rand`.
+///
+/// Each call to `next` yields `r` bits, which are buffered and concatenated without gaps,
+/// so that `fill_bytes` can produce any number of bytes whatever the rate of the underlying SPRNG.
+pub struct SPRNGRng<U>
+where
+    U: Clone + Zeroize
+{
+    /// Underlying SPRNG.
+    sprng: SPRNG<U>,
+
+    /// Buffer of unused output bits (LSB-first).
+    bits: BitBuf
+}
+
+impl<U> Drop for SPRNGRng<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wipes the buffered output bits, the SPRNG wipes itself.
+    fn drop(&mut self) {
+        self.bits.zeroize();
+    }
+}
+
+impl<U> Debug for SPRNGRng<U>
+where
+    U: Clone + Zeroize
+{
+    /// Prints the underlying SPRNG, with the buffered output bits redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SPRNGRng")
+            .field("sprng", &self.sprng)
+            .field("bits", &format_args!("<redacted>"))
+            .finish()
+    }
+}
+
+impl<U> SPRNGRng<U>
+where
+    U: Clone + Zeroize
+{
+    /// Wrap an existing SPRNG.
+    pub fn new(sprng: SPRNG<U>) -> Self {
+        Self { sprng, bits: BitBuf::new(BitOrder::LsbFirst) }
+    }
+
+    /// Access the underlying SPRNG, e.g. to refresh it.
+    pub fn inner(&mut self) -> &mut SPRNG<U> {
+        &mut self.sprng
+    }
+}
+
+impl<U> SPRNGRng<U>
+where
+    U: Copy + Zeroize + From<u8> + Not<Output = U> + BitAnd<Output = U> + BitXor<Output = U> + ToLeBytes
+{
+    /// Collects `p` bytes of output bits, calling `next` as many times as needed.
+    fn next_p_bytes(&mut self, p: usize) -> Result<Vec<u8>, Error> {
+        let r = *self.sprng.r();
+        while self.bits.len() < p*8 {
+            let output = self.sprng.next()?;
+            self.bits.append(&output, r);
+        }
+
+        Ok(self.bits.take_bytes(p))
+    }
+}
+
+impl<U> RngCore for SPRNGRng<U>
+where
+    U: Copy + Zeroize + From<u8> + Not<Output = U> + BitAnd<Output = U> + BitXor<Output = U> + ToLeBytes
+{
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0_u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0_u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).expect("SPRNG: failed to produce output.")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        let bytes = self.next_p_bytes(dest.len()).map_err(rand::Error::new)?;
+        dest.copy_from_slice(&bytes);
+        Ok(())
+    }
+}
+
+impl<U> CryptoRng for SPRNGRng<U>
+where
+    U: Copy + Zeroize + From<u8> + Not<Output = U> + BitAnd<Output = U> + BitXor<Output = U> + ToLeBytes
+{}
+
+/// Permutation used by the seedable SPRNG: four rounds of the SipHash permutation.
+fn seedable_perm(mut state: Ux4<u64>) -> Ux4<u64> {
+    for _ in 0..4 {state = SipHash_perm(&state);}
+    state
+}
+
+impl SeedableRng for SPRNGRng<Ux4<u64>>
+{
+    type Seed = [u8; 32];
+
+    /// Deterministic instantiation with a 256-bit state, using four SipHash permutation rounds,
+    /// rate r = 32, t = 1 and a seed vector of size s = 3.
+    /// The public seed vector is fixed to the SipHash initialization constants,
+    /// and the seed bytes are split in r-bit blocks absorbed using `refresh`.
+    fn from_seed(seed: Self::Seed) -> Self {
+        let params = SPRNGParams::sprng_256();
+
+        let inputs: Vec<Ux4<u64>> = seed.chunks(params.r / 8)
+            .map(|chunk| {
+                let mut block = [0_u8; 8];
+                block[..chunk.len()].copy_from_slice(chunk);
+                Ux4::new([u64::from_le_bytes(block), 0, 0, 0])
+            })
+            .collect();
+
+        let seed_vec = vec![
+            Ux4::new([0x736f6d6570736575, 0, 0, 0]),
+            Ux4::new([0x646f72616e646f6d, 0, 0, 0]),
+            Ux4::new([0x6c7967656e657261, 0, 0, 0]),
+        ];
+
+        let mut sprng = SPRNG::with_seed(params, seedable_perm, seed_vec)
+            .expect("SPRNG: invalid seedable parameters.");
+        sprng.refresh(inputs).expect("SPRNG: failed to absorb the seed.");
+
+        Self::new(sprng)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn params(r: usize) -> SPRNGParams {
+        SPRNGParams::builder().n(64).r(r).s(2).build().unwrap()
+    }
+    use crate::utilities::ustates::{UxN, test::perm_320};
+
+    #[test]
+    fn seedable_is_deterministic() {
+        let mut a = SPRNGRng::<Ux4<u64>>::from_seed([7; 32]);
+        let mut b = SPRNGRng::<Ux4<u64>>::from_seed([7; 32]);
+        let mut c = SPRNGRng::<Ux4<u64>>::from_seed([8; 32]);
+        let (x, y, z) = (a.next_u64(), b.next_u64(), c.next_u64());
+        assert!(x == y);
+        assert!(x != z);
+    }
+
+    #[test]
+    fn fill_bytes_without_gaps() {
+        // With r = 4, each output byte is made of two consecutive next() outputs
+        fn perm(state: u64) -> u64 {state.rotate_left(17) ^ 0x9E3779B97F4A7C15}
+        let mut sprng = SPRNG::with_seed(params(4), perm, vec![0, 0]).unwrap();
+        sprng.refresh(vec![1, 2, 3]).unwrap();
+
+        let mut reference = sprng.clone();
+        let expected: Vec<u8> = (0..4)
+            .map(|_| {
+                let lo = reference.next().unwrap() as u8;
+                let hi = reference.next().unwrap() as u8;
+                lo | (hi << 4)
+            })
+            .collect();
+
+        let mut rng = SPRNGRng::new(sprng);
+        let mut bytes = [0_u8; 4];
+        rng.fill_bytes(&mut bytes[..1]);
+        rng.fill_bytes(&mut bytes[1..]);
+        assert!(bytes.to_vec() == expected);
+    }
+
+    #[test]
+    fn wide_state() {
+        let seed = vec![UxN::from(3), UxN::from(5)];
+        let mut a = SPRNG::with_seed(SPRNGParams::sprng_320(), perm_320, seed.clone()).unwrap();
+        let mut b = SPRNG::with_seed(SPRNGParams::sprng_320(), perm_320, seed).unwrap();
+        a.refresh(vec![UxN::from(1), UxN::from(2)]).unwrap();
+        b.refresh(vec![UxN::from(1), UxN::from(2)]).unwrap();
+
+        let (x, y) = (a.next().unwrap(), b.next().unwrap());
+        assert!(x == y);
+        assert!(x & !*a.mask() == UxN::zero());
+    }
+
+    #[test]
+    fn setup_errors() {
+        fn perm(state: u64) -> u64 {state.rotate_left(17)}
+        let result = SPRNGParams::builder().n(64).r(80).s(2).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+        let result = SPRNGParams::builder().n(64).r(8).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "s", .. })));
+
+        let built = SPRNGParams::builder().n(64).r(8).s(3).build().unwrap();
+        assert!(built == SPRNGParams::builder().n(64).r(8).t(1).s(3).build().unwrap());
+        let result = SPRNG::with_seed(built, perm, vec![1, 2]);
+        assert!(matches!(result, Err(Error::BadLength { field: "seed", expected: 3, got: 2 })));
+        let result = SPRNG::with_seed(SPRNGParams::sprng_256(), perm, vec![1, 2, 3]);
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "n", .. })));
+
+        let mut sprng = SPRNG::with_seed(params(8), perm, vec![1, 2]).unwrap();
+        assert!(matches!(sprng.refresh(vec![]), Err(Error::InvalidParameter { field: "inputs", .. })));
+    }
+
+    #[test]
+    fn width_edge_cases() {
+        fn perm(state: u64) -> u64 {state.rotate_left(17) ^ 0x9E3779B97F4A7C15}
+
+        // r = n, the whole state is output
+        let mut sprng = SPRNG::with_seed(params(64), perm, vec![1, 2]).unwrap();
+        assert!(*sprng.mask() == u64::MAX);
+        sprng.refresh(vec![u64::MAX]).unwrap();
+        sprng.next().unwrap();
+
+        // r = 1
+        let mut sprng = SPRNG::with_seed(params(1), perm, vec![1, 2]).unwrap();
+        assert!(*sprng.mask() == 1);
+        sprng.refresh(vec![1]).unwrap();
+        assert!(sprng.next().unwrap() <= 1);
+
+        let result = SPRNGParams::builder().n(64).r(0).s(2).build();
+        assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+        let wide = SPRNGParams::builder().n(320).r(320).s(2).build().unwrap();
+        let sprng = SPRNG::with_seed(wide, |x: UxN<u64, 5>| x, vec![UxN::from(1), UxN::from(2)]).unwrap();
+        assert!(*sprng.mask() == !UxN::zero());
+    }
+
+    #[test]
+    fn wipe_and_redact() {
+        let mut sprng = SPRNG::with_seed(params(8), |x: u64| x.rotate_left(17), vec![0xabcdef, 0x123456]).unwrap();
+        sprng.refresh(vec![0x99]).unwrap();
+        let debug = format!("{:?}", SPRNGRng::new(sprng.clone()));
+        assert!(debug.contains("<redacted>") && !debug.contains(&format!("{}", 0xef_u64)));
+
+        sprng.zeroize();
+        assert!(sprng.seed.is_empty());
+        assert!(sprng.state == 0);
+        assert!(matches!(sprng.refresh(vec![0x99]), Err(Error::KeyNotSet)));
+        assert!(matches!(sprng.next(), Err(Error::KeyNotSet)));
+    }
+
+    #[test]
+    fn rand_sampling() {
+        let mut rng = SPRNGRng::<Ux4<u64>>::from_seed([1; 32]);
+        let a: Ux4<u64> = rng.gen();
+        let b: Ux4<u64> = rng.gen();
+        assert!(a != b);
+    }
+}
@@ -0,0 +1,189 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing Lamport one-time signatures.
+//!
+//! Based on L. Lamport, Constructing Digital Signatures from a One Way Function (SRI CSL-98, 1979),
+//! generic over the `Digest` trait. With an n-byte Hash function, the message is hashed to 8n bits,
+//! and each bit reveals one of two secret values of n bytes.
+
+use crate::error::Error;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::prng::PRNG;
+use crate::sign::{hash, draw};
+use crate::utilities::{ct::ct_eq, zeroize::Zeroize};
+
+// Lamport structure.
+
+/// Structure implementing a Lamport one-time key pair.
+///
+/// The secret key is the sequence of secret values x\[i\]\[b\] for each message bit i and value b,
+/// the public key is the sequence of their hashes in the same order.
+pub struct Lamport<D>
+{
+    /// Underlying Hash function.
+    digest: D,
+
+    /// Output size of the Hash function in bytes.
+    n: usize,
+
+    /// Secret values, empty once the key has signed.
+    secret: Vec<u8>,
+
+    /// Public key.
+    public: Vec<u8>,
+}
+
+impl<D> Zeroize for Lamport<D>
+{
+    /// Wipes the secret key.
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+        self.secret.clear();
+    }
+}
+
+impl<D> Drop for Lamport<D>
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<D> Debug for Lamport<D>
+{
+    /// Prints the sizes and the public key, the secret key is redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lamport")
+            .field("n", &self.n)
+            .field("secret", &format_args!("<redacted>"))
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+impl<D> Lamport<D>
+where
+    D: Digest + Clone
+{
+    /// Key generation over the Hash function `digest`, the 16n^2 bytes of secret values being drawn from `prng`.
+    pub fn keygen<P>(digest: D, prng: &mut P) -> Result<Self, Error>
+    where
+        P: PRNG<Output = Vec<u8>>
+    {
+        let n = digest.output_size();
+        if n == 0 {
+            return Err(Error::invalid("digest", "output size of the Hash function must be greater than 0"));
+        }
+
+        let secret = draw(prng, 2 * 8 * n * n)?;
+        let mut public = Vec::with_capacity(secret.len());
+        for value in secret.chunks(n) {
+            public.extend(hash(&digest, &[value], n)?);
+        }
+
+        Ok(Self { digest, n, secret, public })
+    }
+
+    /// Public key, as 16n hashes of n bytes.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public
+    }
+
+    /// Sign `message`, revealing x\[i\]\[b\] for each bit b of H(message), most significant bit first.
+    /// The secret key is wiped afterwards.
+    pub fn sign(&mut self, message: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        if self.secret.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
+
+        let n = self.n;
+        let bits = message_bits(&self.digest, message.as_ref(), n)?;
+        let mut signature = Vec::with_capacity(8 * n * n);
+        for (i, bit) in bits.enumerate() {
+            let index = 2 * i + bit;
+            signature.extend(&self.secret[index * n..(index + 1) * n]);
+        }
+
+        self.zeroize();
+        Ok(signature)
+    }
+
+    /// Verify the `signature` of `message` under `public_key`, with the Hash function `digest`.
+    pub fn verify(digest: D, public_key: impl AsRef<[u8]>, message: impl AsRef<[u8]>,
+        signature: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        let (public_key, signature) = (public_key.as_ref(), signature.as_ref());
+        let n = digest.output_size();
+        if public_key.len() != 2 * 8 * n * n {
+            return Err(Error::BadLength { field: "public_key", expected: 2 * 8 * n * n, got: public_key.len() });
+        }
+        if signature.len() != 8 * n * n {
+            return Err(Error::AuthenticationFailure);
+        }
+
+        let bits = message_bits(&digest, message.as_ref(), n)?;
+        let mut valid = true;
+        for (i, (bit, value)) in bits.zip(signature.chunks(n)).enumerate() {
+            let index = 2 * i + bit;
+            valid &= ct_eq(&hash(&digest, &[value], n)?, &public_key[index * n..(index + 1) * n]);
+        }
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::AuthenticationFailure)
+        }
+    }
+}
+
+/// Bits of H(message), most significant bit of each byte first.
+fn message_bits<D>(digest: &D, message: &[u8], n: usize) -> Result<impl Iterator<Item = usize>, Error>
+where
+    D: Digest + Clone
+{
+    let digest = hash(digest, &[message], n)?;
+    Ok((0..8 * n).map(move |i| ((digest[i / 8] >> (7 - i % 8)) & 1) as usize))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::{sponge::Sponge, sha2::SHA2};
+    use crate::sign::test::{drbg, sha256, check_forgeries};
+    use crate::utilities::hex::from_hex;
+
+    #[test]
+    fn deterministic_vectors() {
+        // Digests of the public key and the signature of "abc", from an independent Python implementation
+        let mut key = Lamport::keygen(SHA2::sha256(), &mut drbg()).unwrap();
+        assert!(key.public_key().len() == 512 * 32);
+        assert!(sha256(key.public_key()) == from_hex("c841f4802a714fa9b55d7e441c350db6358cb83e6d850949fed9ebaf71959816").unwrap());
+
+        let signature = key.sign(b"abc").unwrap();
+        assert!(signature[..32] == from_hex("a20597892af4a5f37d4a442d87fbd199458afad287b9095c6e189b41ff676d4f").unwrap());
+        assert!(sha256(&signature) == from_hex("576a40c001bfeff68fcb79331552aecc24367c49d8e2c2a47e14f4d00eb7073e").unwrap());
+        Lamport::verify(SHA2::sha256(), key.public_key(), b"abc", &signature).unwrap();
+    }
+
+    #[test]
+    fn one_time_and_forgeries() {
+        let mut key = Lamport::keygen(Sponge::siphash().unwrap(), &mut drbg()).unwrap();
+        let public_key = key.public_key().to_vec();
+        let signature = key.sign(b"message").unwrap();
+        assert!(matches!(key.sign(b"message"), Err(Error::KeyNotSet)));
+        assert!(format!("{:?}", key).contains("<redacted>"));
+
+        // Each revealed value hashes to one of the two public values of its bit
+        let n = 32;
+        for (i, value) in signature.chunks(n).enumerate() {
+            let h = hash(&Sponge::siphash().unwrap(), &[value], n).unwrap();
+            assert!(h == public_key[2 * i * n..(2 * i + 1) * n] || h == public_key[(2 * i + 1) * n..(2 * i + 2) * n]);
+        }
+
+        check_forgeries(|public_key, message, signature| Lamport::verify(Sponge::siphash().unwrap(), public_key, message, signature),
+            &public_key, b"message", &signature);
+    }
+}
@@ -0,0 +1,87 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing hash-based signatures.
+//!
+//! This module groups one-time signature schemes built from a Hash function alone,
+//! generic over the `Digest` trait so that sponges over any permutation can be used.
+//! Secret keys are drawn from any PRNG of the crate producing bytes, such as a seeded Hash_DRBG,
+//! so that key generation from a seed is deterministic.
+//! A one-time key is wiped after its first signature: signing again fails with `KeyNotSet`.
+
+use crate::error::Error;
+use crate::hash::Digest;
+use crate::prng::PRNG;
+
+/// Hash the concatenation of `inputs` with a fresh copy of `digest`, truncated to `n` bytes.
+pub(crate) fn hash<D>(digest: &D, inputs: &[&[u8]], n: usize) -> Result<Vec<u8>, Error>
+where
+    D: Digest + Clone
+{
+    let mut digest = digest.clone();
+    digest.reset();
+    for input in inputs {
+        digest.update(input);
+    }
+    let mut output = digest.finalize_bytes()?;
+    output.truncate(n);
+    Ok(output)
+}
+
+/// Draw `length` bytes from the concatenated outputs of `prng`.
+pub(crate) fn draw<P>(prng: &mut P, length: usize) -> Result<Vec<u8>, Error>
+where
+    P: PRNG<Output = Vec<u8>>
+{
+    let mut output = Vec::with_capacity(length);
+    while output.len() < length {
+        let block = prng.next()?;
+        if block.is_empty() {
+            return Err(Error::invalid("prng", "PRNG output must not be empty"));
+        }
+        output.extend(block);
+    }
+    output.truncate(length);
+    Ok(output)
+}
+
+pub mod lamport;
+pub mod wots;
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::hash::sha2::SHA2;
+    use crate::prng::drbg::HashDRBG;
+
+    /// Hash_DRBG with SHA-256, instantiated with the seed 00..1f.
+    pub(crate) fn drbg() -> HashDRBG<SHA2<u32>> {
+        let mut drbg = HashDRBG::new(1000, SHA2::sha256()).unwrap();
+        drbg.instantiate((0x00..=0x1f).collect::<Vec<u8>>(), [], []).unwrap();
+        drbg
+    }
+
+    /// SHA-256 of `data`.
+    pub(crate) fn sha256(data: &[u8]) -> Vec<u8> {
+        hash(&SHA2::sha256(), &[data], 32).unwrap()
+    }
+
+    /// Check that `verify` accepts the `signature` of `message` under `public_key`,
+    /// and rejects another message, a truncated signature or public key, and a flipped bit of the signature.
+    pub(crate) fn check_forgeries<V>(verify: V, public_key: &[u8], message: &[u8], signature: &[u8])
+    where
+        V: Fn(&[u8], &[u8], &[u8]) -> Result<(), Error>
+    {
+        verify(public_key, message, signature).unwrap();
+
+        let mut other = message.to_vec();
+        other[0] ^= 1;
+        assert!(matches!(verify(public_key, &other, signature), Err(Error::AuthenticationFailure)));
+        assert!(verify(public_key, message, &signature[1..]).is_err());
+        assert!(matches!(verify(&public_key[1..], message, signature), Err(Error::BadLength { field: "public_key", .. })));
+
+        let mut forged = signature.to_vec();
+        forged[signature.len() / 2] ^= 1;
+        assert!(verify(public_key, message, &forged).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing WOTS+ one-time signatures.
+//!
+//! Based on [RFC 8391](https://www.rfc-editor.org/rfc/rfc8391), Section 3, generic over the `Digest` trait.
+//! The chaining function F and the PRF generating keys and bitmasks are H(toByte(0, n) || KEY || M)
+//! and H(toByte(3, n) || KEY || M), truncated to n bytes. The message is hashed to n bytes before signing.
+
+use crate::error::Error;
+use getset::Getters;
+use std::fmt::{self, Debug, Formatter};
+
+use crate::hash::Digest;
+use crate::prng::PRNG;
+use crate::sign::{hash, draw};
+use crate::utilities::{ct::ct_eq, zeroize::Zeroize};
+
+// WOTS+ parameters.
+
+#[derive(Getters, Clone, Copy, Debug, PartialEq, Eq)]
+/// Parameters of WOTS+, built with [`WOTSParams::builder`] or taken from a preset.
+pub struct WOTSParams
+{
+    /// Size of the hashes in bytes, at most the output size of the Hash function.
+    #[getset(get = "pub")]
+    n: usize,
+
+    /// Winternitz parameter, 4, 16 or 256.
+    #[getset(get = "pub")]
+    w: usize,
+}
+
+impl WOTSParams
+{
+    /// Start building parameters. The Winternitz parameter defaults to 16, the size `n` must be set.
+    pub fn builder() -> WOTSParamsBuilder {
+        WOTSParamsBuilder::default()
+    }
+
+    /// WOTSP-SHA2_256 from RFC 8391: n = 32 and w = 16.
+    pub fn wotsp_sha2_256() -> Self {
+        Self { n: 32, w: 16 }
+    }
+
+    /// Check the parameters.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.n == 0 {
+            return Err(Error::invalid("n", "size of the hashes must be greater than 0"));
+        }
+        if ![4, 16, 256].contains(&self.w) {
+            return Err(Error::invalid("w", "Winternitz parameter must be 4, 16 or 256"));
+        }
+        Ok(())
+    }
+
+    /// Number of bits per base-w digit, log2(w).
+    pub fn log_w(&self) -> usize {
+        self.w.trailing_zeros() as usize
+    }
+
+    /// Number of base-w digits of the message, len_1 = ceil(8n / log2(w)).
+    pub fn len_1(&self) -> usize {
+        (8 * self.n).div_ceil(self.log_w())
+    }
+
+    /// Number of base-w digits of the checksum, len_2 = floor(log2(len_1 (w - 1)) / log2(w)) + 1.
+    pub fn len_2(&self) -> usize {
+        (self.len_1() * (self.w - 1)).ilog2() as usize / self.log_w() + 1
+    }
+
+    /// Number of hash chains, len = len_1 + len_2.
+    pub fn chains(&self) -> usize {
+        self.len_1() + self.len_2()
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Builder for [`WOTSParams`].
+pub struct WOTSParamsBuilder
+{
+    n: Option<usize>,
+    w: usize,
+}
+
+impl Default for WOTSParamsBuilder
+{
+    fn default() -> Self {
+        Self { n: None, w: 16 }
+    }
+}
+
+impl WOTSParamsBuilder
+{
+    /// Set the size of the hashes in bytes.
+    pub fn n(mut self, n: usize) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Set the Winternitz parameter.
+    pub fn w(mut self, w: usize) -> Self {
+        self.w = w;
+        self
+    }
+
+    /// Check the parameters and build them.
+    pub fn build(self) -> Result<WOTSParams, Error> {
+        let params = WOTSParams {
+            n: self.n.ok_or_else(|| Error::invalid("n", "size of the hashes must be set"))?,
+            w: self.w,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+// WOTS+ structure.
+
+/// Structure implementing a WOTS+ one-time key pair.
+///
+/// The public key is the public seed followed by the ends of the len hash chains, (len + 1) n bytes in total.
+/// The signature is made of len intermediate values of the chains, len n bytes in total.
+pub struct WOTSPlus<D>
+{
+    /// Parameters.
+    params: WOTSParams,
+
+    /// Underlying Hash function.
+    digest: D,
+
+    /// Starts of the hash chains, empty once the key has signed.
+    secret: Vec<u8>,
+
+    /// Public seed and ends of the hash chains.
+    public: Vec<u8>,
+}
+
+impl<D> Zeroize for WOTSPlus<D>
+{
+    /// Wipes the secret key.
+    fn zeroize(&mut self) {
+        self.secret.zeroize();
+        self.secret.clear();
+    }
+}
+
+impl<D> Drop for WOTSPlus<D>
+{
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl<D> Debug for WOTSPlus<D>
+{
+    /// Prints the parameters and the public key, the secret key is redacted.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WOTSPlus")
+            .field("params", &self.params)
+            .field("secret", &format_args!("<redacted>"))
+            .field("public", &self.public)
+            .finish()
+    }
+}
+
+impl<D> WOTSPlus<D>
+where
+    D: Digest + Clone
+{
+    /// Key generation over the Hash function `digest`.
+    /// The public seed, then the len secret chain starts, are drawn from `prng`, n bytes each.
+    pub fn keygen<P>(params: WOTSParams, digest: D, prng: &mut P) -> Result<Self, Error>
+    where
+        P: PRNG<Output = Vec<u8>>
+    {
+        check(&params, &digest)?;
+
+        let n = params.n;
+        let mut public = draw(prng, n)?;
+        let secret = draw(prng, params.chains() * n)?;
+        for (i, start) in secret.chunks(n).enumerate() {
+            let end = chain(&params, &digest, start, 0, params.w - 1, &public[..n], i)?;
+            public.extend(end);
+        }
+
+        Ok(Self { params, digest, secret, public })
+    }
+
+    /// Public key, as the public seed followed by the len ends of the hash chains.
+    pub fn public_key(&self) -> &[u8] {
+        &self.public
+    }
+
+    /// Sign `message`, walking each chain i up to the i-th base-w digit of H(message) and its checksum.
+    /// The secret key is wiped afterwards.
+    pub fn sign(&mut self, message: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+        if self.secret.is_empty() {
+            return Err(Error::KeyNotSet);
+        }
+
+        let n = self.params.n;
+        let digits = message_digits(&self.params, &self.digest, message.as_ref())?;
+        let mut signature = Vec::with_capacity(self.params.chains() * n);
+        for (i, (start, digit)) in self.secret.chunks(n).zip(digits).enumerate() {
+            signature.extend(chain(&self.params, &self.digest, start, 0, digit, &self.public[..n], i)?);
+        }
+
+        self.zeroize();
+        Ok(signature)
+    }
+
+    /// Verify the `signature` of `message` under `public_key`, with the Hash function `digest`.
+    /// Each chain is completed from the signature, and must end on the public key.
+    pub fn verify(params: WOTSParams, digest: D, public_key: impl AsRef<[u8]>, message: impl AsRef<[u8]>,
+        signature: impl AsRef<[u8]>) -> Result<(), Error>
+    {
+        check(&params, &digest)?;
+        let (public_key, signature) = (public_key.as_ref(), signature.as_ref());
+        let (n, len) = (params.n, params.chains());
+        if public_key.len() != (len + 1) * n {
+            return Err(Error::BadLength { field: "public_key", expected: (len + 1) * n, got: public_key.len() });
+        }
+        if signature.len() != len * n {
+            return Err(Error::AuthenticationFailure);
+        }
+
+        let (seed, ends) = public_key.split_at(n);
+        let digits = message_digits(&params, &digest, message.as_ref())?;
+        let mut valid = true;
+        for (i, ((value, digit), end)) in signature.chunks(n).zip(digits).zip(ends.chunks(n)).enumerate() {
+            let computed = chain(&params, &digest, value, digit, params.w - 1 - digit, seed, i)?;
+            valid &= ct_eq(&computed, end);
+        }
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::AuthenticationFailure)
+        }
+    }
+}
+
+/// Check that the Hash function outputs at least n bytes.
+fn check<D>(params: &WOTSParams, digest: &D) -> Result<(), Error>
+where
+    D: Digest
+{
+    params.validate()?;
+    if params.n > digest.output_size() {
+        return Err(Error::invalid("n", format!("WOTS+: n must be at most the output size {} of the Hash function",
+            digest.output_size())));
+    }
+    Ok(())
+}
+
+/// Address of the hash `hash` of the chain `chain`, selecting the key (0) or the bitmask (1).
+/// The other fields of the 32-byte OTS address of RFC 8391 are zero.
+fn address(chain: usize, hash: usize, key_and_mask: u32) -> [u8; 32] {
+    let mut adrs = [0_u8; 32];
+    adrs[20..24].copy_from_slice(&(chain as u32).to_be_bytes());
+    adrs[24..28].copy_from_slice(&(hash as u32).to_be_bytes());
+    adrs[28..32].copy_from_slice(&key_and_mask.to_be_bytes());
+    adrs
+}
+
+/// Chaining function: `steps` iterations of F from position `start` of the chain `index`,
+/// each keyed and masked by the PRF of the public `seed`.
+fn chain<D>(params: &WOTSParams, digest: &D, x: &[u8], start: usize, steps: usize, seed: &[u8], index: usize)
+    -> Result<Vec<u8>, Error>
+where
+    D: Digest + Clone
+{
+    let n = params.n;
+    let mut padding = vec![0x00; n];
+    let mut x = x.to_vec();
+    for j in start..start + steps {
+        padding[n - 1] = 3;
+        let key = hash(digest, &[&padding, seed, &address(index, j, 0)], n)?;
+        let mask = hash(digest, &[&padding, seed, &address(index, j, 1)], n)?;
+        x.iter_mut().zip(&mask).for_each(|(x, m)| *x ^= m);
+        padding[n - 1] = 0;
+        x = hash(digest, &[&padding, &key, &x], n)?;
+    }
+    Ok(x)
+}
+
+/// Base-w digits of `bytes`, most significant first, `count` of them.
+fn base_w(bytes: &[u8], log_w: usize, count: usize) -> Vec<usize> {
+    let mask = (1 << log_w) - 1;
+    (0..count).map(|i| {
+        let bit = i * log_w;
+        (bytes[bit / 8] as usize >> (8 - log_w - bit % 8)) & mask
+    }).collect()
+}
+
+/// The len_1 base-w digits of H(message), followed by the len_2 base-w digits of the checksum.
+fn message_digits<D>(params: &WOTSParams, digest: &D, message: &[u8]) -> Result<Vec<usize>, Error>
+where
+    D: Digest + Clone
+{
+    let (log_w, len2) = (params.log_w(), params.len_2());
+    let mut digits = base_w(&hash(digest, &[message], params.n)?, log_w, params.len_1());
+
+    // The checksum is left-aligned in ceil(len_2 log2(w) / 8) bytes
+    let mut checksum: usize = digits.iter().map(|d| params.w - 1 - d).sum();
+    checksum <<= (8 - (len2 * log_w) % 8) % 8;
+    let bytes = (len2 * log_w).div_ceil(8);
+    digits.extend(base_w(&checksum.to_be_bytes()[size_of::<usize>() - bytes..], log_w, len2));
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hash::{sponge::Sponge, sha2::SHA2};
+    use crate::sign::test::{drbg, sha256, check_forgeries};
+    use crate::utilities::hex::from_hex;
+
+    #[test]
+    fn parameters() {
+        // Lengths of RFC 8391, Section 5.2, and of the other Winternitz parameters
+        let cases = [(32, 16, 64, 3), (32, 4, 128, 5), (32, 256, 32, 2), (16, 16, 32, 3), (64, 16, 128, 3)];
+        for (n, w, len1, len2) in cases {
+            let params = WOTSParams::builder().n(n).w(w).build().unwrap();
+            assert!(params.len_1() == len1 && params.len_2() == len2 && params.chains() == len1 + len2);
+        }
+        assert!(WOTSParams::builder().n(32).build().unwrap() == WOTSParams::wotsp_sha2_256());
+
+        assert!(matches!(WOTSParams::builder().build(), Err(Error::InvalidParameter { field: "n", .. })));
+        assert!(matches!(WOTSParams::builder().n(32).w(8).build(), Err(Error::InvalidParameter { field: "w", .. })));
+        let params = WOTSParams::builder().n(33).build().unwrap();
+        assert!(matches!(WOTSPlus::keygen(params, SHA2::sha256(), &mut drbg()),
+            Err(Error::InvalidParameter { field: "n", .. })));
+    }
+
+    #[test]
+    fn deterministic_vectors() {
+        // Digests of the public key and the signature of "abc", and checksum digits,
+        // from an independent Python implementation of RFC 8391
+        type Case = (usize, usize, &'static str, &'static str, Vec<usize>);
+        let cases: [Case; 4] = [
+            (32, 16, "38407cd6c4a8ceacbb826e71ae0776f6409159fd5ccefd76b0c0eab55cb1f7da",
+                "7ea584aae0482b0f25a2e9684b4bfd49f53e05e479df892cb81e8076ee335be6", vec![2, 1, 2]),
+            (32, 4, "bd08a09b320ab272c08de49183356db12d19e9ae972985ba263683f4c9b9c441",
+                "25180b3cc3857d5149f179e75e9bf3d930ebd83e7ec57c8b6ea4eeb3b04c96db", vec![0, 3, 0, 2, 3]),
+            (32, 256, "121d128b50dac73435ffe3d518e0aee605ca020850bf24f3a05933b91beb1f3c",
+                "e5eb7014144e307cbc1050be10507ba13be1a013998e8a2a1c83bd7c228e56a0", vec![17, 78]),
+            (16, 16, "ad1e44258f2466b316214f22eced39c13ed41ad0d6489d22f5a8b712c86f6602",
+                "9ba7ca0ffc87d491f21ba6e10643bba5948448193699de3e103421ab7e47c412", vec![0, 15, 4]),
+        ];
+
+        for (n, w, public_key, signature, checksum) in cases {
+            let params = WOTSParams::builder().n(n).w(w).build().unwrap();
+            let digits = message_digits(&params, &SHA2::sha256(), b"abc").unwrap();
+            assert!(digits[params.len_1()..] == checksum);
+
+            let mut key = WOTSPlus::keygen(params, SHA2::sha256(), &mut drbg()).unwrap();
+            assert!(sha256(key.public_key()) == from_hex(public_key).unwrap());
+            let computed = key.sign(b"abc").unwrap();
+            assert!(sha256(&computed) == from_hex(signature).unwrap());
+            WOTSPlus::verify(params, SHA2::sha256(), key.public_key(), b"abc", &computed).unwrap();
+        }
+    }
+
+    #[test]
+    fn one_time_and_forgeries() {
+        let params = WOTSParams::builder().n(32).build().unwrap();
+        let mut key = WOTSPlus::keygen(params, Sponge::siphash().unwrap(), &mut drbg()).unwrap();
+        let public_key = key.public_key().to_vec();
+        let signature = key.sign(b"message").unwrap();
+        assert!(matches!(key.sign(b"message"), Err(Error::KeyNotSet)));
+        assert!(format!("{:?}", key).contains("<redacted>"));
+
+        let verify = |public_key: &[u8], message: &[u8], signature: &[u8]|
+            WOTSPlus::verify(params, Sponge::siphash().unwrap(), public_key, message, signature);
+        check_forgeries(verify, &public_key, b"message", &signature);
+
+        // Advancing a chain by one step stays on the chain, but does not give a valid signature:
+        // the message digit would increase without the checksum decreasing
+        let (n, siphash) = (params.n, Sponge::siphash().unwrap());
+        let digits = message_digits(&params, &siphash, b"message").unwrap();
+        let i = digits.iter().position(|&d| d < params.w - 2).unwrap();
+        let (seed, value) = (&public_key[..n], &signature[i * n..(i + 1) * n]);
+        let next = chain(&params, &siphash, value, digits[i], 1, seed, i).unwrap();
+        let end = chain(&params, &siphash, &next, digits[i] + 1, params.w - 2 - digits[i], seed, i).unwrap();
+        assert!(end == public_key[(i + 1) * n..(i + 2) * n]);
+
+        let mut forged = signature.clone();
+        forged[i * n..(i + 1) * n].copy_from_slice(&next);
+        assert!(matches!(verify(&public_key, b"message", &forged), Err(Error::AuthenticationFailure)));
+    }
+}
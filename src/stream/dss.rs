@@ -4,24 +4,120 @@
 //!
 //! Based on [TODO].
 
-use std::io::Error;
+use crate::error::Error;
 use getset::Getters;
 use std::{ops::{BitXor, BitAnd, BitOr, Not, Add, Sub, Shl, Shr}, cmp::PartialEq, convert::From};
-use std::fmt::{LowerHex, Debug};
+use std::fmt::{self, LowerHex, Debug, Formatter};
 
-use crate::utilities::{ToLeBytes, bitops::bits_to_bytes};
+use crate::utilities::{ToLeBytes, StateWidth, bitbuf::{BitBuf, BitOrder}, bitops::{low_mask, check_width}};
+use crate::utilities::{ct::ConstantTime, zeroize::Zeroize};
+
+// DSS parameters.
+
+#[derive(Getters, Clone, Copy, Debug, PartialEq, Eq)]
+/// Parameters of DSS, built with [`DSSParams::builder`] or taken from a preset.
+pub struct DSSParams
+{
+    /// Size of the state in bits.
+    #[getset(get = "pub")]
+    b: usize,
+
+    /// Size of the outer part (b = c + r).
+    #[getset(get = "pub")]
+    r: usize,
+
+    /// Size of the key (k < b).
+    #[getset(get = "pub")]
+    k: usize,
+}
+
+impl DSSParams
+{
+  /// Start building parameters, all fields must be set.
+  pub fn builder() -> DSSParamsBuilder {
+    DSSParamsBuilder::default()
+  }
+
+  /// 256-bit state with r = 32 and k = 32, as in the `dss_256` demo.
+  pub fn dss_256() -> Self {
+    Self { b: 256, r: 32, k: 32 }
+  }
+
+  /// 320-bit state with r = 64 and k = 128, for Ascon-sized permutations.
+  pub fn dss_320() -> Self {
+    Self { b: 320, r: 64, k: 128 }
+  }
+
+  /// Check the relations between the parameters.
+  pub fn validate(&self) -> Result<(), Error> {
+    if self.r == 0 {
+      return Err(Error::invalid("r", "rate r must be greater than 0"));
+    }
+    if self.k == 0 {
+      return Err(Error::invalid("k", "key size k must be greater than 0"));
+    }
+    if self.b <= self.k {
+      return Err(Error::invalid("k", "state size b must be greater than key size k"));
+    }
+    if self.b <= self.r {
+      return Err(Error::invalid("r", "state size b must be greater than rate r"));
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+/// Builder for [`DSSParams`].
+pub struct DSSParamsBuilder
+{
+    b: Option<usize>,
+    r: Option<usize>,
+    k: Option<usize>,
+}
+
+impl DSSParamsBuilder
+{
+  /// Set the size of the state in bits.
+  pub fn b(mut self, b: usize) -> Self {
+    self.b = Some(b);
+    self
+  }
+
+  /// Set the rate.
+  pub fn r(mut self, r: usize) -> Self {
+    self.r = Some(r);
+    self
+  }
+
+  /// Set the size of the key.
+  pub fn k(mut self, k: usize) -> Self {
+    self.k = Some(k);
+    self
+  }
+
+  /// Check the parameters and build them.
+  pub fn build(self) -> Result<DSSParams, Error> {
+    let params = DSSParams {
+      b: self.b.ok_or_else(|| Error::invalid("b", "state size b must be set"))?,
+      r: self.r.ok_or_else(|| Error::invalid("r", "rate r must be set"))?,
+      k: self.k.ok_or_else(|| Error::invalid("k", "key size k must be set"))?,
+    };
+    params.validate()?;
+    Ok(params)
+  }
+}
 
 // DSS structure.
 
 #[allow(dead_code)]
-#[derive(Getters, Clone, Debug)]
+#[derive(Getters, Clone)]
 /// Structure implementing [TODO].
 ///
 /// Note that the state is reversed for easier use of the outputs.
 /// The outer part is stored in the lower bits.
 pub struct DSS<U>
 where
-    U: Clone,
+    U: Clone + Zeroize
 {
     /// Size of the state in bits.
     #[getset(get = "pub")]
@@ -61,26 +157,64 @@ where
     state_down: Option<U>,
 }
 
+impl<U> Zeroize for DSS<U>
+where
+    U: Clone + Zeroize,
+{
+  /// Wipes the key and the inner states.
+  /// The instance must be rekeyed before being used again.
+  fn zeroize(&mut self) {
+    self.key.zeroize();
+    self.state_up.zeroize();
+    self.state_down.zeroize();
+  }
+}
+
+impl<U> Drop for DSS<U>
+where
+    U: Clone + Zeroize,
+{
+  fn drop(&mut self) {
+    self.zeroize();
+  }
+}
+
+impl<U> Debug for DSS<U>
+where
+    U: Clone + Zeroize,
+{
+  /// Prints the parameters, with the key and the inner states redacted.
+  fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DSS")
+      .field("b", &self.b)
+      .field("r", &self.r)
+      .field("c", &self.c)
+      .field("k", &self.k)
+      .field("key", &format_args!("<redacted>"))
+      .field("state_up", &format_args!("<redacted>"))
+      .field("state_down", &format_args!("<redacted>"))
+      .finish()
+  }
+}
+
 impl<U> DSS<U>
 where
-    U: Copy + From<u8> + Not<Output = U> + Shl<usize, Output = U> + Shr<usize, Output = U> + Add<Output = U>
-    + Sub<Output = U> + BitAnd<Output = U> + BitOr<Output = U> + BitXor<Output = U> + Shr<usize, Output = U>
-    + LowerHex + Debug + ToLeBytes + PartialEq,
+    U: Copy + Zeroize + StateWidth + From<u8> + Not<Output = U> + Shl<usize, Output = U> + Shr<usize, Output = U>
+    + Add<Output = U> + Sub<Output = U> + BitAnd<Output = U> + BitOr<Output = U> + BitXor<Output = U> + Shr<usize, Output = U>
+    + LowerHex + Debug + ToLeBytes + PartialEq + ConstantTime,
 {
   /// Creates a new DSS instance.
-  pub fn new(params: Vec<usize>, perm: fn(U) -> U) -> Result<Self, Error> {
-    let (b, r, k) = (params[0], params[1], params[2]);
-    assert!(b > k, "Invalid parameters: State size b must be greater than key size k");
-    assert!(b > r, "Invalid parameters: State size b must be greater than rate r");
+  pub fn new(params: DSSParams, perm: fn(U) -> U) -> Result<Self, Error> {
+    params.validate()?;
+    let DSSParams { b, r, k } = params;
+    check_width::<U>("b", b)?;
     let c = b - r; // Calculate the capacity size
 
     // Create the key mask (lower k bits)
-    let mut kmask: U = 1_u8.into();
-    kmask = (kmask << k) - 1_u8.into();
+    let kmask: U = low_mask(k);
 
     // Create the outer part mask (lower r bits)
-    let mut rmask: U = 1_u8.into();
-    rmask = (rmask << r) - 1_u8.into();
+    let rmask: U = low_mask(r);
 
     //Create the domain mask (highest bit)
     let mut dmask: U = 1_u8.into();
@@ -105,7 +239,9 @@ where
   ///
   /// This function reinitializes the state of the DSS instance using a new key.
   pub fn rekey(&mut self, key: U) -> Result<(), Error> {
-    assert!(key != U::from(0_u8), "Key must be non-zero");
+    if key.ct_is_zero() {
+      return Err(Error::invalid("key", "key must be non-zero"));
+    }
 
     self.key = Some(key);
     self.state_up = None;
@@ -117,9 +253,11 @@ where
   ///
   /// This function initializes the state of the DSS instance using a new nonce.
   pub fn init(&mut self, nonce: U) -> Result<(), Error> {
-    assert!(nonce != U::from(0_u8), "Nonce must be non-zero");
+    if nonce.ct_is_zero() {
+      return Err(Error::invalid("nonce", "nonce must be non-zero"));
+    }
     if self.key.is_none() {
-      return Err(Error::new(std::io::ErrorKind::Other, "DSS key is not set"));
+      return Err(Error::KeyNotSet);
     }
 
     // Initialize the state with the key
@@ -144,9 +282,10 @@ where
   /// Squeeze the DSS state.
   ///
   /// This function squeezes the DSS instance to produce a stream block of size `r`.
+  #[allow(clippy::should_implement_trait)]
   pub fn next(&mut self) -> Result<Vec<u8>, Error> {
     if self.state_up.is_none() {
-      return Err(Error::new(std::io::ErrorKind::Other, "DSS state is not initialized"));
+      return Err(Error::StateNotInitialized);
     }
 
     // Permute the states
@@ -157,7 +296,7 @@ where
     let sub_key = state_up & self.rmask;
 
     // Extract the outer part (rate) from state_down (S_i)
-    let r_bits = (self.r + 7) / 8;
+    let r_bits = self.r.div_ceil(8);
     let output = state_down & self.rmask;
 
     // Construct the new states
@@ -178,34 +317,18 @@ where
   /// The superfluous bits are discarded.
   fn next_p_bytes(&mut self, p: usize) -> Result<Vec<u8>, Error> {
     if self.state_up.is_none() {
-      return Err(Error::new(std::io::ErrorKind::Other, "DSS state is not initialized"));
+      return Err(Error::StateNotInitialized);
     }
 
-    let mut keystream_bits: Vec<u8> = Vec::new();
-    let p_bits = p*8;
-
-    // Collect r bits of successive next() outputs until we have p bits
-    let mut collected_bits = 0;
-    while keystream_bits.len() < p_bits {
-      let mut output = self.next()?;
-      let mut output_index = 0;
-
-      for byte in output.drain(..) {
-        for i in 0..8 {
-          if output_index < self.r && collected_bits < p_bits { // avoid padding from r & superfluous bits\
-            // Collect the i-th bit of the byte
-            // Note: The bits are collected in LSB-first order
-            keystream_bits.push((byte >> i) & 1_u8);
-            collected_bits += 1;
-            output_index += 1;
-          } else {
-            break;
-          }
-        }
-      }
+    // Collect r bits of successive next() outputs until we have p bytes
+    // Note: The bits are collected in LSB-first order
+    let mut keystream = BitBuf::new(BitOrder::LsbFirst);
+    while keystream.len() < p*8 {
+      let output = self.next()?;
+      keystream.append_bytes(&output, self.r);
     }
 
-    Ok(bits_to_bytes(&keystream_bits))
+    Ok(keystream.take_bytes(p))
   }
 
   /// Encrypt a plaintext using the DSS instance.
@@ -234,4 +357,64 @@ where
 
     Ok(ciphertext)
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::utilities::ustates::{Ux4, UxN, test::perm_320 as perm};
+  use crate::stream::test::check_encrypt_320;
+
+  #[test]
+  fn encrypt_320() {
+    let mut cipher = DSS::<UxN<u64, 5>>::new(DSSParams::dss_320(), perm).unwrap();
+    check_encrypt_320(|key, nonce, data| cipher.encrypt(key, nonce, data).unwrap());
+  }
+
+  #[test]
+  fn width_edge_cases() {
+    let result = DSSParams::builder().b(320).r(320).k(128).build();
+    assert!(matches!(result, Err(Error::InvalidParameter { field: "r", .. })));
+    let params = DSSParams::builder().b(512).r(32).k(32).build().unwrap();
+    let result = DSS::<Ux4<u64>>::new(params, |x| x);
+    assert!(matches!(result, Err(Error::InvalidParameter { field: "b", .. })));
+
+    // The domain bit is the top bit of the state, outside the rate even for r = b - 1,
+    // and it is the only difference between the two halves of the state
+    for r in [1, 319] {
+      let params = DSSParams::builder().b(320).r(r).k(128).build().unwrap();
+      let mut cipher = DSS::<UxN<u64, 5>>::new(params, perm).unwrap();
+      assert!(cipher.dmask == UxN::new([0, 0, 0, 0, 1 << 63]));
+      assert!(cipher.dmask & cipher.rmask == UxN::zero());
+
+      cipher.rekey(UxN::from(7)).unwrap();
+      cipher.init(UxN::from(9)).unwrap();
+      for _ in 0..2 {
+        assert!(cipher.state_up.unwrap() ^ cipher.state_down.unwrap() == cipher.dmask);
+        cipher.next().unwrap();
+      }
+      check_encrypt_320(|key, nonce, data| cipher.encrypt(key, nonce, data).unwrap());
+    }
+  }
+
+  #[test]
+  fn zeroize_wipes_secrets() {
+    // Drop only calls zeroize, which is checked here on a live instance
+    let mut cipher = DSS::<UxN<u64, 5>>::new(DSSParams::dss_320(), perm).unwrap();
+    cipher.rekey(UxN::new([0x5ec7e7, 0, 0, 0, 0])).unwrap();
+    cipher.init(UxN::new([1, 0, 0, 0, 0])).unwrap();
+    assert!(cipher.key.is_some() && cipher.state_up.is_some());
+
+    cipher.zeroize();
+    assert!(cipher.key.is_none() && cipher.state_up.is_none() && cipher.state_down.is_none());
+  }
+
+  #[test]
+  fn debug_is_redacted() {
+    let mut cipher = DSS::<UxN<u64, 5>>::new(DSSParams::dss_320(), perm).unwrap();
+    cipher.rekey(UxN::new([0x5ec7e7, 0, 0, 0, 0])).unwrap();
+    let debug = format!("{:?}", cipher);
+    assert!(debug.contains("<redacted>"));
+    assert!(!debug.contains(&format!("{}", 0x5ec7e7)) && !debug.contains("5ec7e7"));
+  }
+}
@@ -6,4 +6,25 @@
 //! These are accessible through the Stream trait, which implements TODO.
 
 pub mod asakey;
-pub mod dss;
\ No newline at end of file
+pub mod dss;
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::utilities::ustates::UxN;
+
+    /// Encrypt a fixed message under a fixed 320-bit key and nonce with `encrypt`,
+    /// and check that encrypting the ciphertext again gives back the message.
+    pub(crate) fn check_encrypt_320<F>(mut encrypt: F)
+    where
+        F: FnMut(UxN<u64, 5>, UxN<u64, 5>, &[u8]) -> Vec<u8>
+    {
+        let key = UxN::new([0x0123456789abcdef, 0xfedcba9876543210, 0, 0, 0]);
+        let nonce = UxN::new([0x1111111111111111, 0x2222222222222222, 0, 0, 0]);
+
+        let plaintext = b"A 320-bit state, as used by Ascon.";
+        let ciphertext = encrypt(key, nonce, plaintext);
+        assert!(ciphertext.len() == plaintext.len());
+        assert!(ciphertext != plaintext.to_vec());
+        assert!(encrypt(key, nonce, &ciphertext) == plaintext.to_vec());
+    }
+}
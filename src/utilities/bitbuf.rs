@@ -0,0 +1,259 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing packed bit buffers.
+//!
+//! Sponge constructions output `r` bits per call, where `r` is not necessarily a multiple of 8.
+//! `BitBuf` concatenates such outputs without gaps, eight bits per byte,
+//! and gives them back as bytes, bit slices or unsigned values.
+
+use std::ops::{BitOr, Range, Shl};
+
+use crate::utilities::{ToLeBytes, zeroize::Zeroize};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+/// Order of the bits within each byte of a `BitBuf`.
+pub enum BitOrder {
+    /// The first bit of the stream is the least significant bit of the first byte.
+    #[default]
+    LsbFirst,
+
+    /// The first bit of the stream is the most significant bit of the first byte.
+    MsbFirst,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// Packed, growable sequence of bits.
+///
+/// Values are appended least significant bit first in `LsbFirst` order,
+/// and most significant bit first in `MsbFirst` order,
+/// so that appending whole bytes gives back the same bytes in both orders.
+/// The unused bits of the last byte are always zero.
+pub struct BitBuf {
+    /// Packed bits.
+    bytes: Vec<u8>,
+
+    /// Number of bits.
+    len: usize,
+
+    /// Bit order within bytes.
+    order: BitOrder,
+}
+
+impl BitBuf {
+    /// Return a new empty buffer.
+    pub fn new(order: BitOrder) -> Self {
+        Self { bytes: Vec::new(), len: 0, order }
+    }
+
+    /// Return a buffer holding all the bits of `bytes`.
+    pub fn from_bytes(bytes: &[u8], order: BitOrder) -> Self {
+        Self { bytes: bytes.to_vec(), len: bytes.len() * 8, order }
+    }
+
+    /// Bit order of the buffer.
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    /// Number of bits in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer holds no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Position of the `i`-th bit within its byte.
+    fn shift(&self, i: usize) -> usize {
+        match self.order {
+            BitOrder::LsbFirst => i % 8,
+            BitOrder::MsbFirst => 7 - i % 8,
+        }
+    }
+
+    /// Value of the `i`-th bit, if any.
+    pub fn get(&self, i: usize) -> Option<bool> {
+        (i < self.len).then(|| (self.bytes[i / 8] >> self.shift(i)) & 1 == 1)
+    }
+
+    /// Append one bit.
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        let shift = self.shift(self.len);
+        *self.bytes.last_mut().unwrap() |= (bit as u8) << shift;
+        self.len += 1;
+    }
+
+    /// Append the `n` low-order bits of the little-endian value held in `bytes`.
+    pub fn append_bytes(&mut self, bytes: &[u8], n: usize) {
+        assert!(n <= bytes.len() * 8, "BitBuf: cannot append {} bits from {} bytes.", n, bytes.len());
+
+        match self.order {
+            BitOrder::LsbFirst => {
+                for (i, &byte) in bytes.iter().enumerate().take(n.div_ceil(8)) {
+                    let k = (n - 8 * i).min(8);
+                    let byte = if k < 8 {byte & ((1 << k) - 1)} else {byte};
+                    let offset = self.len % 8;
+                    if offset == 0 {
+                        self.bytes.push(byte);
+                    } else {
+                        *self.bytes.last_mut().unwrap() |= byte << offset;
+                        if offset + k > 8 {
+                            self.bytes.push(byte >> (8 - offset));
+                        }
+                    }
+                    self.len += k;
+                }
+            },
+            BitOrder::MsbFirst => {
+                for i in (0..n).rev() {
+                    self.push((bytes[i / 8] >> (i % 8)) & 1 == 1);
+                }
+            },
+        }
+    }
+
+    /// Append the `n` low-order bits of `value`.
+    pub fn append<T>(&mut self, value: &T, n: usize)
+    where
+        T: ToLeBytes
+    {
+        self.append_bytes(&value.to_le_bytes(), n);
+    }
+
+    /// Shorten the buffer to its first `n` bits.
+    pub fn truncate(&mut self, n: usize) {
+        if n >= self.len {
+            return;
+        }
+        self.bytes.truncate(n.div_ceil(8));
+        self.len = n;
+        for i in n..self.bytes.len() * 8 {
+            let shift = self.shift(i);
+            self.bytes[i / 8] &= !(1 << shift);
+        }
+    }
+
+    /// Copy of the bits in `range`, with the same bit order.
+    pub fn slice(&self, range: Range<usize>) -> BitBuf {
+        assert!(range.end <= self.len, "BitBuf: range end {} out of bounds ({} bits).", range.end, self.len);
+        let mut slice = BitBuf::new(self.order);
+        if self.order == BitOrder::LsbFirst && range.start.is_multiple_of(8) {
+            slice.bytes = self.bytes[range.start / 8..range.end.div_ceil(8)].to_vec();
+            slice.len = self.len.min(range.end.div_ceil(8) * 8) - range.start;
+            slice.truncate(range.len());
+        } else {
+            for i in range {
+                slice.push(self.get(i).unwrap());
+            }
+        }
+        slice
+    }
+
+    /// Split the buffer into slices of `n` bits, the last one possibly shorter.
+    pub fn chunks(&self, n: usize) -> impl Iterator<Item = BitBuf> + '_ {
+        assert!(n > 0, "BitBuf: chunk size must be greater than 0.");
+        (0..self.len).step_by(n).map(move |start| self.slice(start..(start + n).min(self.len)))
+    }
+
+    /// Packed bytes, the last one padded with zeros.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.bytes.clone()
+    }
+
+    /// Remove the first `p` bytes (8p bits) of the buffer and return them.
+    pub fn take_bytes(&mut self, p: usize) -> Vec<u8> {
+        assert!(p * 8 <= self.len, "BitBuf: cannot take {} bytes from {} bits.", p, self.len);
+        self.len -= p * 8;
+        self.bytes.drain(..p).collect()
+    }
+
+    /// Bits as a vector of 0s and 1s, as used by the statistical tests.
+    pub fn to_bits(&self) -> Vec<u8> {
+        (0..self.len).map(|i| self.get(i).unwrap() as u8).collect()
+    }
+
+    /// Unsigned value of the buffer: the first bit is the least significant in `LsbFirst` order,
+    /// and the most significant in `MsbFirst` order.
+    /// The buffer must not be longer than the bit width of `U`.
+    pub fn to_uint<U>(&self) -> U
+    where
+        U: Copy + From<u8> + Shl<usize, Output = U> + BitOr<Output = U>
+    {
+        match self.order {
+            BitOrder::LsbFirst => self.bytes.iter().enumerate()
+                .fold(0_u8.into(), |acc: U, (i, &byte)| acc | (U::from(byte) << (8 * i))),
+            BitOrder::MsbFirst => (0..self.len)
+                .fold(0_u8.into(), |acc: U, i| (acc << 1) | U::from(self.get(i).unwrap() as u8)),
+        }
+    }
+}
+
+impl Zeroize for BitBuf {
+    fn zeroize(&mut self) {
+        self.bytes.zeroize();
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn append_and_read() {
+        // Three 5-bit values packed without gaps
+        let mut lsb = BitBuf::new(BitOrder::LsbFirst);
+        let mut msb = BitBuf::new(BitOrder::MsbFirst);
+        for value in [0b10110_u8, 0b00111, 0b11001] {
+            lsb.append(&value, 5);
+            msb.append(&value, 5);
+        }
+        assert!(lsb.len() == 15 && msb.len() == 15);
+        assert!(lsb.to_bytes() == vec![0b1111_0110, 0b0110_0100]);
+        assert!(msb.to_bytes() == vec![0b1011_0001, 0b1111_0010]);
+        assert!(lsb.to_bits() == vec![0,1,1,0,1, 1,1,1,0,0, 1,0,0,1,1]);
+        assert!(msb.to_bits() == vec![1,0,1,1,0, 0,0,1,1,1, 1,1,0,0,1]);
+    }
+
+    #[test]
+    fn whole_bytes_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for order in [BitOrder::LsbFirst, BitOrder::MsbFirst] {
+            let mut buffer = BitBuf::new(order);
+            for byte in bytes.iter() {
+                buffer.append(byte, 8);
+            }
+            assert!(buffer == BitBuf::from_bytes(&bytes, order));
+            assert!(buffer.take_bytes(10) == bytes[..10]);
+            assert!(buffer.len() == 246 * 8);
+        }
+    }
+
+    #[test]
+    fn slices_and_values() {
+        let buffer = BitBuf::from_bytes(&0x0123456789abcdef_u64.to_le_bytes(), BitOrder::LsbFirst);
+        assert!(buffer.slice(0..64).to_uint::<u64>() == 0x0123456789abcdef);
+        assert!(buffer.slice(8..24).to_uint::<u64>() == 0xabcd);
+        assert!(buffer.slice(4..12).to_uint::<u64>() == 0xde);
+        assert!(buffer.chunks(12).map(|chunk| chunk.len()).collect::<Vec<_>>() == vec![12, 12, 12, 12, 12, 4]);
+        assert!(buffer.chunks(12).last().unwrap().to_uint::<u64>() == 0x0);
+
+        let msb = BitBuf::from_bytes(&[0b1010_0000], BitOrder::MsbFirst);
+        assert!(msb.slice(0..3).to_uint::<u8>() == 0b101);
+    }
+
+    #[test]
+    fn truncate_clears_unused_bits() {
+        let mut buffer = BitBuf::from_bytes(&[0xff, 0xff], BitOrder::LsbFirst);
+        buffer.truncate(11);
+        assert!(buffer.to_bytes() == vec![0xff, 0x07]);
+        buffer.push(true);
+        assert!(buffer.to_bytes() == vec![0xff, 0x0f]);
+    }
+}
@@ -3,27 +3,75 @@
 
 //! Module implementing additional bitwise operations.
 
-use std::mem::size_of;
-use std::ops::{BitOr, Shl, Shr, Sub};
+use std::ops::{Not, Shl, Sub};
+use std::convert::From;
 
-use crate::utilities::ToLeBytes;
+use crate::error::Error;
 
-/// Unsigned integer rotation function
-pub fn urot<U>(value: U, shift: usize) -> U
-    where U: Copy + BitOr<Output = U> + Shl<usize, Output = U> +
-        Shr<usize, Output = U> + Sub<Output = U> + Sized
+use crate::utilities::{ToLeBytes, FromLeBytes, ToBeBytes, FromBeBytes, StateWidth};
+
+/// Trait for bitwise rotations of unsigned values.
+///
+/// Unlike the primitive `rotate_left`, the shift is a `usize` taken modulo the bit width,
+/// so that any shift is valid, including 0 and shifts larger than the width.
+pub trait Rotate: Sized {
+    /// Rotate left by `n` bits, modulo the bit width.
+    fn rotl(self, n: usize) -> Self;
+
+    /// Rotate right by `n` bits, modulo the bit width.
+    fn rotr(self, n: usize) -> Self;
+}
+
+macro_rules! impl_rotate {
+    ($($t:ty),*) => {$(
+        impl Rotate for $t {
+            fn rotl(self, n: usize) -> Self {
+                <$t>::rotate_left(self, (n % <$t>::BITS as usize) as u32)
+            }
+
+            fn rotr(self, n: usize) -> Self {
+                <$t>::rotate_right(self, (n % <$t>::BITS as usize) as u32)
+            }
+        }
+    )*};
+}
+
+impl_rotate!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_state_width {
+    ($($t:ty),*) => {$(
+        impl StateWidth for $t {
+            const BITS: usize = <$t>::BITS as usize;
+        }
+    )*};
+}
+
+impl_state_width!(u8, u16, u32, u64, u128, usize);
+
+/// Mask of the `n` lower bits of `U`, also valid for `n` greater than or equal to the width of `U`.
+pub fn low_mask<U>(n: usize) -> U
+    where U: StateWidth + From<u8> + Not<Output = U> + Shl<usize, Output = U> + Sub<Output = U>
 {
-    let size: usize = size_of::<U>()*8;
-    (value << shift) | (value >> (size - shift))
+    if n >= U::BITS {
+        !U::from(0)
+    } else {
+        (U::from(1) << n) - U::from(1)
+    }
 }
 
-/// Converts a slice of bits (0s and 1s) into a vector of bytes.
-pub fn bits_to_bytes(bits: &[u8]) -> Vec<u8> {
-    bits.chunks(8).map(|chunk| {
-        chunk.iter().enumerate().fold(0u8, |acc, (i, &bit)| {
-            acc | (bit << i)  // LSB-first
-        })
-    }).collect()
+/// Check that the size parameter `field` is the bit width of the state type `U`.
+pub fn check_width<U: StateWidth>(field: &'static str, value: usize) -> Result<(), Error> {
+    if value != U::BITS {
+        return Err(Error::invalid(field, format!(
+            "state size {} = {} does not match the bit width of the state type ({})", field, value, U::BITS
+        )));
+    }
+    Ok(())
+}
+
+/// Unsigned integer rotation function, any shift is taken modulo the bit width.
+pub fn urot<U: Rotate>(value: U, shift: usize) -> U {
+    value.rotl(shift)
 }
 
 // ToLeBytes trait for converting to little-endian byte representation.
@@ -74,3 +122,108 @@ impl ToLeBytes for usize {
         usize::to_le_bytes(*self).to_vec()
     }
 }
+
+/// Byte strings, such as Hash outputs, are their own byte representation.
+impl ToLeBytes for Vec<u8> {
+    fn to_le_bytes(&self) -> Vec<u8> {
+        self.clone()
+    }
+}
+
+// FromLeBytes, ToBeBytes and FromBeBytes traits for the other byte representations.
+
+/// Check that `bytes` has the size of the type `name`, and convert it to an array.
+fn sized_bytes<const S: usize>(bytes: &[u8], name: &'static str) -> Result<[u8; S], Error> {
+    bytes.try_into().map_err(|_| Error::BadLength { field: name, expected: S, got: bytes.len() })
+}
+
+macro_rules! impl_byte_conversions {
+    ($($t:ty),*) => {$(
+        impl FromLeBytes for $t {
+            fn from_le_bytes(bytes: &[u8]) -> Result<Self, Error> {
+                Ok(<$t>::from_le_bytes(sized_bytes(bytes, stringify!($t))?))
+            }
+        }
+
+        impl ToBeBytes for $t {
+            fn to_be_bytes(&self) -> Vec<u8> {
+                <$t>::to_be_bytes(*self).to_vec()
+            }
+        }
+
+        impl FromBeBytes for $t {
+            fn from_be_bytes(bytes: &[u8]) -> Result<Self, Error> {
+                Ok(<$t>::from_be_bytes(sized_bytes(bytes, stringify!($t))?))
+            }
+        }
+    )*};
+}
+
+impl_byte_conversions!(u8, u16, u32, u64, u128, usize);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn byte_round_trips() {
+        let x: u64 = 0x0123456789abcdef;
+        assert!(<u64 as FromLeBytes>::from_le_bytes(&ToLeBytes::to_le_bytes(&x)).unwrap() == x);
+        assert!(<u64 as FromBeBytes>::from_be_bytes(&ToBeBytes::to_be_bytes(&x)).unwrap() == x);
+        assert!(ToBeBytes::to_be_bytes(&x)[0] == 0x01);
+
+        let y: u128 = u128::MAX - 5;
+        assert!(<u128 as FromLeBytes>::from_le_bytes(&ToLeBytes::to_le_bytes(&y)).unwrap() == y);
+        assert!(<u8 as FromLeBytes>::from_le_bytes(&[0x7f]).unwrap() == 0x7f);
+        assert!(<usize as FromBeBytes>::from_be_bytes(&ToBeBytes::to_be_bytes(&42_usize)).unwrap() == 42);
+    }
+
+    #[test]
+    fn rotate_exhaustive() {
+        for x in 0..=u8::MAX {
+            for n in 0..64 {
+                let expected = ((x as u16) << (n % 8) | (x as u16) >> (8 - n % 8)) as u8;
+                assert!(x.rotl(n) == expected);
+                assert!(x.rotr(n).rotl(n) == x);
+                assert!(x.rotr(n) == x.rotl(8 - n % 8));
+            }
+        }
+
+        for x in 0..=u16::MAX {
+            for n in [0, 1, 7, 8, 15, 16, 17, 31, 32, 100] {
+                let expected = ((x as u32) << (n % 16) | (x as u32) >> (16 - n % 16)) as u16;
+                assert!(urot(x, n) == expected);
+                assert!(x.rotr(n).rotl(n) == x);
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_full_range() {
+        assert!(urot(0x80000001_u32, 0) == 0x80000001);
+        assert!(urot(0x80000001_u32, 32) == 0x80000001);
+        assert!(urot(0x80000001_u32, 33) == 0x00000003);
+        assert!(u64::MAX.rotr(usize::MAX) == u64::MAX);
+        assert!(1_u128.rotr(1) == 1 << 127);
+    }
+
+    #[test]
+    fn masks_and_widths() {
+        const { assert!(<u8 as StateWidth>::BITS == 8 && <u128 as StateWidth>::BITS == 128) };
+        assert!(low_mask::<u64>(0) == 0);
+        assert!(low_mask::<u64>(1) == 1);
+        assert!(low_mask::<u64>(63) == u64::MAX >> 1);
+        assert!(low_mask::<u64>(64) == u64::MAX);
+        assert!(low_mask::<u8>(200) == u8::MAX);
+
+        assert!(check_width::<u32>("n", 32).is_ok());
+        assert!(matches!(check_width::<u32>("n", 64), Err(Error::InvalidParameter { field: "n", .. })));
+    }
+
+    #[test]
+    fn byte_length_errors() {
+        assert!(<u32 as FromLeBytes>::from_le_bytes(&[1, 2, 3]).is_err());
+        assert!(<u16 as FromBeBytes>::from_be_bytes(&[1, 2, 3]).is_err());
+        assert!(<u8 as FromLeBytes>::from_le_bytes(&[]).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing constant-time operations.
+//!
+//! Comparisons and selections on secret values (keys, nonces, tags) must not branch
+//! on the data, otherwise their running time leaks information about it.
+//! The functions of this module accumulate differences with bitwise operations,
+//! and only convert the result to a `bool` at the end, behind `std::hint::black_box`
+//! so that the compiler does not reintroduce early exits.
+
+use std::hint::black_box;
+use std::num::Wrapping;
+use std::ops::{BitAnd, BitOr, BitXor, Not, Sub};
+
+use crate::utilities::ustates::UxN;
+
+/// Trait for constant-time comparison and selection.
+pub trait ConstantTime: Sized {
+    /// Equality test, without early exit.
+    fn ct_eq(&self, other: &Self) -> bool;
+
+    /// Test for zero, without early exit.
+    fn ct_is_zero(&self) -> bool;
+
+    /// Returns `b` if `choice` is true and `a` otherwise, without branching on `choice`.
+    fn ct_select(a: &Self, b: &Self, choice: bool) -> Self;
+}
+
+/// Returns 1 if `x` is non-zero, 0 otherwise.
+fn nonzero_bit(x: u8) -> u8 {
+    let x = black_box(x);
+    (x | x.wrapping_neg()) >> 7
+}
+
+macro_rules! impl_constant_time {
+    ($($t:ty),*) => {$(
+        impl ConstantTime for $t {
+            fn ct_eq(&self, other: &Self) -> bool {
+                (*self ^ *other).ct_is_zero()
+            }
+
+            fn ct_is_zero(&self) -> bool {
+                let x = black_box(*self);
+                ((x | x.wrapping_neg()) >> (<$t>::BITS - 1)) == 0
+            }
+
+            fn ct_select(a: &Self, b: &Self, choice: bool) -> Self {
+                let mask = (black_box(choice) as $t).wrapping_neg();
+                (*a & !mask) | (*b & mask)
+            }
+        }
+    )*};
+}
+
+impl_constant_time!(u8, u16, u32, u64, u128, usize);
+
+impl<U, const N: usize> ConstantTime for UxN<U, N>
+where
+    U: ConstantTime + Copy + From<u8> + BitXor<Output = U> + BitOr<Output = U> + BitAnd<Output = U>
+    + Not<Output = U>,
+    Wrapping<U>: Sub<Output = Wrapping<U>>
+{
+    fn ct_eq(&self, other: &Self) -> bool {
+        (*self ^ *other).ct_is_zero()
+    }
+
+    fn ct_is_zero(&self) -> bool {
+        self.0.iter().fold(U::from(0_u8), |acc, &x| acc | x).ct_is_zero()
+    }
+
+    fn ct_select(a: &Self, b: &Self, choice: bool) -> Self {
+        let mask = (Wrapping(U::from(0_u8)) - Wrapping(U::from(black_box(choice) as u8))).0;
+        (*a & !UxN([mask; N])) | (*b & UxN([mask; N]))
+    }
+}
+
+/// Constant-time equality of byte slices.
+/// The lengths are considered public: slices of different lengths are not equal.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let diff = a.iter().zip(b.iter()).fold(0_u8, |acc, (x, y)| acc | (x ^ y));
+    nonzero_bit(diff) == 0
+}
+
+/// Constant-time test for an all-zero byte slice.
+pub fn ct_is_zero(a: &[u8]) -> bool {
+    nonzero_bit(a.iter().fold(0_u8, |acc, x| acc | x)) == 0
+}
+
+/// Constant-time selection of byte slices of the same length: returns `b` if `choice` is true and `a` otherwise.
+pub fn ct_select(a: &[u8], b: &[u8], choice: bool) -> Vec<u8> {
+    assert!(a.len() == b.len(), "Constant-time selection: slices must have the same length.");
+    let mask = (black_box(choice) as u8).wrapping_neg();
+    a.iter().zip(b.iter()).map(|(x, y)| (x & !mask) | (y & mask)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::ustates::Ux4;
+
+    #[test]
+    fn byte_slices() {
+        assert!(ct_eq(b"tag", b"tag"));
+        assert!(!ct_eq(b"tag", b"tah"));
+        assert!(!ct_eq(b"tag", b"tags"));
+        assert!(ct_is_zero(&[0; 16]));
+        assert!(!ct_is_zero(&[0, 0, 0x80, 0]));
+        assert!(ct_select(b"abc", b"xyz", false) == b"abc");
+        assert!(ct_select(b"abc", b"xyz", true) == b"xyz");
+    }
+
+    #[test]
+    fn primitives() {
+        for (a, b) in [(0_u64, 0_u64), (1, 0), (u64::MAX, u64::MAX), (1 << 63, 0)] {
+            assert!(a.ct_eq(&b) == (a == b));
+            assert!(a.ct_is_zero() == (a == 0));
+            assert!(u64::ct_select(&a, &b, true) == b);
+            assert!(u64::ct_select(&a, &b, false) == a);
+        }
+        for x in 0..=255_u8 {
+            assert!(x.ct_is_zero() == (x == 0));
+        }
+    }
+
+    #[test]
+    fn states() {
+        let a = Ux4::<u64>::new([1, 2, 3, 4]);
+        let b = Ux4::<u64>::new([1, 2, 3, 5]);
+        assert!(a.ct_eq(&a) && !a.ct_eq(&b));
+        assert!(Ux4::<u64>::zero().ct_is_zero() && !b.ct_is_zero());
+        assert!(Ux4::ct_select(&a, &b, true) == b);
+        assert!(Ux4::ct_select(&a, &b, false) == a);
+    }
+}
@@ -0,0 +1,48 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing hexadecimal decoding.
+//!
+//! Used to read test vectors and to parse the multi-register states.
+//! The input is checked byte by byte, so that non-ASCII characters
+//! or signs accepted by `u8::from_str_radix` are rejected with `Error::Parse`.
+
+use crate::error::Error;
+
+/// Value of a single ASCII hexadecimal digit.
+fn hex_digit(digit: u8) -> Result<u8, Error> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(Error::Parse("invalid hexadecimal digit".to_string())),
+    }
+}
+
+/// Decode a hexadecimal string into bytes.
+/// Only ASCII hexadecimal digits are accepted, without sign or prefix.
+pub fn from_hex(value: &str) -> Result<Vec<u8>, Error> {
+    let digits = value.as_bytes();
+    if !digits.len().is_multiple_of(2) {
+        return Err(Error::Parse("odd number of hexadecimal digits".to_string()));
+    }
+
+    digits.chunks(2)
+        .map(|pair| Ok((hex_digit(pair[0])? << 4) | hex_digit(pair[1])?))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn errors() {
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+        assert!(from_hex("+f").is_err());
+        assert!(from_hex("aéa").is_err());
+        assert!(from_hex("0A1b").unwrap() == vec![0x0a, 0x1b]);
+        assert!(from_hex("").unwrap().is_empty());
+    }
+}
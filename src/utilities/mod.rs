@@ -3,6 +3,8 @@
 
 //! Module implementing various utilities.
 
+use crate::error::Error;
+
 /// Trait for converting types to little-endian byte representation.
 ///
 /// Provides a method `to_le_bytes` that converts the implementing type
@@ -13,5 +15,40 @@ pub trait ToLeBytes {
     fn to_le_bytes(&self) -> Vec<u8>;
 }
 
+/// Trait for parsing types from their little-endian byte representation.
+///
+/// Inverse of `ToLeBytes`: the input must have exactly the length produced by `to_le_bytes`.
+pub trait FromLeBytes: Sized {
+    /// Parses a value from bytes in little-endian order, or returns an error if the length is wrong.
+    fn from_le_bytes(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+/// Trait for converting types to big-endian byte representation.
+pub trait ToBeBytes {
+    /// Converts the implementing type to a vector of bytes in big-endian order.
+    fn to_be_bytes(&self) -> Vec<u8>;
+}
+
+/// Trait for parsing types from their big-endian byte representation.
+pub trait FromBeBytes: Sized {
+    /// Parses a value from bytes in big-endian order, or returns an error if the length is wrong.
+    fn from_be_bytes(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+/// Trait giving the bit width of a state type.
+///
+/// Used by the sponge constructions to check their size parameter against the state type,
+/// e.g. 64 for u64 and 320 for UxN<u64, 5>.
+pub trait StateWidth {
+    /// Number of bits of the type.
+    const BITS: usize;
+}
+
 pub mod bitops;
+pub mod bitbuf;
 pub mod ustates;
+pub mod hex;
+pub mod rsp;
+pub mod sts;
+pub mod ct;
+pub mod zeroize;
@@ -0,0 +1,139 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing a parser for NIST CAVP response files.
+//!
+//! The `.rsp` files of the [Cryptographic Algorithm Validation Program](https://csrc.nist.gov/projects/cryptographic-algorithm-validation-program)
+//! are made of sections, introduced by bracketed header lines such as `[SHA-256]` or `[NonceLen = 128]`,
+//! each followed by records of `Key = Value` lines separated by blank lines.
+//! Lines starting with `#` are comments.
+
+use crate::error::Error;
+use crate::utilities::hex::from_hex;
+
+/// Single record of a response file, with the header of the section it belongs to.
+#[derive(Clone, Debug, Default)]
+pub struct RspRecord {
+    /// Header of the section, in order. Flags such as `[SHA-256]` have an empty value.
+    pub header: Vec<(String, String)>,
+
+    /// Fields of the record, in order. Keys may be repeated.
+    pub fields: Vec<(String, String)>,
+}
+
+impl RspRecord {
+    /// Value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Values of all the fields named `key`, in order.
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.fields.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+    }
+
+    /// Value of the first field named `key`, decoded from hexadecimal.
+    pub fn get_hex(&self, key: &str) -> Result<Vec<u8>, Error> {
+        match self.get(key) {
+            Some(value) => from_hex(value),
+            None => Err(Error::Parse(format!("RSP: missing field {}", key))),
+        }
+    }
+
+    /// Values of all the fields named `key`, decoded from hexadecimal.
+    pub fn get_all_hex(&self, key: &str) -> Result<Vec<Vec<u8>>, Error> {
+        self.get_all(key).into_iter().map(from_hex).collect()
+    }
+
+    /// Value of the header entry named `key`.
+    pub fn header(&self, key: &str) -> Option<&str> {
+        self.header.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the section header contains the flag or entry `key`.
+    pub fn has_header(&self, key: &str) -> bool {
+        self.header.iter().any(|(k, _)| k == key)
+    }
+}
+
+/// Split a `Key = Value` line, trimming both sides.
+fn split_entry(line: &str) -> (String, String) {
+    match line.split_once('=') {
+        Some((key, value)) => (key.trim().to_string(), value.trim().to_string()),
+        None => (line.trim().to_string(), String::new()),
+    }
+}
+
+/// Parse the content of a response file into its records.
+pub fn parse_rsp(content: &str) -> Vec<RspRecord> {
+    let mut records: Vec<RspRecord> = Vec::new();
+    let mut header: Vec<(String, String)> = Vec::new();
+    let mut current = RspRecord::default();
+    let mut new_section = true;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with('[') {
+            // Close the current record
+            if !current.fields.is_empty() {
+                records.push(std::mem::take(&mut current));
+                new_section = true;
+            }
+
+            if let Some(entry) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                // A header line after some records starts a new section
+                if new_section {
+                    header.clear();
+                    new_section = false;
+                }
+                header.push(split_entry(entry));
+            }
+            continue;
+        }
+
+        if current.fields.is_empty() {
+            current.header = header.clone();
+        }
+        current.fields.push(split_entry(line));
+    }
+
+    if !current.fields.is_empty() {
+        records.push(current);
+    }
+    records
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_sections() {
+        let content = "# Comment\n\n[SHA-1]\n[NonceLen = 128]\n\nCOUNT = 0\nKey = 0aff\nKey = \n\nCOUNT = 1\nKey = 00\n\n[SHA-256]\n[NonceLen = 0]\n\nCOUNT = 0\nKey = 01\n";
+        let records = parse_rsp(content);
+        assert!(records.len() == 3);
+
+        assert!(records[0].has_header("SHA-1"));
+        assert!(records[0].header("NonceLen") == Some("128"));
+        assert!(records[0].get_all_hex("Key").unwrap() == vec![vec![0x0a, 0xff], vec![]]);
+        assert!(records[1].get("COUNT") == Some("1"));
+
+        assert!(records[2].has_header("SHA-256"));
+        assert!(!records[2].has_header("SHA-1"));
+        assert!(records[2].header("NonceLen") == Some("0"));
+    }
+
+    #[test]
+    fn parse_drbg_vectors() {
+        for content in [include_str!("../../vectors/drbg/HMAC_DRBG.rsp"), include_str!("../../vectors/drbg/hash_drbg_sha256.rsp")] {
+            let records = parse_rsp(content);
+            assert!(records.len() == 24);
+            assert!(records.iter().all(|r| r.has_header("SHA-256")));
+            assert!(records.iter().all(|r| r.get_hex("ReturnedBits").unwrap().len() == 128));
+            assert!(records.iter().all(|r| r.get_all("AdditionalInput").len() == 2));
+        }
+    }
+}
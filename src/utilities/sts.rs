@@ -0,0 +1,597 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing a statistical test battery for random bit sequences.
+//!
+//! Implements the tests of [NIST SP 800-22 Rev. 1a](https://doi.org/10.6028/NIST.SP.800-22r1a):
+//! frequency, block frequency, runs, longest run of ones, binary matrix rank, discrete Fourier transform,
+//! serial, approximate entropy and cumulative sums.
+//! Sequences are given as slices of bits (0s and 1s), as produced by `BitBuf::to_bits`.
+//! Each test returns its p-value(s); a sequence is considered non-random at level `alpha`
+//! (usually 0.01) when a p-value is smaller than `alpha`.
+
+use crate::error::Error;
+use std::f64::consts::{PI, SQRT_2};
+
+use crate::prng::PRNG;
+use crate::utilities::{ToLeBytes, bitbuf::{BitBuf, BitOrder}};
+
+// Special functions.
+
+/// Logarithm of the Gamma function, using the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8,
+        771.323_428_777_653_1, -176.615_029_162_140_6, 12.507_343_278_686_905,
+        -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// Regularized upper incomplete Gamma function Q(a, x).
+pub fn igamc(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-15;
+    const MAX_ITERATIONS: usize = 10_000;
+
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+
+    if x < a + 1.0 {
+        // Series expansion of P(a, x)
+        let (mut term, mut sum, mut n) = (1.0 / a, 1.0 / a, a);
+        for _ in 0..MAX_ITERATIONS {
+            n += 1.0;
+            term *= x / n;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        1.0 - sum * prefactor
+    } else {
+        // Continued fraction of Q(a, x), modified Lentz method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_ITERATIONS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {d = tiny;}
+            c = b + an / c;
+            if c.abs() < tiny {c = tiny;}
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+        h * prefactor
+    }
+}
+
+/// Complementary error function.
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        2.0 - erfc(-x)
+    } else {
+        igamc(0.5, x * x)
+    }
+}
+
+/// Standard normal cumulative distribution function.
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / SQRT_2)
+}
+
+// Fourier transform.
+
+/// In-place radix-2 FFT, the length of `a` being a power of two.
+fn fft_pow2(a: &mut [(f64, f64)], invert: bool) {
+    let n = a.len();
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let angle = 2.0 * PI / len as f64 * if invert {1.0} else {-1.0};
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (s, c) = (angle * k as f64).sin_cos();
+                let (ur, ui) = a[start + k];
+                let (vr, vi) = a[start + k + len / 2];
+                let (tr, ti) = (vr * c - vi * s, vr * s + vi * c);
+                a[start + k] = (ur + tr, ui + ti);
+                a[start + k + len / 2] = (ur - tr, ui - ti);
+            }
+        }
+        len <<= 1;
+    }
+
+    if invert {
+        for x in a.iter_mut() {
+            *x = (x.0 / n as f64, x.1 / n as f64);
+        }
+    }
+}
+
+/// Discrete Fourier transform of a real sequence of any length,
+/// using Bluestein's algorithm when the length is not a power of two.
+fn dft(x: &[f64]) -> Vec<(f64, f64)> {
+    let n = x.len();
+    if n.is_power_of_two() {
+        let mut a: Vec<(f64, f64)> = x.iter().map(|&v| (v, 0.0)).collect();
+        fft_pow2(&mut a, false);
+        return a;
+    }
+
+    // Chirp w_j = exp(-i pi j^2 / n), with j^2 reduced modulo 2n for accuracy
+    let chirp: Vec<(f64, f64)> = (0..n)
+        .map(|j| {
+            let angle = PI * ((j * j) % (2 * n)) as f64 / n as f64;
+            (angle.cos(), -angle.sin())
+        })
+        .collect();
+
+    let m = (2 * n - 1).next_power_of_two();
+    let mut a = vec![(0.0, 0.0); m];
+    let mut b = vec![(0.0, 0.0); m];
+    for j in 0..n {
+        a[j] = (x[j] * chirp[j].0, x[j] * chirp[j].1);
+        b[j] = (chirp[j].0, -chirp[j].1);
+        if j > 0 {
+            b[m - j] = b[j];
+        }
+    }
+
+    fft_pow2(&mut a, false);
+    fft_pow2(&mut b, false);
+    for (u, v) in a.iter_mut().zip(b.iter()) {
+        *u = (u.0 * v.0 - u.1 * v.1, u.0 * v.1 + u.1 * v.0);
+    }
+    fft_pow2(&mut a, true);
+
+    (0..n)
+        .map(|k| {
+            let (cr, ci) = chirp[k];
+            (a[k].0 * cr - a[k].1 * ci, a[k].0 * ci + a[k].1 * cr)
+        })
+        .collect()
+}
+
+// Statistical tests.
+
+/// Check that the sequence has at least `min` bits.
+fn check_length(bits: &[u8], min: usize, test: &str) -> Result<usize, Error> {
+    if bits.len() < min {
+        return Err(Error::invalid("bits", format!("STS {}: at least {} bits are required, got {}", test, min, bits.len())));
+    }
+    Ok(bits.len())
+}
+
+/// Frequency (monobit) test.
+pub fn frequency(bits: &[u8]) -> Result<f64, Error> {
+    let n = check_length(bits, 1, "Frequency")?;
+    let sum: i64 = bits.iter().map(|&b| 2 * b as i64 - 1).sum();
+    let s_obs = (sum.abs() as f64) / (n as f64).sqrt();
+    Ok(erfc(s_obs / SQRT_2))
+}
+
+/// Frequency test within blocks of `M` bits.
+pub fn block_frequency(bits: &[u8], M: usize) -> Result<f64, Error> {
+    if M == 0 {
+        return Err(Error::invalid("M", "STS Block Frequency: block length M must be at least 1"));
+    }
+    check_length(bits, M, "Block Frequency")?;
+    let N = bits.len() / M;
+
+    let chi_squared: f64 = bits.chunks_exact(M)
+        .map(|block| {
+            let pi = block.iter().map(|&b| b as f64).sum::<f64>() / M as f64;
+            (pi - 0.5) * (pi - 0.5)
+        })
+        .sum::<f64>() * 4.0 * M as f64;
+
+    Ok(igamc(N as f64 / 2.0, chi_squared / 2.0))
+}
+
+/// Runs test.
+pub fn runs(bits: &[u8]) -> Result<f64, Error> {
+    let n = check_length(bits, 2, "Runs")? as f64;
+    let pi = bits.iter().map(|&b| b as f64).sum::<f64>() / n;
+
+    // Frequency prerequisite
+    if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+        return Ok(0.0);
+    }
+
+    let v_obs = 1 + bits.windows(2).filter(|w| w[0] != w[1]).count();
+    let num = (v_obs as f64 - 2.0 * n * pi * (1.0 - pi)).abs();
+    let den = 2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi);
+    Ok(erfc(num / den))
+}
+
+/// Test for the longest run of ones in a block.
+pub fn longest_run(bits: &[u8]) -> Result<f64, Error> {
+    let n = check_length(bits, 128, "Longest Run")?;
+
+    // Block size, class boundaries and class probabilities depending on n
+    let (M, min, pi): (usize, usize, &[f64]) = if n < 6272 {
+        (8, 1, &[0.2148, 0.3672, 0.2305, 0.1875])
+    } else if n < 750_000 {
+        (128, 4, &[0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124])
+    } else {
+        (10_000, 10, &[0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727])
+    };
+    let K = pi.len() - 1;
+
+    let mut v = vec![0_usize; K + 1];
+    let mut N = 0;
+    for block in bits.chunks_exact(M) {
+        let (mut run, mut longest) = (0, 0);
+        for &b in block {
+            run = if b == 1 {run + 1} else {0};
+            longest = longest.max(run);
+        }
+        v[longest.clamp(min, min + K) - min] += 1;
+        N += 1;
+    }
+
+    let chi_squared: f64 = v.iter().zip(pi.iter())
+        .map(|(&vi, &p)| {
+            let expected = N as f64 * p;
+            (vi as f64 - expected).powi(2) / expected
+        })
+        .sum();
+
+    Ok(igamc(K as f64 / 2.0, chi_squared / 2.0))
+}
+
+/// Rank of a 32x32 binary matrix given by its rows.
+fn binary_rank(mut rows: [u32; 32]) -> usize {
+    let mut rank = 0;
+    for col in 0..32 {
+        let bit = 1_u32 << (31 - col);
+        if let Some(pivot) = (rank..32).find(|&i| rows[i] & bit != 0) {
+            rows.swap(rank, pivot);
+            for i in 0..32 {
+                if i != rank && rows[i] & bit != 0 {
+                    rows[i] ^= rows[rank];
+                }
+            }
+            rank += 1;
+        }
+    }
+    rank
+}
+
+/// Binary matrix rank test, with 32x32 matrices.
+pub fn rank(bits: &[u8]) -> Result<f64, Error> {
+    check_length(bits, 38 * 1024, "Rank")?;
+    let N = bits.len() / 1024;
+
+    let mut counts = [0_usize; 3]; // full rank, full rank - 1, lower
+    for matrix in bits.chunks_exact(1024) {
+        let mut rows = [0_u32; 32];
+        for (row, chunk) in rows.iter_mut().zip(matrix.chunks_exact(32)) {
+            *row = chunk.iter().fold(0, |acc, &b| (acc << 1) | b as u32);
+        }
+        match binary_rank(rows) {
+            32 => counts[0] += 1,
+            31 => counts[1] += 1,
+            _ => counts[2] += 1,
+        }
+    }
+
+    let pi = [0.288_788_095_1, 0.577_576_190_1, 0.133_635_714_8];
+    let chi_squared: f64 = counts.iter().zip(pi.iter())
+        .map(|(&f, &p)| (f as f64 - p * N as f64).powi(2) / (p * N as f64))
+        .sum();
+
+    Ok((-chi_squared / 2.0).exp())
+}
+
+/// Discrete Fourier transform (spectral) test.
+pub fn spectral(bits: &[u8]) -> Result<f64, Error> {
+    let n = check_length(bits, 2, "DFT")? as f64;
+    let x: Vec<f64> = bits.iter().map(|&b| 2.0 * b as f64 - 1.0).collect();
+    let S = dft(&x);
+
+    let threshold = ((1.0_f64 / 0.05).ln() * n).sqrt();
+    let N0 = 0.95 * n / 2.0;
+    let N1 = S[..bits.len() / 2].iter()
+        .filter(|(re, im)| (re * re + im * im).sqrt() < threshold)
+        .count() as f64;
+
+    let d = (N1 - N0) / (n * 0.95 * 0.05 / 4.0).sqrt();
+    Ok(erfc(d.abs() / SQRT_2))
+}
+
+/// psi^2_m statistic of the serial test, counting overlapping m-bit patterns with wraparound.
+fn psi_squared(bits: &[u8], m: usize) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+    let n = bits.len();
+    let counts = pattern_counts(bits, m);
+    let sum: f64 = counts.iter().map(|&c| (c as f64) * (c as f64)).sum();
+    sum * (1_usize << m) as f64 / n as f64 - n as f64
+}
+
+/// Frequencies of all overlapping m-bit patterns, the sequence being extended with its first m-1 bits.
+fn pattern_counts(bits: &[u8], m: usize) -> Vec<usize> {
+    let n = bits.len();
+    let mask = (1_usize << m) - 1;
+    let mut counts = vec![0_usize; 1 << m];
+
+    let mut pattern = 0_usize;
+    for i in 0..(n + m - 1) {
+        pattern = ((pattern << 1) | bits[i % n] as usize) & mask;
+        if i >= m - 1 {
+            counts[pattern] += 1;
+        }
+    }
+    counts
+}
+
+/// Serial test with patterns of `m` bits, returning both p-values.
+pub fn serial(bits: &[u8], m: usize) -> Result<(f64, f64), Error> {
+    check_length(bits, m + 1, "Serial")?;
+    if m < 2 {
+        return Err(Error::invalid("m", "STS Serial: pattern length m must be at least 2"));
+    }
+
+    let psi_m = psi_squared(bits, m);
+    let psi_m1 = psi_squared(bits, m - 1);
+    let psi_m2 = psi_squared(bits, m - 2);
+
+    let delta1 = psi_m - psi_m1;
+    let delta2 = psi_m - 2.0 * psi_m1 + psi_m2;
+
+    let p1 = igamc((1_usize << (m - 1)) as f64 / 2.0, delta1 / 2.0);
+    let p2 = igamc((1_usize << (m - 2)) as f64 / 2.0, delta2 / 2.0);
+    Ok((p1, p2))
+}
+
+/// Approximate entropy test with blocks of `m` bits.
+pub fn approximate_entropy(bits: &[u8], m: usize) -> Result<f64, Error> {
+    let n = check_length(bits, m + 2, "Approximate Entropy")? as f64;
+    if m < 1 {
+        return Err(Error::invalid("m", "STS Approximate Entropy: block length m must be at least 1"));
+    }
+
+    let phi = |m: usize| -> f64 {
+        pattern_counts(bits, m).iter()
+            .filter(|&&c| c > 0)
+            .map(|&c| {
+                let p = c as f64 / n;
+                p * p.ln()
+            })
+            .sum()
+    };
+
+    let ap_en = phi(m) - phi(m + 1);
+    let chi_squared = 2.0 * n * (2.0_f64.ln() - ap_en);
+    Ok(igamc((1_usize << (m - 1)) as f64, chi_squared / 2.0))
+}
+
+/// Cumulative sums test, returning the forward and backward p-values.
+pub fn cumulative_sums(bits: &[u8]) -> Result<(f64, f64), Error> {
+    let n = check_length(bits, 1, "Cumulative Sums")? as i64;
+
+    let p_value = |z: i64| -> f64 {
+        let sqrt_n = (n as f64).sqrt();
+        let z_f = z as f64;
+
+        let mut sum1 = 0.0;
+        let mut k = (-n / z + 1) / 4;
+        while k <= (n / z - 1) / 4 {
+            sum1 += normal_cdf((4 * k + 1) as f64 * z_f / sqrt_n);
+            sum1 -= normal_cdf((4 * k - 1) as f64 * z_f / sqrt_n);
+            k += 1;
+        }
+
+        let mut sum2 = 0.0;
+        let mut k = (-n / z - 3) / 4;
+        while k <= (n / z - 1) / 4 {
+            sum2 += normal_cdf((4 * k + 3) as f64 * z_f / sqrt_n);
+            sum2 -= normal_cdf((4 * k + 1) as f64 * z_f / sqrt_n);
+            k += 1;
+        }
+
+        1.0 - sum1 + sum2
+    };
+
+    let max_excursion = |iter: &mut dyn Iterator<Item = &u8>| -> i64 {
+        let mut sum = 0_i64;
+        let mut z = 0_i64;
+        for &b in iter {
+            sum += 2 * b as i64 - 1;
+            z = z.max(sum.abs());
+        }
+        z.max(1)
+    };
+
+    let forward = p_value(max_excursion(&mut bits.iter()));
+    let backward = p_value(max_excursion(&mut bits.iter().rev()));
+    Ok((forward, backward))
+}
+
+// Battery.
+
+/// Result of one test of the battery.
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    /// Name of the test.
+    pub name: &'static str,
+
+    /// p-values of the test.
+    pub p_values: Vec<f64>,
+}
+
+impl TestResult {
+    /// Whether all the p-values are at least `alpha`.
+    pub fn passed(&self, alpha: f64) -> bool {
+        self.p_values.iter().all(|&p| p >= alpha)
+    }
+}
+
+/// Minimum number of bits for running the whole battery (38 matrices for the rank test).
+pub const MIN_BATTERY_BITS: usize = 38 * 1024;
+
+/// Run the whole battery with the recommended parameters:
+/// M = 128 for the block frequency test, and the largest pattern lengths (up to 16 for the serial test
+/// and 10 for the approximate entropy test) allowed by the length of the sequence.
+pub fn run_all(bits: &[u8]) -> Result<Vec<TestResult>, Error> {
+    check_length(bits, MIN_BATTERY_BITS, "Battery")?;
+    let log_n = bits.len().ilog2() as usize;
+    let m_serial = (log_n - 3).min(16);
+    let m_apen = (log_n - 6).min(10);
+
+    let (serial1, serial2) = serial(bits, m_serial)?;
+    let (cusum_fwd, cusum_bwd) = cumulative_sums(bits)?;
+
+    Ok(vec![
+        TestResult { name: "Frequency", p_values: vec![frequency(bits)?] },
+        TestResult { name: "Block Frequency", p_values: vec![block_frequency(bits, 128)?] },
+        TestResult { name: "Runs", p_values: vec![runs(bits)?] },
+        TestResult { name: "Longest Run", p_values: vec![longest_run(bits)?] },
+        TestResult { name: "Rank", p_values: vec![rank(bits)?] },
+        TestResult { name: "DFT", p_values: vec![spectral(bits)?] },
+        TestResult { name: "Serial", p_values: vec![serial1, serial2] },
+        TestResult { name: "Approximate Entropy", p_values: vec![approximate_entropy(bits, m_apen)?] },
+        TestResult { name: "Cumulative Sums", p_values: vec![cusum_fwd, cusum_bwd] },
+    ])
+}
+
+/// Collect `n` bits from a PRNG, keeping the `width` lower bits of each output (LSB-first).
+pub fn prng_bits<P>(prng: &mut P, width: usize, n: usize) -> Result<Vec<u8>, Error>
+where
+    P: PRNG,
+    P::Output: ToLeBytes
+{
+    let mut bits = BitBuf::new(BitOrder::LsbFirst);
+    while bits.len() < n {
+        bits.append(&prng.next()?, width);
+    }
+    bits.truncate(n);
+    Ok(bits.to_bits())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// First 100 binary digits of pi, used as example throughout SP 800-22.
+    const EPSILON_100: &str = "1100100100001111110110101010001000100001011010001100001000110100110001001100011001100010100010111000";
+
+    fn parse(s: &str) -> Vec<u8> {
+        s.bytes().map(|c| c - b'0').collect()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn special_functions() {
+        assert!(close(erfc(0.0), 1.0));
+        assert!(close(erfc(1.0), 0.157_299_207_050_285_1));
+        assert!(close(erfc(-0.5), 1.520_499_877_813_046_5));
+        assert!(close(igamc(3.0, 2.5), 0.543_813_813_150_189_6));
+        assert!(close(igamc(0.5, 10.0), 7.744_216_431_044_077e-6));
+        assert!(close(ln_gamma(10.0), 12.801_827_480_081_469));
+    }
+
+    #[test]
+    fn dft_matches_naive() {
+        let x: Vec<f64> = parse(EPSILON_100).iter().map(|&b| 2.0 * b as f64 - 1.0).collect();
+        for n in [10, 64, 100] {
+            let fast = dft(&x[..n]);
+            for (k, &(re, im)) in fast.iter().enumerate() {
+                let (mut nr, mut ni) = (0.0, 0.0);
+                for (j, &v) in x[..n].iter().enumerate() {
+                    let angle = -2.0 * PI * (j * k) as f64 / n as f64;
+                    nr += v * angle.cos();
+                    ni += v * angle.sin();
+                }
+                assert!((re - nr).abs() < 1e-9 && (im - ni).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn sp800_22_examples() {
+        let epsilon = parse(EPSILON_100);
+        assert!(close(frequency(&parse("1011010101")).unwrap(), 0.527_089));
+        assert!(close(frequency(&epsilon).unwrap(), 0.109_599));
+        assert!(close(block_frequency(&parse("0110011010"), 3).unwrap(), 0.801_252));
+        assert!(close(block_frequency(&epsilon, 10).unwrap(), 0.706_438));
+        assert!(matches!(block_frequency(&epsilon, 0), Err(Error::InvalidParameter { field: "M", .. })));
+        assert!(close(runs(&parse("1001101011")).unwrap(), 0.147_232));
+        assert!(close(runs(&epsilon).unwrap(), 0.500_798));
+        // The DFT examples of the document predate the corrected variance, so the reference
+        // value below is computed with a naive DFT and the Rev. 1a statistic
+        assert!(close(spectral(&epsilon).unwrap(), 0.646_355));
+        let (p1, p2) = serial(&parse("0011011101"), 3).unwrap();
+        assert!(close(p1, 0.808_792) && close(p2, 0.670_320));
+        assert!(close(approximate_entropy(&parse("0100110101"), 3).unwrap(), 0.261_961));
+        assert!(close(approximate_entropy(&epsilon, 2).unwrap(), 0.235_301));
+        assert!(close(cumulative_sums(&parse("1011010111")).unwrap().0, 0.411_658_8));
+        let (forward, backward) = cumulative_sums(&epsilon).unwrap();
+        assert!(close(forward, 0.219_194) && close(backward, 0.114_866));
+    }
+
+    #[test]
+    fn longest_run_example() {
+        let epsilon = parse("11001100000101010110110001001100111000000000001001001101010100010001001111010110100000001101011111001100111001101101100010110010");
+        assert!((longest_run(&epsilon).unwrap() - 0.180_598).abs() < 1e-5);
+    }
+
+    #[test]
+    fn matrix_rank() {
+        let identity: [u32; 32] = std::array::from_fn(|i| 1 << i);
+        assert!(binary_rank(identity) == 32);
+        let mut rows = identity;
+        rows[5] = rows[3] ^ rows[7];
+        assert!(binary_rank(rows) == 31);
+        assert!(binary_rank([0; 32]) == 0);
+    }
+
+    #[test]
+    fn detects_periodic_sequence() {
+        let bits: Vec<u8> = (0..MIN_BATTERY_BITS).map(|i| ((i * 7) % 13 < 6) as u8).collect();
+        let results = run_all(&bits).unwrap();
+        assert!(results.len() == 9);
+        assert!(results.iter().any(|r| !r.passed(0.01)));
+    }
+}
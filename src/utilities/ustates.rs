@@ -3,72 +3,88 @@
 
 //! Module implementing multi-register states.
 //!
-//! Provides a structure `Ux4<U>` that represents a state consisting of four registers of type `U`.
-//! It supports various operations such as addition, subtraction, bitwise operations, and random generation.
+//! Provides a structure `UxN<U, N>` that represents a state consisting of N registers of type `U`,
+//! and the alias `Ux4<U>` for four-register states.
+//! It supports various operations such as arithmetic, comparisons, bitwise operations, and random generation.
 //! Compatible U types: `u8`, `u16`, `u32`, `u64`, `u128`.
 
 use std::fmt;
 use std::convert::From;
 use std::num::Wrapping;
-use std::cmp::{PartialOrd, PartialEq};
-use std::ops::{BitXor, BitOr, BitAnd, Not, Shl, Shr, Sub, Add};
+use std::cmp::{Ordering, PartialOrd, PartialEq};
+use std::ops::{BitXor, BitOr, BitAnd, Not, Shl, Shr, Sub, Add, Mul, Div, Rem};
 use rand::{Rng, thread_rng, distributions::Standard, prelude::Distribution};
 
-use crate::utilities::ToLeBytes;
+use crate::error::Error;
+use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug, Default)]
-/// Structure for four-register states.
+use crate::utilities::{ToLeBytes, FromLeBytes, ToBeBytes, FromBeBytes, StateWidth, hex::from_hex, bitops::Rotate};
+
+#[derive(Copy, Clone, Debug)]
+/// Structure for N-register states.
 ///
 /// Note that this structure uses little-endian format,
 /// i.e. the first register is the least significant.
 /// When printing, it will display in little-endian order.
-pub struct Ux4<U>(pub [U; 4]);
+pub struct UxN<U, const N: usize>(pub [U; N]);
+
+/// Four-register states, e.g. 256-bit states with `U = u64`.
+pub type Ux4<U> = UxN<U, 4>;
 
-impl<U> Ux4<U>
+impl<U, const N: usize> UxN<U, N>
 where
     U: From<u8> + Copy
 {
-    /// Return a new Ux4 with values `state`.
-    pub fn new(state: [U; 4]) -> Self {
-        Ux4(state)
+    /// Return a new UxN with values `state`.
+    pub fn new(state: [U; N]) -> Self {
+        UxN(state)
     }
 
-    /// Return a new zero-formatted Ux4.
+    /// Return a new zero-formatted UxN.
     pub fn zero() -> Self {
-        Ux4([0_u8.into(); 4])
+        UxN([0_u8.into(); N])
     }
 }
 
 /// UNSAFE.
-impl<U> Ux4<U>
+impl<U, const N: usize> UxN<U, N>
 where
     Standard: Distribution<U>
 {
-    /// Draw a random Ux4.
+    /// Draw a random UxN.
     pub fn rand() -> Self {
         let mut rng = thread_rng();
-        Ux4([rng.gen::<U>(), rng.gen::<U>(), rng.gen::<U>(), rng.gen::<U>()])
+        UxN(std::array::from_fn(|_| rng.gen::<U>()))
     }
 }
 
-impl<U> Ux4<U>
+impl<U, const N: usize> UxN<U, N>
 where
     U: Copy
 {
     /// Getter for the state values.
-    pub fn get(&self) -> [U; 4] {
-        [self.0[0], self.0[1], self.0[2], self.0[3]]
+    pub fn get(&self) -> [U; N] {
+        self.0
     }
 
     /// Setter for the state values.
-    pub fn set(&mut self, i: [U;4]) {
-        *self = Ux4(i);
+    pub fn set(&mut self, i: [U; N]) {
+        *self = UxN(i);
     }
 }
 
 // General Unsigned traits.
 
-impl<U> ToLeBytes for Ux4<U>
+impl<U, const N: usize> Default for UxN<U, N>
+where
+    U: Default
+{
+    fn default() -> Self {
+        UxN(std::array::from_fn(|_| U::default()))
+    }
+}
+
+impl<U, const N: usize> ToLeBytes for UxN<U, N>
 where
     U: Copy + ToLeBytes
 {
@@ -79,255 +95,623 @@ where
     }
 }
 
-impl<U> From<u8> for Ux4<U>
+impl<U, const N: usize> FromLeBytes for UxN<U, N>
+where
+    U: From<u8> + Copy + FromLeBytes
+{
+    fn from_le_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let unit = std::mem::size_of::<U>();
+        if bytes.len() != N * unit {
+            return Err(Error::BadLength { field: "UxN", expected: N * unit, got: bytes.len() });
+        }
+
+        let mut state = UxN::<U, N>::zero();
+        for (limb, chunk) in state.0.iter_mut().zip(bytes.chunks(unit)) {
+            *limb = U::from_le_bytes(chunk)?;
+        }
+        Ok(state)
+    }
+}
+
+impl<U, const N: usize> ToBeBytes for UxN<U, N>
+where
+    U: Copy + ToBeBytes
+{
+    fn to_be_bytes(&self) -> Vec<u8> {
+        self.0.iter().rev()
+            .flat_map(|x| x.to_be_bytes())
+            .collect()
+    }
+}
+
+impl<U, const N: usize> FromBeBytes for UxN<U, N>
+where
+    U: From<u8> + Copy + FromBeBytes
+{
+    fn from_be_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let unit = std::mem::size_of::<U>();
+        if bytes.len() != N * unit {
+            return Err(Error::BadLength { field: "UxN", expected: N * unit, got: bytes.len() });
+        }
+
+        let mut state = UxN::<U, N>::zero();
+        for (limb, chunk) in state.0.iter_mut().rev().zip(bytes.chunks(unit)) {
+            *limb = U::from_be_bytes(chunk)?;
+        }
+        Ok(state)
+    }
+}
+
+impl<U, const N: usize> FromStr for UxN<U, N>
+where
+    U: From<u8> + Copy + FromBeBytes
+{
+    type Err = Error;
+
+    /// Parses a hexadecimal number, most significant digit first, with an optional `0x` prefix.
+    /// Shorter strings are zero-extended.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        let size = 2 * N * std::mem::size_of::<U>();
+        if digits.is_empty() || digits.len() > size {
+            return Err(Error::Parse(format!("expected 1 to {} hexadecimal digits, got {}", size, digits.len())));
+        }
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::Parse(format!("invalid hexadecimal number {:?}", s)));
+        }
+
+        let padded = format!("{}{}", "0".repeat(size - digits.len()), digits);
+        Self::from_be_bytes(&from_hex(&padded)?)
+    }
+}
+
+impl<U, const N: usize> From<u8> for UxN<U, N>
 where
     U: From<u8> + Copy
 {
     fn from(item: u8) -> Self {
-        let mut state = Ux4::<U>::zero();
+        let mut state = UxN::<U, N>::zero();
         state.0[0] = item.into();
         state
     }
 }
 
-impl Distribution<Ux4<u64>> for Standard
+impl<U, const N: usize> Distribution<UxN<U, N>> for Standard
+where
+    Standard: Distribution<U>
 {
-    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Ux4<u64> {
-        Ux4([
-            rng.gen::<u64>(),
-            rng.gen::<u64>(),
-            rng.gen::<u64>(),
-            rng.gen::<u64>(),
-        ])
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> UxN<U, N> {
+        UxN(std::array::from_fn(|_| rng.gen::<U>()))
     }
 }
 
 // Unsigned operations.
 
-impl<U> Not for Ux4<U>
+impl<U, const N: usize> Not for UxN<U, N>
 where
     U: Not<Output = U> + Copy
 {
     type Output = Self;
 
     fn not(self) -> Self::Output {
-        Ux4([!self.0[0], !self.0[1], !self.0[2], !self.0[3]])
+        UxN(self.0.map(|x| !x))
     }
 }
 
-impl<U> BitAnd for Ux4<U>
+impl<U, const N: usize> BitAnd for UxN<U, N>
 where
     U: BitAnd<Output = U> + Copy
 {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        Ux4([self.0[0] & rhs.0[0], self.0[1] & rhs.0[1],
-            self.0[2] & rhs.0[2], self.0[3] & rhs.0[3]])
+        UxN(std::array::from_fn(|i| self.0[i] & rhs.0[i]))
     }
 }
 
-impl<U> BitXor for Ux4<U>
+impl<U, const N: usize> BitXor for UxN<U, N>
 where
     U: BitXor<Output = U> + Copy
 {
     type Output = Self;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
-        Ux4([self.0[0] ^ rhs.0[0], self.0[1] ^ rhs.0[1],
-            self.0[2] ^ rhs.0[2], self.0[3] ^ rhs.0[3]])
+        UxN(std::array::from_fn(|i| self.0[i] ^ rhs.0[i]))
     }
 }
 
-impl<U> BitOr for Ux4<U>
+impl<U, const N: usize> BitOr for UxN<U, N>
 where
     U: BitOr<Output = U> + Copy
 {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        Ux4([self.0[0] | rhs.0[0], self.0[1] | rhs.0[1],
-            self.0[2] | rhs.0[2], self.0[3] | rhs.0[3]])
+        UxN(std::array::from_fn(|i| self.0[i] | rhs.0[i]))
     }
 }
 
-impl<U> Shl<usize> for Ux4<U>
+impl<U, const N: usize> Shl<usize> for UxN<U, N>
 where
-    U: From<u8> + Copy + Shl<usize, Output = U> + Shr<usize, Output = U> + PartialEq + PartialOrd
-    + Add<Output = U> + BitAnd<Output = U>
+    U: From<u8> + Copy + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
 {
     type Output = Self;
 
+    /// Shift decomposed into a shift of whole registers and a shift of bits within registers.
     fn shl(self, shift: usize) -> Self::Output {
-        let mut result = self;
         let bits_per_unit = std::mem::size_of::<U>() * 8;
+        let (words, bits) = (shift / bits_per_unit, shift % bits_per_unit);
+        let mut result = UxN::<U, N>::zero();
 
-        for _ in 0..shift {
-            let mut carry: U = 0_u8.into();
-            for i in 0..4 {
-                let new_carry = result.0[i] >> (bits_per_unit - 1);
-                result.0[i] = ((result.0[i]) << 1) + carry;
-                carry = new_carry;
+        for i in words..N {
+            result.0[i] = self.0[i - words] << bits;
+            if bits > 0 && i > words {
+                result.0[i] = result.0[i] | (self.0[i - words - 1] >> (bits_per_unit - bits));
             }
         }
         result
     }
 }
 
-impl<U> Shr<usize> for Ux4<U>
+impl<U, const N: usize> Shr<usize> for UxN<U, N>
 where
-    U: From<u8> + Copy + Shr<usize, Output = U> + Shl<usize, Output = U> + PartialEq + PartialOrd
-    + Add<Output = U> + BitAnd<Output = U>
+    U: From<u8> + Copy + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
 {
     type Output = Self;
 
+    /// Shift decomposed into a shift of whole registers and a shift of bits within registers.
     fn shr(self, shift: usize) -> Self::Output {
-        let mut result = self;
         let bits_per_unit = std::mem::size_of::<U>() * 8;
+        let (words, bits) = (shift / bits_per_unit, shift % bits_per_unit);
+        let mut result = UxN::<U, N>::zero();
 
-        for _ in 0..shift {
-            let mut carry: U = 0_u8.into();
-            for i in (0..4).rev() {
-                // Shift current element and add carry
-                let new_carry = result.0[i] & 1_u8.into();
-                result.0[i] = ((result.0[i]) >> 1) + (carry << (bits_per_unit - 1));
-                carry = new_carry;
+        for i in 0..N.saturating_sub(words) {
+            result.0[i] = self.0[i + words] >> bits;
+            if bits > 0 && i + words + 1 < N {
+                result.0[i] = result.0[i] | (self.0[i + words + 1] << (bits_per_unit - bits));
             }
         }
         result
     }
 }
 
-impl<U> Add for Ux4<U>
+impl<U, const N: usize> UxN<U, N>
+where
+    U: From<u8> + Copy + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+{
+    /// Rotate the whole state left by `n` bits.
+    pub fn rotate_left(self, n: usize) -> Self {
+        let size = N * std::mem::size_of::<U>() * 8;
+        match n % size {
+            0 => self,
+            n => (self << n) | (self >> (size - n)),
+        }
+    }
+
+    /// Rotate the whole state right by `n` bits.
+    pub fn rotate_right(self, n: usize) -> Self {
+        let size = N * std::mem::size_of::<U>() * 8;
+        self.rotate_left(size - n % size)
+    }
+}
+
+impl<U, const N: usize> StateWidth for UxN<U, N>
 where
-    U: From<u8> + Copy + Add<Output = U> + PartialOrd,
+    U: StateWidth
+{
+    const BITS: usize = N * U::BITS;
+}
+
+impl<U, const N: usize> Rotate for UxN<U, N>
+where
+    U: From<u8> + Copy + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+{
+    fn rotl(self, n: usize) -> Self {
+        self.rotate_left(n)
+    }
+
+    fn rotr(self, n: usize) -> Self {
+        self.rotate_right(n)
+    }
+}
+
+impl<U, const N: usize> UxN<U, N>
+where
+    U: From<u8> + Copy + PartialOrd,
     Wrapping<U>: Add<Output = Wrapping<U>>
 {
-    type Output = Self;
+    /// Addition modulo 2^(N x bits of U), also returning whether a carry was lost.
+    pub fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+        let mut result = UxN::<U, N>::zero();
+        let mut carry = false;
+
+        for (i, limb) in result.0.iter_mut().enumerate() {
+            let partial = Wrapping(self.0[i]) + Wrapping(rhs.0[i]);
+            let sum = partial + Wrapping((carry as u8).into());
+            *limb = sum.0;
+            carry = partial < Wrapping(self.0[i]) || sum < partial;
+        }
+        (result, carry)
+    }
 
-    fn add(self, rhs: Self) -> Self::Output {
-        let mut result: [U; 4] = [0_u8.into(); 4];
-        let mut carry: U = 0_u8.into();
-
-        for i in 0..4 {
-            let sum = Wrapping(self.0[i]) + Wrapping(rhs.0[i]) + Wrapping(carry);
-            result[i] = sum.0;
-            carry = if sum < Wrapping(self.0[i]) || sum < Wrapping(rhs.0[i]) {
-                1_u8.into()
-            } else {
-                0_u8.into()
-            };
+    /// Addition returning `None` on overflow.
+    pub fn checked_add(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_add(rhs) {
+            (result, false) => Some(result),
+            _ => None,
         }
-        Ux4(result)
     }
 }
 
-impl<U> Sub for Ux4<U>
+impl<U, const N: usize> UxN<U, N>
 where
-    U: From<u8> + Copy + Add<Output = U> + Not<Output = U> + PartialOrd,
+    U: From<u8> + Copy + PartialOrd,
+    Wrapping<U>: Sub<Output = Wrapping<U>>
+{
+    /// Subtraction modulo 2^(N x bits of U), also returning whether a borrow was lost.
+    pub fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+        let mut result = UxN::<U, N>::zero();
+        let mut borrow = false;
+
+        for (i, limb) in result.0.iter_mut().enumerate() {
+            let partial = Wrapping(self.0[i]) - Wrapping(rhs.0[i]);
+            let difference = partial - Wrapping((borrow as u8).into());
+            *limb = difference.0;
+            borrow = self.0[i] < rhs.0[i] || partial < Wrapping((borrow as u8).into());
+        }
+        (result, borrow)
+    }
+
+    /// Subtraction returning `None` on underflow.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_sub(rhs) {
+            (result, false) => Some(result),
+            _ => None,
+        }
+    }
+}
+
+impl<U, const N: usize> UxN<U, N>
+where
+    U: From<u8> + Copy + Ord + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+    + BitAnd<Output = U>,
+    Wrapping<U>: Add<Output = Wrapping<U>> + Sub<Output = Wrapping<U>>
+{
+    /// Value of the `i`-th bit.
+    fn bit(&self, i: usize) -> bool {
+        let bits_per_unit = std::mem::size_of::<U>() * 8;
+        (self.0[i / bits_per_unit] >> (i % bits_per_unit)) & 1_u8.into() != 0_u8.into()
+    }
+
+    /// Multiplication modulo 2^(N x bits of U), also returning whether the product overflowed.
+    /// Uses shift-and-add over the bits of `rhs`.
+    pub fn overflowing_mul(self, rhs: Self) -> (Self, bool) {
+        let size = N * std::mem::size_of::<U>() * 8;
+        let mut result = UxN::<U, N>::zero();
+        let mut overflow = false;
+
+        for i in (0..size).filter(|&i| rhs.bit(i)) {
+            let shifted = self << i;
+            overflow |= (shifted >> i) != self;
+            let (sum, carry) = result.overflowing_add(shifted);
+            result = sum;
+            overflow |= carry;
+        }
+        (result, overflow)
+    }
+
+    /// Multiplication returning `None` on overflow.
+    pub fn checked_mul(self, rhs: Self) -> Option<Self> {
+        match self.overflowing_mul(rhs) {
+            (result, false) => Some(result),
+            _ => None,
+        }
+    }
+
+    /// Quotient and remainder of the euclidean division, using binary long division.
+    /// Returns `None` if `rhs` is zero.
+    pub fn checked_div_rem(self, rhs: Self) -> Option<(Self, Self)> {
+        if rhs == UxN::zero() {
+            return None;
+        }
+        let size = N * std::mem::size_of::<U>() * 8;
+        let one = UxN::<U, N>::from(1_u8);
+        let (mut quotient, mut remainder) = (UxN::<U, N>::zero(), UxN::<U, N>::zero());
+
+        for i in (0..size).rev() {
+            // The remainder is below rhs, so the bit shifted out is zero unless rhs has its top bit set
+            let top = remainder.bit(size - 1);
+            remainder = (remainder << 1) | if self.bit(i) {one} else {UxN::zero()};
+            if top || remainder >= rhs {
+                remainder = remainder.overflowing_sub(rhs).0;
+                quotient = quotient | (one << i);
+            }
+        }
+        Some((quotient, remainder))
+    }
+
+    /// Division returning `None` if `rhs` is zero.
+    pub fn checked_div(self, rhs: Self) -> Option<Self> {
+        self.checked_div_rem(rhs).map(|(quotient, _)| quotient)
+    }
+
+    /// Remainder returning `None` if `rhs` is zero.
+    pub fn checked_rem(self, rhs: Self) -> Option<Self> {
+        self.checked_div_rem(rhs).map(|(_, remainder)| remainder)
+    }
+}
+
+impl<U, const N: usize> Add for UxN<U, N>
+where
+    U: From<u8> + Copy + PartialOrd,
     Wrapping<U>: Add<Output = Wrapping<U>>
 {
     type Output = Self;
 
+    /// Wrapping addition.
+    fn add(self, rhs: Self) -> Self::Output {
+        self.overflowing_add(rhs).0
+    }
+}
+
+impl<U, const N: usize> Sub for UxN<U, N>
+where
+    U: From<u8> + Copy + PartialOrd,
+    Wrapping<U>: Sub<Output = Wrapping<U>>
+{
+    type Output = Self;
+
+    /// Wrapping subtraction.
     fn sub(self, rhs: Self) -> Self::Output {
-        let two_complement_rhs = (!rhs) +
-            Ux4([1u8.into(),0u8.into(),0u8.into(),0u8.into()]);
-        self + two_complement_rhs
+        self.overflowing_sub(rhs).0
+    }
+}
+
+impl<U, const N: usize> Mul for UxN<U, N>
+where
+    U: From<u8> + Copy + Ord + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+    + BitAnd<Output = U>,
+    Wrapping<U>: Add<Output = Wrapping<U>> + Sub<Output = Wrapping<U>>
+{
+    type Output = Self;
+
+    /// Wrapping multiplication.
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.overflowing_mul(rhs).0
+    }
+}
+
+impl<U, const N: usize> Div for UxN<U, N>
+where
+    U: From<u8> + Copy + Ord + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+    + BitAnd<Output = U>,
+    Wrapping<U>: Add<Output = Wrapping<U>> + Sub<Output = Wrapping<U>>
+{
+    type Output = Self;
+
+    /// Euclidean division, panics if `rhs` is zero.
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(rhs).expect("attempt to divide by zero")
     }
 }
 
-impl<U> PartialEq for Ux4<U>
+impl<U, const N: usize> Rem for UxN<U, N>
+where
+    U: From<u8> + Copy + Ord + Shl<usize, Output = U> + Shr<usize, Output = U> + BitOr<Output = U>
+    + BitAnd<Output = U>,
+    Wrapping<U>: Add<Output = Wrapping<U>> + Sub<Output = Wrapping<U>>
+{
+    type Output = Self;
+
+    /// Remainder of the euclidean division, panics if `rhs` is zero.
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.checked_rem(rhs).expect("attempt to calculate the remainder with a divisor of zero")
+    }
+}
+
+impl<U, const N: usize> PartialEq for UxN<U, N>
 where
     U: PartialEq
 {
+    /// Compares all the registers, without early exit.
     fn eq(&self, other: &Self) -> bool {
-        self.0.iter().zip(other.0.iter()).all(|(a, b)| a == b)
+        self.0.iter().zip(other.0.iter()).fold(true, |acc, (a, b)| acc & (a == b))
+    }
+}
+
+impl<U, const N: usize> Eq for UxN<U, N>
+where
+    U: Eq
+{}
+
+impl<U, const N: usize> PartialOrd for UxN<U, N>
+where
+    U: Ord
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<U, const N: usize> Ord for UxN<U, N>
+where
+    U: Ord
+{
+    /// Numerical order, comparing from the most significant register.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
     }
 }
 
 // Print formats.
 
-impl<U> fmt::Display for Ux4<U>
+impl<U, const N: usize> fmt::Display for UxN<U, N>
 where
     U: fmt::Display + Copy
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for &item in &self.0 {
-            write!(f, "{} ", item)?;
+        for i in 0..N {
+            write!(f, "{}", self.0[i])?;
+            if i + 1 < N {write!(f, " ")?;}
         }
         Ok(())
     }
 }
 
-impl<U> fmt::LowerHex for Ux4<U>
+impl<U, const N: usize> fmt::LowerHex for UxN<U, N>
 where
     U: fmt::LowerHex + Copy
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..4 {
+        for i in 0..N {
             write!(f, "{:x}", self.0[i])?;
-            if i < 3 {write!(f, " ")?;}
+            if i + 1 < N {write!(f, " ")?;}
         }
         Ok(())
     }
 }
 
-impl<U> fmt::UpperHex for Ux4<U>
+impl<U, const N: usize> fmt::UpperHex for UxN<U, N>
 where
     U: fmt::UpperHex + Copy
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..4 {
+        for i in 0..N {
             write!(f, "{:X}", self.0[i])?;
-            if i < 3 {write!(f, " ")?;}
+            if i + 1 < N {write!(f, " ")?;}
         }
         Ok(())
     }
 }
 
-impl<U> fmt::Binary for Ux4<U>
+impl<U, const N: usize> fmt::Binary for UxN<U, N>
 where
     U: fmt::Binary + Copy
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for i in 0..4 {
+        for i in 0..N {
             write!(f, "{:b}", self.0[i])?;
-            if i < 3 {write!(f, " ")?;}
+            if i + 1 < N {write!(f, " ")?;}
         }
         Ok(())
     }
 }
 
 #[cfg(test)]
+/// Tests of the multi-register states.
 pub mod test {
     use super::*;
+    use crate::utilities::bitops::urot;
+    use num::BigUint;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    /// Toy ARX permutation of a 320-bit state, shared by the tests of the Ascon-sized constructions.
+    pub fn perm_320(state: UxN<u64, 5>) -> UxN<u64, 5> {
+        let mut x = state.get();
+        for _ in 0..4 {
+            for i in 0..5 {
+                x[i] ^= x[(i + 1) % 5].wrapping_add(x[(i + 3) % 5]).rotate_left(23);
+            }
+        }
+        UxN::new(x)
+    }
+
+    fn to_biguint<U: Copy + ToLeBytes, const N: usize>(x: UxN<U, N>) -> BigUint {
+        BigUint::from_bytes_le(&x.to_le_bytes())
+    }
 
     #[test]
     fn addition() {
-        let a = Ux4::<u64>([1,0,0,0].into());
-        let b = Ux4::<u64>([1,0,0,0].into());
-        let c = Ux4::<u64>([2,0,0,0].into());
+        let a = Ux4::<u64>::new([1,0,0,0]);
+        let b = Ux4::<u64>::new([1,0,0,0]);
+        let c = Ux4::<u64>::new([2,0,0,0]);
         assert!(a + b == c);
     }
 
     #[test]
     fn addition_carry() {
-        let a = Ux4::<u8>([255,0,0,0].into());
-        let b = Ux4::<u8>([1,0,0,0].into());
-        let c = Ux4::<u8>([0,1,0,0].into());
+        let a = Ux4::<u8>::new([255,0,0,0]);
+        let b = Ux4::<u8>::new([1,0,0,0]);
+        let c = Ux4::<u8>::new([0,1,0,0]);
         assert!(a + b == c);
     }
 
+    #[test]
+    fn addition_carry_wide() {
+        let a = UxN::<u8, 5>::new([255,255,255,255,0]);
+        let b = UxN::<u8, 5>::new([1,0,0,0,0]);
+        let c = UxN::<u8, 5>::new([0,0,0,0,1]);
+        assert!(a + b == c);
+        assert!(c - b == a);
+    }
+
+    #[test]
+    fn addition_carry_through_max_limb() {
+        // 255 + 255 + incoming carry must carry out
+        let a = Ux4::<u8>::new([255,255,0,0]);
+        let b = Ux4::<u8>::new([1,255,0,0]);
+        let c = Ux4::<u8>::new([0,255,1,0]);
+        assert!(a + b == c);
+        assert!(c - b == a);
+    }
+
+    /// Random values biased towards edge cases: zero and all-ones limbs, and small values.
+    fn sample(rng: &mut StdRng) -> Ux4<u64> {
+        let mut x: Ux4<u64> = rng.gen();
+        for limb in x.0.iter_mut() {
+            match rng.gen_range(0..4) {
+                0 => *limb = 0,
+                1 => *limb = u64::MAX,
+                _ => (),
+            }
+        }
+        x >> rng.gen_range(0..256)
+    }
+
+    #[test]
+    fn arithmetic_matches_biguint() {
+        let mut rng = StdRng::seed_from_u64(34);
+        let modulus = BigUint::from(1_u8) << 256;
+        for _ in 0..1000 {
+            let (a, b) = (sample(&mut rng), sample(&mut rng));
+            let (x, y) = (to_biguint(a), to_biguint(b));
+
+            let (sum, carry) = a.overflowing_add(b);
+            assert!(to_biguint(sum) == (&x + &y) % &modulus);
+            assert!(carry == (&x + &y >= modulus));
+            assert!(a.checked_add(b).is_none() == carry);
+
+            let (difference, borrow) = a.overflowing_sub(b);
+            assert!(to_biguint(difference) == (&x + &modulus - &y) % &modulus);
+            assert!(borrow == (x < y));
+            assert!(a.checked_sub(b).is_none() == borrow);
+
+            let (product, overflow) = a.overflowing_mul(b);
+            assert!(to_biguint(product) == (&x * &y) % &modulus);
+            assert!(overflow == (&x * &y >= modulus));
+            assert!(a.checked_mul(b).is_none() == overflow);
+
+            if y != BigUint::from(0_u8) {
+                assert!(to_biguint(a / b) == &x / &y);
+                assert!(to_biguint(a % b) == &x % &y);
+            } else {
+                assert!(a.checked_div(b).is_none() && a.checked_rem(b).is_none());
+            }
+
+            assert!(a.cmp(&b) == x.cmp(&y));
+        }
+    }
+
     #[test]
     fn shl() {
-        let a = Ux4::<u8>([1,1,1,1].into());
-        let b = Ux4::<u8>([2,2,2,2].into());
+        let a = Ux4::<u8>::new([1,1,1,1]);
+        let b = Ux4::<u8>::new([2,2,2,2]);
 
-        let c = Ux4::<u8>([127,127,127,127].into());
-        let d = Ux4::<u8>([254,254,254,254].into());
+        let c = Ux4::<u8>::new([127,127,127,127]);
+        let d = Ux4::<u8>::new([254,254,254,254]);
 
-        let e = Ux4::<u8>([1,2,4,8].into());
-        let f = Ux4::<u8>([16,32,64,128].into());
+        let e = Ux4::<u8>::new([1,2,4,8]);
+        let f = Ux4::<u8>::new([16,32,64,128]);
 
         assert!((a<<1) == b);
         assert!((c<<1) == d);
@@ -336,17 +720,17 @@ pub mod test {
 
     #[test]
     fn shl_carry() {
-        let a = Ux4::<u8>([255,0,0,0].into());
-        let b = Ux4::<u8>([254,1,0,0].into());
+        let a = Ux4::<u8>::new([255,0,0,0]);
+        let b = Ux4::<u8>::new([254,1,0,0]);
 
-        let c = Ux4::<u8>([128,128,128,0].into());
-        let d = Ux4::<u8>([0,1,1,1].into());
+        let c = Ux4::<u8>::new([128,128,128,0]);
+        let d = Ux4::<u8>::new([0,1,1,1]);
 
-        let e = Ux4::<u8>([255,255,255,255].into());
-        let f = Ux4::<u8>([254,255,255,255].into());
+        let e = Ux4::<u8>::new([255,255,255,255]);
+        let f = Ux4::<u8>::new([254,255,255,255]);
 
-        let g = Ux4::<u8>([255,255,255,255].into());
-        let h = Ux4::<u8>([0,255,255,255].into());
+        let g = Ux4::<u8>::new([255,255,255,255]);
+        let h = Ux4::<u8>::new([0,255,255,255]);
 
         assert!((a<<1) == b);
         assert!((c<<1) == d);
@@ -354,12 +738,95 @@ pub mod test {
         assert!((g<<8) == h);
     }
 
+    #[test]
+    fn shift_wide() {
+        let a = UxN::<u64, 5>::new([1,0,0,0,0]);
+        let b = UxN::<u64, 5>::new([0,0,0,0,1 << 63]);
+        assert!((a << 319) == b);
+        assert!((b >> 319) == a);
+        assert!((a << 320) == UxN::zero());
+    }
+
+    #[test]
+    fn shifts_match_biguint() {
+        let modulus = BigUint::from(1_u8) << 256;
+        for _ in 0..8 {
+            let a = Ux4::<u64>::rand();
+            let x = to_biguint(a);
+            for shift in 0..=256 {
+                assert!(to_biguint(a << shift) == (&x << shift) % &modulus);
+                assert!(to_biguint(a >> shift) == &x >> shift);
+            }
+        }
+    }
+
+    #[test]
+    fn rotations_match_biguint() {
+        let modulus = BigUint::from(1_u8) << 256;
+        for _ in 0..8 {
+            let a = Ux4::<u64>::rand();
+            let x = to_biguint(a);
+            for n in 0..=256 {
+                let left = ((&x << n) | (&x >> (256 - n))) % &modulus;
+                assert!(to_biguint(a.rotate_left(n)) == left);
+                assert!(to_biguint(a.rotate_right(256 - n)) == left);
+            }
+        }
+
+        let b = Ux4::<u8>::new([0x81, 0x01, 0x00, 0x80]);
+        assert!(b.rotate_left(1) == Ux4::<u8>::new([0x03, 0x03, 0x00, 0x00]));
+        assert!(b.rotate_right(1) == Ux4::<u8>::new([0xc0, 0x00, 0x00, 0xc0]));
+
+        // Rotate trait, shifts are taken modulo the width
+        assert!(b.rotl(33) == b.rotate_left(1) && b.rotr(32) == b);
+        assert!(urot(b, 31) == b.rotate_right(1));
+        const { assert!(<Ux4<u8> as StateWidth>::BITS == 32 && <UxN<u64, 5> as StateWidth>::BITS == 320) };
+    }
+
+    #[test]
+    fn byte_round_trips() {
+        for _ in 0..16 {
+            let a = Ux4::<u64>::rand();
+            assert!(Ux4::<u64>::from_le_bytes(&a.to_le_bytes()).unwrap() == a);
+            assert!(Ux4::<u64>::from_be_bytes(&a.to_be_bytes()).unwrap() == a);
+            assert!(BigUint::from_bytes_be(&a.to_be_bytes()) == to_biguint(a));
+
+            let b = UxN::<u32, 5>::rand();
+            assert!(UxN::<u32, 5>::from_le_bytes(&b.to_le_bytes()).unwrap() == b);
+        }
+        assert!(Ux4::<u64>::from_le_bytes(&[0; 31]).is_err());
+        assert!(Ux4::<u16>::from_be_bytes(&[0; 9]).is_err());
+    }
+
+    #[test]
+    fn hex_parsing() {
+        let a: Ux4<u64> = "0x0123456789abcdef00000000000000010000000000000002fedcba9876543210".parse().unwrap();
+        assert!(a == Ux4::<u64>::new([0xfedcba9876543210, 2, 1, 0x0123456789abcdef]));
+
+        let b: Ux4<u8> = "1ff".parse().unwrap();
+        assert!(b == Ux4::<u8>::new([0xff, 0x01, 0, 0]));
+
+        for _ in 0..16 {
+            let c = Ux4::<u64>::rand();
+            let hex: String = c.to_be_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+            assert!(hex.parse::<Ux4<u64>>().unwrap() == c);
+        }
+
+        assert!("".parse::<Ux4<u8>>().is_err());
+        assert!("0x123456789".parse::<Ux4<u8>>().is_err());
+        assert!("12g4".parse::<Ux4<u8>>().is_err());
+        assert!("+f".parse::<Ux4<u8>>().is_err());
+        assert!("0x+1".parse::<Ux4<u8>>().is_err());
+        assert!("aéa".parse::<Ux4<u8>>().is_err());
+        assert!("é".parse::<Ux4<u8>>().is_err());
+    }
+
     #[test]
     fn print_int() {
         let [a,b,c,d] = [0xABCD0123, 0xBCDE1234, 0xCDEF2345, 0xDEF83456];
         let test_string = format!("{} {} {} {}", a, b, c, d);
 
-        let ux4_instance = Ux4::<u64>([a, b, c, d]);
+        let ux4_instance = Ux4::<u64>::new([a, b, c, d]);
         let formatted_string = format!("{}", ux4_instance);
         assert!(formatted_string == test_string);
     }
@@ -369,7 +836,7 @@ pub mod test {
         let [a,b,c,d] = [0xABCD0123, 0xBCDE1234, 0xCDEF2345, 0xDEF83456];
         let test_string = format!("{:x} {:x} {:x} {:x}", a, b, c, d);
 
-        let ux4_instance = Ux4::<u64>([a, b, c, d]);
+        let ux4_instance = Ux4::<u64>::new([a, b, c, d]);
         let formatted_string = format!("{:x}", ux4_instance);
         assert!(formatted_string == test_string);
     }
@@ -379,7 +846,7 @@ pub mod test {
         let [a,b,c,d] = [0xABCD0123, 0xBCDE1234, 0xCDEF2345, 0xDEF83456];
         let test_string = format!("{:X} {:X} {:X} {:X}", a, b, c, d);
 
-        let ux4_instance = Ux4::<u64>([a, b, c, d]);
+        let ux4_instance = Ux4::<u64>::new([a, b, c, d]);
         let formatted_string = format!("{:X}", ux4_instance);
         assert!(formatted_string == test_string);
     }
@@ -389,8 +856,15 @@ pub mod test {
         let [a,b,c,d] = [0xABCD0123, 0xBCDE1234, 0xCDEF2345, 0xDEF83456];
         let test_string = format!("{:b} {:b} {:b} {:b}", a, b, c, d);
 
-        let ux4_instance = Ux4::<u64>([a, b, c, d]);
+        let ux4_instance = Ux4::<u64>::new([a, b, c, d]);
         let formatted_string = format!("{:b}", ux4_instance);
         assert!(formatted_string == test_string);
     }
+
+    #[test]
+    fn print_wide() {
+        let state = UxN::<u32, 6>::new([1, 2, 3, 4, 5, 0xff]);
+        assert!(format!("{}", state) == "1 2 3 4 5 255");
+        assert!(format!("{:x}", state) == "1 2 3 4 5 ff");
+    }
 }
\ No newline at end of file
@@ -0,0 +1,134 @@
+#![warn(missing_docs)]
+#![allow(non_snake_case)]
+
+//! Module implementing the secure wiping of secret material.
+//!
+//! Keys, seeds and inner states are overwritten with zeros using volatile writes,
+//! followed by a compiler fence, so that the compiler cannot elide the writes
+//! as dead stores when the memory is about to be released.
+//! Structures holding secrets call `zeroize` from their `Drop` implementation.
+
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use crate::utilities::ustates::UxN;
+
+/// Trait for values that can be securely overwritten with zeros.
+pub trait Zeroize {
+    /// Overwrites the value with zeros.
+    fn zeroize(&mut self);
+}
+
+macro_rules! impl_zeroize {
+    ($($t:ty),*) => {$(
+        impl Zeroize for $t {
+            fn zeroize(&mut self) {
+                // SAFETY: `self` is a valid, aligned and exclusive reference.
+                unsafe { ptr::write_volatile(self, 0) };
+                compiler_fence(Ordering::SeqCst);
+            }
+        }
+    )*};
+}
+
+impl_zeroize!(u8, u16, u32, u64, u128, usize);
+
+impl<U, const N: usize> Zeroize for UxN<U, N>
+where
+    U: Zeroize
+{
+    fn zeroize(&mut self) {
+        self.0.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl<T> Zeroize for [T]
+where
+    T: Zeroize
+{
+    fn zeroize(&mut self) {
+        self.iter_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+impl<T> Zeroize for Vec<T>
+where
+    T: Zeroize
+{
+    /// Wipes the elements and the spare capacity, then empties the vector (the allocation is kept).
+    fn zeroize(&mut self) {
+        self.as_mut_slice().zeroize();
+        self.clear();
+        // Elements removed by `truncate`, `drain` or `pop` are left behind the length
+        for slot in self.spare_capacity_mut() {
+            let bytes = slot.as_mut_ptr().cast::<u8>();
+            for i in 0..mem::size_of::<T>() {
+                // SAFETY: the slot belongs to the allocation of the vector, and `MaybeUninit` accepts any byte.
+                unsafe { ptr::write_volatile(bytes.add(i), 0) };
+            }
+        }
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl<T> Zeroize for Option<T>
+where
+    T: Zeroize
+{
+    /// Wipes the content, then sets the option to `None`.
+    fn zeroize(&mut self) {
+        if let Some(value) = self {
+            value.zeroize();
+        }
+        *self = None;
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utilities::ustates::Ux4;
+
+    #[test]
+    fn wipes_values() {
+        let mut x = 0xdeadbeef_u32;
+        x.zeroize();
+        assert!(x == 0);
+
+        let mut state = Ux4::<u64>::new([1, 2, 3, 4]);
+        state.zeroize();
+        assert!(state == Ux4::zero());
+
+        let mut key = Some(Ux4::<u64>::new([5, 6, 7, 8]));
+        key.zeroize();
+        assert!(key.is_none());
+    }
+
+    #[test]
+    fn wipes_vector_buffer() {
+        let mut keys = vec![0x0123456789abcdef_u64; 8];
+        let buffer = keys.as_ptr();
+        keys.zeroize();
+        assert!(keys.is_empty());
+
+        // The allocation is kept by `clear`, so the old buffer can still be inspected
+        for i in 0..8 {
+            assert!(unsafe { ptr::read(buffer.add(i)) } == 0);
+        }
+    }
+
+    #[test]
+    fn wipes_spare_capacity() {
+        let mut keys = vec![0x0123456789abcdef_u64; 8];
+        keys.drain(4..);
+        keys.zeroize();
+        assert!(keys.capacity() >= 8);
+
+        // The drained elements sit in the spare capacity of the vector
+        for slot in keys.spare_capacity_mut() {
+            assert!(unsafe { slot.assume_init_read() } == 0);
+        }
+    }
+}